@@ -0,0 +1,361 @@
+use crate::{
+    database::{to_typed_document, Database},
+    document::TypedCouchDocument,
+    error::CouchResult,
+    types::document::DocumentCreatedResult,
+};
+use serde_json::Value;
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+/// A database with an in-memory, read-through cache for `get` by id; intended for reference data that
+/// rarely changes. The cache is bounded: when it is full, the least recently used document is evicted.
+///
+/// Clones share the same cache. The writes of this wrapper (`save`, `upsert`, `patch`, `move_doc`,
+/// `remove` and `bulk_docs`) invalidate the documents they write, so a `get` after a write returns the
+/// new revision. Other operations are available on the underlying database through
+/// [`CachedDatabase::inner`]; writes made that way bypass the cache.
+///
+/// Staleness: by default a cached document is returned without contacting `CouchDB`, so changes made
+/// after it was cached by other processes, or through [`CachedDatabase::inner`], are not seen until the
+/// document is evicted or [`CachedDatabase::invalidate`]d.
+/// With [`CachedDatabase::validate_with_etag`] every cache hit is validated with a `HEAD` request,
+/// comparing the current `_rev` (the `ETag`) with the cached one; this still saves transferring and
+/// parsing the document, and never returns an outdated revision.
+///
+/// Usage:
+/// ```
+/// use couch_rs::cached::CachedDatabase;
+/// use couch_rs::error::CouchResult;
+/// use serde_json::Value;
+///
+/// #[tokio::main]
+/// async fn main() -> CouchResult<()> {
+///     let client = couch_rs::Client::new_local_test()?;
+///     let db = CachedDatabase::new(client.db("countries").await?, 1000).validate_with_etag(true);
+///     let country: Value = db.get("nl").await?;
+///     // served from the cache, after checking the revision is still current
+///     let country_again: Value = db.get("nl").await?;
+///     assert_eq!(country, country_again);
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct CachedDatabase {
+    db: Database,
+    cache: Arc<Mutex<DocumentCache>>,
+    validate: bool,
+}
+
+impl CachedDatabase {
+    /// Wraps the database with a cache of at most `capacity` documents.
+    #[must_use]
+    pub fn new(db: Database, capacity: usize) -> CachedDatabase {
+        CachedDatabase {
+            db,
+            cache: Arc::new(Mutex::new(DocumentCache::new(capacity))),
+            validate: false,
+        }
+    }
+
+    /// Validates every cache hit with a `HEAD` request, to make sure the cached revision is still current.
+    #[must_use]
+    pub fn validate_with_etag(mut self, validate: bool) -> Self {
+        self.validate = validate;
+        self
+    }
+
+    /// The underlying database, for the operations that are not cached. Writes through it do *not*
+    /// invalidate the cache; call [`CachedDatabase::invalidate`] for the documents they change.
+    #[must_use]
+    pub fn inner(&self) -> &Database {
+        &self.db
+    }
+
+    /// Gets one document, from the cache when possible.
+    /// See [`crate::database::Database::get`] for details.
+    pub async fn get<T: TypedCouchDocument>(&self, id: &str) -> CouchResult<T> {
+        let cached = self.cache().get(id);
+        if let Some((rev, doc)) = cached {
+            if !self.validate || self.db.head_rev(id).await?.as_deref() == Some(rev.as_str()) {
+                return to_typed_document(doc);
+            }
+            self.invalidate(id);
+        }
+
+        // a write that invalidates the cache while the document is fetched makes it outdated
+        let generation = self.cache().generation;
+        let doc: Value = self.db.get(id).await?;
+        self.cache()
+            .insert_unless_invalidated(generation, id, doc.get_rev().into_owned(), doc.clone());
+        to_typed_document(doc)
+    }
+
+    /// Saves a document, and removes it from the cache.
+    /// See [`crate::database::Database::save`] for details.
+    pub async fn save<T: TypedCouchDocument>(&self, doc: &mut T) -> DocumentCreatedResult {
+        let result = self.db.save(doc).await;
+        self.invalidate(&doc.get_id());
+        result
+    }
+
+    /// Creates or updates a document, and removes it from the cache.
+    /// See [`crate::database::Database::upsert`] for details.
+    pub async fn upsert<T: TypedCouchDocument>(&self, doc: &mut T) -> DocumentCreatedResult {
+        let result = self.db.upsert(doc).await;
+        self.invalidate(&doc.get_id());
+        result
+    }
+
+    /// Updates a document in place, and removes it from the cache.
+    /// See [`crate::database::Database::patch`] for details.
+    pub async fn patch<F: Fn(&mut Value)>(&self, id: &str, patch: F) -> DocumentCreatedResult {
+        let result = self.db.patch(id, patch).await;
+        self.invalidate(id);
+        result
+    }
+
+    /// Moves a document to a new id, and removes both ids from the cache.
+    /// See [`crate::database::Database::move_doc`] for details.
+    pub async fn move_doc(&self, old_id: &str, new_id: &str) -> DocumentCreatedResult {
+        let result = self.db.move_doc(old_id, new_id).await;
+        self.invalidate(old_id);
+        self.invalidate(new_id);
+        result
+    }
+
+    /// Removes a document from the database and from the cache.
+    /// See [`crate::database::Database::remove`] for details.
+    pub async fn remove<T: TypedCouchDocument>(&self, doc: &T) -> bool {
+        let removed = self.db.remove(doc).await;
+        self.invalidate(&doc.get_id());
+        removed
+    }
+
+    /// Writes documents in bulk, and removes them from the cache.
+    /// See [`crate::database::Database::bulk_docs`] for details.
+    pub async fn bulk_docs<T: TypedCouchDocument>(&self, docs: &mut [T]) -> CouchResult<Vec<DocumentCreatedResult>> {
+        let result = self.db.bulk_docs(docs).await;
+        let mut cache = self.cache();
+        for doc in docs.iter() {
+            cache.invalidate(&doc.get_id());
+        }
+        result
+    }
+
+    /// Removes a document from the cache, so the next `get` fetches it from `CouchDB`.
+    pub fn invalidate(&self, id: &str) {
+        self.cache().invalidate(id);
+    }
+
+    /// Removes all documents from the cache.
+    pub fn clear(&self) {
+        self.cache().clear();
+    }
+
+    /// Number of documents in the cache.
+    #[must_use]
+    pub fn cached_len(&self) -> usize {
+        self.cache().len()
+    }
+
+    fn cache(&self) -> MutexGuard<'_, DocumentCache> {
+        // the cache is never left in an inconsistent state, so a poisoned lock can safely be used
+        self.cache.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Bounded least-recently-used cache of documents, keyed by id.
+#[derive(Debug)]
+struct DocumentCache {
+    capacity: usize,
+    tick: u64,
+    entries: HashMap<String, CacheEntry>,
+    /// The ids by the tick they were last used at, oldest first, to find the entry to evict.
+    recency: BTreeMap<u64, String>,
+    /// Incremented on every invalidation, to detect documents that were fetched before it.
+    generation: u64,
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    rev: String,
+    doc: Value,
+    last_used: u64,
+}
+
+impl DocumentCache {
+    fn new(capacity: usize) -> DocumentCache {
+        DocumentCache {
+            capacity,
+            tick: 0,
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+            generation: 0,
+        }
+    }
+
+    fn get(&mut self, id: &str) -> Option<(String, Value)> {
+        self.tick += 1;
+        let entry = self.entries.get_mut(id)?;
+        let id = self.recency.remove(&entry.last_used)?;
+        entry.last_used = self.tick;
+        self.recency.insert(self.tick, id);
+        Some((entry.rev.clone(), entry.doc.clone()))
+    }
+
+    fn insert(&mut self, id: &str, rev: String, doc: Value) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.remove(id);
+        if self.entries.len() >= self.capacity {
+            if let Some((_, oldest)) = self.recency.pop_first() {
+                self.entries.remove(&oldest);
+            }
+        }
+
+        self.tick += 1;
+        self.recency.insert(self.tick, s!(id));
+        self.entries.insert(
+            s!(id),
+            CacheEntry {
+                rev,
+                doc,
+                last_used: self.tick,
+            },
+        );
+    }
+
+    /// Inserts a document that was fetched when the cache was at `generation`, unless the cache was
+    /// invalidated since; the document may be outdated by the write that caused the invalidation.
+    fn insert_unless_invalidated(&mut self, generation: u64, id: &str, rev: String, doc: Value) {
+        if self.generation == generation {
+            self.insert(id, rev, doc);
+        }
+    }
+
+    fn remove(&mut self, id: &str) {
+        if let Some(entry) = self.entries.remove(id) {
+            self.recency.remove(&entry.last_used);
+        }
+    }
+
+    fn invalidate(&mut self, id: &str) {
+        self.generation += 1;
+        self.remove(id);
+    }
+
+    fn clear(&mut self) {
+        self.generation += 1;
+        self.entries.clear();
+        self.recency.clear();
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{mock_server::mock_server, Client};
+    use serde_json::json;
+
+    #[test]
+    fn test_cache_hit_and_miss() {
+        let mut cache = DocumentCache::new(2);
+        assert!(cache.get("1").is_none());
+
+        cache.insert("1", s!("1-a"), json!({"_id": "1"}));
+        assert_eq!(cache.get("1"), Some((s!("1-a"), json!({"_id": "1"}))));
+
+        cache.insert("1", s!("2-b"), json!({"_id": "1", "updated": true}));
+        assert_eq!(cache.get("1").unwrap().0, "2-b");
+        assert_eq!(cache.len(), 1);
+
+        cache.remove("1");
+        assert!(cache.get("1").is_none());
+    }
+
+    #[test]
+    fn test_cache_evicts_least_recently_used() {
+        let mut cache = DocumentCache::new(2);
+        cache.insert("1", s!("1-a"), json!({}));
+        cache.insert("2", s!("1-b"), json!({}));
+        // use "1", so "2" is the least recently used
+        cache.get("1");
+        cache.insert("3", s!("1-c"), json!({}));
+
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get("1").is_some());
+        assert!(cache.get("2").is_none());
+        assert!(cache.get("3").is_some());
+        assert_eq!(cache.recency.len(), 2);
+
+        // replacing an entry does not evict another one
+        cache.insert("3", s!("2-c"), json!({}));
+        assert!(cache.get("1").is_some());
+        assert_eq!(cache.recency.len(), 2);
+    }
+
+    #[test]
+    fn test_cache_skips_documents_fetched_before_an_invalidation() {
+        let mut cache = DocumentCache::new(2);
+        let generation = cache.generation;
+        // a concurrent write invalidates the document while it is being fetched
+        cache.invalidate("1");
+        cache.insert_unless_invalidated(generation, "1", s!("1-a"), json!({}));
+        assert!(cache.get("1").is_none());
+
+        let generation = cache.generation;
+        cache.insert_unless_invalidated(generation, "1", s!("2-b"), json!({}));
+        assert_eq!(cache.get("1").unwrap().0, "2-b");
+    }
+
+    #[test]
+    fn test_cache_without_capacity_stores_nothing() {
+        let mut cache = DocumentCache::new(0);
+        cache.insert("1", s!("1-a"), json!({}));
+        assert_eq!(cache.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_get_is_served_from_cache() {
+        let (uri, server) = mock_server(200, r#"{"_id":"1","_rev":"1-a","name":"one"}"#).await;
+        let client = Client::new_no_auth(&uri).unwrap();
+        let db = CachedDatabase::new(Database::new(s!("db"), client), 10);
+
+        let doc: Value = db.get("1").await.unwrap();
+        assert_eq!(doc["name"], "one");
+        assert!(server.await.unwrap().starts_with("GET /db/1 "));
+
+        // the mock server is gone, so this can only be served from the cache
+        let cached: Value = db.get("1").await.unwrap();
+        assert_eq!(cached, doc);
+        assert_eq!(db.cached_len(), 1);
+
+        db.invalidate("1");
+        assert!(db.get::<Value>("1").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_save_invalidates_the_cache() {
+        let (uri, server) = mock_server(201, r#"{"ok":true,"id":"1","rev":"2-b"}"#).await;
+        let client = Client::new_no_auth(&uri).unwrap();
+        let db = CachedDatabase::new(Database::new(s!("db"), client), 10);
+        db.cache()
+            .insert("1", s!("1-a"), json!({"_id": "1", "_rev": "1-a", "name": "one"}));
+
+        let mut doc = json!({"_id": "1", "_rev": "1-a", "name": "uno"});
+        db.save(&mut doc).await.unwrap();
+        assert!(server.await.unwrap().starts_with("PUT /db/1 "));
+        assert_eq!(db.cached_len(), 0);
+
+        // the old revision is no longer served; the mock server is gone, so the get has to fail
+        assert!(db.get::<Value>("1").await.is_err());
+    }
+}
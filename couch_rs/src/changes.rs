@@ -5,12 +5,13 @@ use crate::{
 };
 use futures_core::{Future, Stream};
 use futures_util::{ready, FutureExt, StreamExt, TryStreamExt};
-use reqwest::{Method, Response, StatusCode};
+use reqwest::{Response, StatusCode};
 use std::{
     collections::HashMap,
     io,
     pin::Pin,
     task::{Context, Poll},
+    time::Duration,
 };
 use tokio::io::AsyncBufReadExt;
 use tokio_stream::wrappers::LinesStream;
@@ -22,22 +23,30 @@ use tokio_util::io::StreamReader;
 /// [1]: https://docs.couchdb.org/en/stable/api/database/changes.html
 const COUCH_MAX_TIMEOUT: usize = 60000;
 
+/// Delay before the first reconnect attempt; doubled after every failed attempt, up to the max.
+const RECONNECT_INITIAL_BACKOFF: Duration = Duration::from_millis(250);
+const RECONNECT_MAX_BACKOFF: Duration = Duration::from_secs(30);
+
 /// The stream for the `_changes` endpoint.
 ///
 /// This is returned from [`Database::changes`].
 pub struct ChangesStream {
     last_seq: Option<serde_json::Value>,
+    pending: Option<u64>,
     client: Client,
     database: String,
     state: ChangesStreamState,
     params: HashMap<String, String>,
     infinite: bool,
+    reconnect: bool,
+    backoff: Duration,
 }
 
 enum ChangesStreamState {
     Idle,
-    Requesting(Pin<Box<dyn Future<Output = CouchResult<Response>>>>),
-    Reading(Pin<Box<dyn Stream<Item = io::Result<String>>>>),
+    Requesting(Pin<Box<dyn Future<Output = CouchResult<Response>> + Send>>),
+    Reading(Pin<Box<dyn Stream<Item = io::Result<String>> + Send>>),
+    Reconnecting(Pin<Box<tokio::time::Sleep>>),
 }
 
 impl ChangesStream {
@@ -63,7 +72,10 @@ impl ChangesStream {
             params,
             state: ChangesStreamState::Idle,
             infinite: false,
+            reconnect: false,
+            backoff: RECONNECT_INITIAL_BACKOFF,
             last_seq,
+            pending: None,
         }
     }
 
@@ -86,20 +98,121 @@ impl ChangesStream {
         self.params.insert("timeout".to_string(), timeout);
     }
 
+    /// Reconnect when the connection fails, e.g. while `CouchDB` restarts, instead of returning the error.
+    /// The feed is requested again from the last retrieved seq, after a delay that grows from 250ms up to
+    /// 30s while the attempts keep failing. Errors that will not go away by retrying, like a missing
+    /// database or invalid credentials (a 4xx status), are still returned.
+    /// Typically used in combination with infinite mode, for long-lived consumers.
+    pub fn set_reconnect(&mut self, reconnect: bool) {
+        self.reconnect = reconnect;
+    }
+
+    /// Let `CouchDB` send an empty line every `ms` milliseconds while there are no changes, to keep
+    /// the connection alive across proxies. The empty lines are skipped by the stream.
+    /// `CouchDB` keeps a feed with a heartbeat open, regardless of the timeout.
+    pub fn set_heartbeat(&mut self, ms: usize) {
+        self.params.insert("heartbeat".to_string(), ms.to_string());
+    }
+
+    /// Only return a seq on every n-th change, which reduces the load on clustered `CouchDB`.
+    /// The other changes carry a `null` seq; the stream resumes from the last non-null seq.
+    pub fn set_seq_interval(&mut self, interval: u32) {
+        self.params.insert("seq_interval".to_string(), interval.to_string());
+    }
+
+    /// Only return the changes that pass the filter function `name` of the design document `ddoc`, e.g.
+    /// installed with `DesignDocument::filter`. The function receives each changed document and the
+    /// request, and returns whether the change should be included:
+    /// `function (doc, req) { return doc.type === 'important'; }`
+    ///
+    /// Usage:
+    /// ```
+    /// use couch_rs::error::CouchResult;
+    /// use couch_rs::types::design::DesignDocument;
+    /// use futures_util::StreamExt;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> CouchResult<()> {
+    ///     let client = couch_rs::Client::new_local_test()?;
+    ///     let db = client.db("events").await?;
+    ///     let design = DesignDocument::default().filter("important", "function (doc, req) { return doc.type === 'important'; }");
+    ///     db.create_design("filters", design).await?;
+    ///
+    ///     let mut changes = db.changes(None).with_filter("filters", "important");
+    ///     while let Some(change) = changes.next().await {
+    ///         println!("{}", change?.id);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    #[must_use]
+    pub fn with_filter(mut self, ddoc: &str, name: &str) -> Self {
+        self.params.insert("filter".to_string(), format!("{ddoc}/{name}"));
+        self
+    }
+
     /// Get the last retrieved seq.
     pub fn last_seq(&self) -> &Option<serde_json::Value> {
         &self.last_seq
     }
 
+    /// Get the number of changes that were still pending when the feed last finished;
+    /// `None` until the feed finished, or when `CouchDB` does not report it.
+    pub fn pending(&self) -> Option<u64> {
+        self.pending
+    }
+
+    /// Collects up to `n` changes, or fewer when the feed finishes first (in non-infinite mode).
+    /// Returns the first error that occurs.
+    pub async fn collect_n(&mut self, n: usize) -> CouchResult<Vec<ChangeEvent>> {
+        let mut changes = Vec::with_capacity(n);
+        while changes.len() < n {
+            match self.next().await {
+                Some(change) => changes.push(change?),
+                None => break,
+            }
+        }
+        Ok(changes)
+    }
+
     /// Whether this stream is running in infinite mode.
     pub fn infinite(&self) -> bool {
         self.infinite
     }
+
+    /// When reconnecting is enabled and the error is recoverable, schedules a new request and returns
+    /// the state to wait in; otherwise the error has to be returned.
+    fn reconnect_after(&mut self, err: &CouchError) -> Option<ChangesStreamState> {
+        let fatal = err
+            .status()
+            .is_some_and(|status| status.is_client_error() && !is_retryable_client_error(status));
+        if !self.reconnect || fatal {
+            return None;
+        }
+
+        let delay = self.backoff;
+        self.backoff = (self.backoff * 2).min(RECONNECT_MAX_BACKOFF);
+        Some(ChangesStreamState::Reconnecting(Box::pin(tokio::time::sleep(delay))))
+    }
+}
+
+/// A request timeout or rate limit is temporary, unlike the other 4xx statuses.
+fn is_retryable_client_error(status: StatusCode) -> bool {
+    status == StatusCode::REQUEST_TIMEOUT || status == StatusCode::TOO_MANY_REQUESTS
+}
+
+/// Formats a seq for the `since` parameter: a string seq is passed as is, without the surrounding
+/// quotes of its JSON form; any other seq (e.g. a number) is passed as JSON.
+fn since_param(seq: &serde_json::Value) -> String {
+    match seq {
+        serde_json::Value::String(seq) => seq.clone(),
+        seq => seq.to_string(),
+    }
 }
 
 async fn get_changes(client: Client, database: String, params: HashMap<String, String>) -> CouchResult<Response> {
     let path = format!("{database}/_changes");
-    let res = client.req(Method::GET, &path, Some(&params)).send().await?;
+    let res = client.get(&path, Some(&params)).send().await?;
     Ok(res)
 }
 
@@ -111,61 +224,71 @@ impl Stream for ChangesStream {
                 ChangesStreamState::Idle => {
                     let mut params = self.params.clone();
                     if let Some(seq) = &self.last_seq {
-                        params.insert("since".to_string(), seq.to_string());
+                        params.insert("since".to_string(), since_param(seq));
                     }
                     let fut = get_changes(self.client.clone(), self.database.clone(), params);
                     ChangesStreamState::Requesting(Box::pin(fut))
                 }
                 ChangesStreamState::Requesting(ref mut fut) => match ready!(fut.poll_unpin(cx)) {
-                    Err(err) => return Poll::Ready(Some(Err(err))),
+                    Err(err) => match self.reconnect_after(&err) {
+                        Some(state) => state,
+                        None => return Poll::Ready(Some(Err(err))),
+                    },
                     Ok(res) => {
                         if res.status().is_success() {
-                            let stream = res
-                                .bytes_stream()
-                                .map_err(|err| io::Error::new(io::ErrorKind::Other, err));
+                            let stream = res.bytes_stream().map_err(io::Error::other);
                             let reader = StreamReader::new(stream);
                             let lines = Box::pin(LinesStream::new(reader.lines()));
                             ChangesStreamState::Reading(lines)
                         } else {
-                            return Poll::Ready(Some(Err(CouchError::new(
-                                res.status().canonical_reason().unwrap().to_string(),
-                                res.status(),
-                            ))));
+                            let err =
+                                CouchError::new(res.status().canonical_reason().unwrap().to_string(), res.status());
+                            match self.reconnect_after(&err) {
+                                Some(state) => state,
+                                None => return Poll::Ready(Some(Err(err))),
+                            }
                         }
                     }
                 },
+                ChangesStreamState::Reconnecting(ref mut sleep) => {
+                    ready!(sleep.as_mut().poll(cx));
+                    ChangesStreamState::Idle
+                }
                 ChangesStreamState::Reading(ref mut lines) => {
                     let line = ready!(lines.poll_next_unpin(cx));
                     match line {
                         None => ChangesStreamState::Idle,
                         Some(Err(err)) => {
                             let inner = err.get_ref().and_then(|err| err.downcast_ref::<reqwest::Error>());
-                            match inner {
+                            let err = match inner {
                                 Some(reqwest_err) if reqwest_err.is_timeout() && self.infinite => {
-                                    ChangesStreamState::Idle
-                                }
-                                Some(reqwest_err) => {
-                                    return Poll::Ready(Some(Err(CouchError::new(
-                                        reqwest_err.to_string(),
-                                        reqwest_err.status().unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
-                                    ))));
-                                }
-                                _ => {
-                                    return Poll::Ready(Some(Err(CouchError::new(
-                                        format!("{err}"),
-                                        StatusCode::from_u16(500).unwrap(),
-                                    ))));
+                                    self.state = ChangesStreamState::Idle;
+                                    continue;
                                 }
+                                Some(reqwest_err) => CouchError::new(
+                                    reqwest_err.to_string(),
+                                    reqwest_err.status().unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+                                ),
+                                _ => CouchError::new(format!("{err}"), StatusCode::from_u16(500).unwrap()),
+                            };
+                            match self.reconnect_after(&err) {
+                                Some(state) => state,
+                                None => return Poll::Ready(Some(Err(err))),
                             }
                         }
                         Some(Ok(line)) if line.is_empty() => continue,
                         Some(Ok(line)) => match serde_json::from_str::<Event>(&line) {
                             Ok(Event::Change(event)) => {
-                                self.last_seq = Some(event.seq.clone());
+                                self.backoff = RECONNECT_INITIAL_BACKOFF;
+                                // with a seq_interval, most events carry a null seq
+                                if !event.seq.is_null() {
+                                    self.last_seq = Some(event.seq.clone());
+                                }
                                 return Poll::Ready(Some(Ok(event)));
                             }
                             Ok(Event::Finished(event)) => {
                                 self.last_seq = Some(event.last_seq.clone());
+                                self.pending = event.pending;
                                 if !self.infinite {
                                     return Poll::Ready(None);
                                 }
@@ -182,12 +305,133 @@ impl Stream for ChangesStream {
     }
 }
 
+#[cfg(test)]
+mod unit_tests {
+    use super::{since_param, ChangesStream};
+    use crate::{client::Client, mock_server::mock_server};
+    use futures_util::StreamExt;
+    use reqwest::StatusCode;
+    use serde_json::json;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+
+    #[test]
+    fn test_changes_stream_is_send() {
+        fn assert_send<T: Send>() {}
+        assert_send::<ChangesStream>();
+    }
+
+    #[test]
+    fn test_string_seq_has_no_quotes() {
+        let seq = json!("23-g1AAAAJTeJzLYWBgYMpgTmHgzcvPy09JdcjLz8gvLskBCScyJNn___8_K5EBh6I8FiDJ0ACk_kPVZgEA");
+        assert_eq!(
+            since_param(&seq),
+            "23-g1AAAAJTeJzLYWBgYMpgTmHgzcvPy09JdcjLz8gvLskBCScyJNn___8_K5EBh6I8FiDJ0ACk_kPVZgEA"
+        );
+        assert_eq!(since_param(&json!("now")), "now");
+    }
+
+    #[tokio::test]
+    async fn test_changes_with_filter() {
+        let (uri, server) = mock_server(
+            200,
+            "{\"seq\":\"1-a\",\"id\":\"1\",\"changes\":[{\"rev\":\"1-a\"}]}\n{\"last_seq\":\"1-a\",\"pending\":0}\n",
+        )
+        .await;
+        let client = Client::new_no_auth(&uri).unwrap();
+
+        let mut changes = ChangesStream::new(client, "testdb".to_string(), None).with_filter("filters", "important");
+        assert_eq!(changes.next().await.unwrap().unwrap().id, "1");
+        assert!(changes.next().await.is_none());
+        let request = server.await.unwrap();
+        assert!(request.starts_with("GET /testdb/_changes?"));
+        assert!(request.lines().next().unwrap().contains("filter=filters%2Fimportant"));
+    }
+
+    /// Reads a request up to the end of its headers, and returns it.
+    async fn read_request(socket: &mut TcpStream) -> String {
+        let mut request = vec![];
+        let mut buf = [0; 1024];
+        while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+            let n = socket.read(&mut buf).await.unwrap();
+            request.extend_from_slice(&buf[..n]);
+        }
+        String::from_utf8_lossy(&request).to_string()
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_after_dropped_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let uri = format!("http://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            // the first connection drops in the middle of the feed, after one change
+            let (mut socket, _) = listener.accept().await.unwrap();
+            read_request(&mut socket).await;
+            let line = "{\"seq\":\"1-a\",\"id\":\"1\",\"changes\":[{\"rev\":\"1-a\"}]}\n";
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nTransfer-Encoding: chunked\r\n\r\n{:x}\r\n{line}\r\n",
+                line.len()
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+            drop(socket);
+
+            // the second connection resumes from the last seq
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let request = read_request(&mut socket).await;
+            let body = "{\"seq\":\"2-b\",\"id\":\"2\",\"changes\":[{\"rev\":\"1-b\"}]}\n{\"last_seq\":\"2-b\",\"pending\":0}\n";
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+                body.len()
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+            request
+        });
+
+        let client = Client::new_no_auth(&uri).unwrap();
+        let mut changes = ChangesStream::new(client, "testdb".to_string(), None);
+        changes.set_reconnect(true);
+        assert_eq!(changes.next().await.unwrap().unwrap().id, "1");
+        assert_eq!(changes.next().await.unwrap().unwrap().id, "2");
+        assert!(changes.next().await.is_none());
+
+        let request = server.await.unwrap();
+        assert!(request.lines().next().unwrap().contains("since=1-a"));
+    }
+
+    #[tokio::test]
+    async fn test_no_reconnect_after_fatal_error() {
+        let (uri, _server) = mock_server(
+            401,
+            r#"{"error":"unauthorized","reason":"Name or password is incorrect."}"#,
+        )
+        .await;
+        let client = Client::new_no_auth(&uri).unwrap();
+
+        let mut changes = ChangesStream::new(client, "testdb".to_string(), None);
+        changes.set_reconnect(true);
+        let err = changes.next().await.unwrap().unwrap_err();
+        assert_eq!(err.status(), Some(StatusCode::UNAUTHORIZED));
+    }
+
+    #[test]
+    fn test_other_seqs_are_passed_as_json() {
+        assert_eq!(since_param(&json!(42)), "42");
+        assert_eq!(since_param(&json!([1, "abc"])), r#"[1,"abc"]"#);
+    }
+}
+
 #[cfg(feature = "integration-tests")]
 #[cfg(test)]
 mod tests {
+    use super::ChangesStream;
     use crate::client::Client;
+    use crate::types::design::DesignDocument;
     use futures_util::StreamExt;
     use serde_json::{json, Value};
+    use std::collections::HashMap;
+
     #[tokio::test]
     async fn should_get_changes() {
         let client = Client::new_local_test().unwrap();
@@ -220,4 +464,157 @@ mod tests {
         assert!(collected_changes.len() == 10);
         t.await.unwrap();
     }
+
+    #[tokio::test]
+    async fn should_resume_changes_from_a_string_seq() {
+        let client = Client::new_local_test().unwrap();
+        let db = client.db("should_resume_changes_from_a_string_seq").await.unwrap();
+        let mut docs: Vec<Value> = (0..10).map(|idx| json!({ "_id": format!("test_{}", idx) })).collect();
+        db.bulk_docs(&mut docs).await.expect("should insert 10 documents");
+
+        let mut changes = db.changes(None);
+        changes.set_seq_interval(3);
+        let mut first_half = vec![];
+        while let Some(change) = changes.next().await {
+            first_half.push(change.unwrap().id);
+            if first_half.len() == 6 {
+                break;
+            }
+        }
+
+        // resume from the stored seq, as a plain string
+        let stored_seq = changes.last_seq().clone().unwrap();
+        let stored_seq = stored_seq.as_str().expect("clustered seqs are strings").to_string();
+        let mut changes = db.changes(Some(Value::String(stored_seq)));
+        let mut second_half = vec![];
+        while let Some(change) = changes.next().await {
+            second_half.push(change.unwrap().id);
+        }
+
+        let mut all: Vec<String> = first_half.into_iter().chain(second_half).collect();
+        all.sort();
+        all.dedup();
+        assert_eq!(all.len(), 10);
+        client
+            .destroy_db("should_resume_changes_from_a_string_seq")
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_stay_open_across_an_idle_gap_with_heartbeat() {
+        let client = Client::new_local_test().unwrap();
+        let db = client.db("should_stay_open_across_an_idle_gap").await.unwrap();
+        let mut changes = db.changes(None);
+        changes.set_infinite(true);
+        changes.set_heartbeat(100);
+
+        let t = tokio::spawn({
+            let db = db.clone();
+            async move {
+                // stay idle for a number of heartbeats
+                tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+                db.create(&mut json!({"_id": "after_gap"}))
+                    .await
+                    .expect("should insert a document");
+            }
+        });
+
+        let change = changes.next().await.expect("stream should stay open").unwrap();
+        assert_eq!(change.id, "after_gap");
+        t.await.unwrap();
+        client.destroy_db("should_stay_open_across_an_idle_gap").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_consume_changes_in_a_spawned_task() {
+        let client = Client::new_local_test().unwrap();
+        let db = client.db("should_consume_changes_in_a_spawned_task").await.unwrap();
+        let mut docs: Vec<Value> = (0..3).map(|idx| json!({ "_id": format!("test_{}", idx) })).collect();
+        db.bulk_docs(&mut docs).await.expect("should insert 3 documents");
+
+        let changes = db.changes(None);
+        let collected = tokio::spawn(async move {
+            let mut changes = changes;
+            let mut collected = vec![];
+            while let Some(change) = changes.next().await {
+                collected.push(change.unwrap());
+            }
+            collected
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(collected.len(), 3);
+        client
+            .destroy_db("should_consume_changes_in_a_spawned_task")
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_report_pending_after_a_finite_feed() {
+        let client = Client::new_local_test().unwrap();
+        let db = client.db("should_report_pending_after_a_finite_feed").await.unwrap();
+        let mut docs: Vec<Value> = (0..5).map(|idx| json!({ "_id": format!("test_{}", idx) })).collect();
+        db.bulk_docs(&mut docs).await.expect("should insert 5 documents");
+
+        let mut params = HashMap::new();
+        params.insert("feed".to_string(), "continuous".to_string());
+        params.insert("timeout".to_string(), "0".to_string());
+        params.insert("limit".to_string(), "2".to_string());
+        let mut changes = ChangesStream::with_params(client.clone(), db.name().to_string(), None, params);
+        assert_eq!(changes.pending(), None);
+        while let Some(change) = changes.next().await {
+            change.unwrap();
+        }
+
+        assert_eq!(changes.pending(), Some(3));
+        client
+            .destroy_db("should_report_pending_after_a_finite_feed")
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_get_filtered_changes() {
+        let client = Client::new_local_test().unwrap();
+        let db = client.db("should_get_filtered_changes").await.unwrap();
+        let design =
+            DesignDocument::default().filter("important", "function (doc, req) { return doc.type === 'important'; }");
+        db.create_design("filters", design)
+            .await
+            .expect("should create the filter");
+        let mut docs: Vec<Value> = (0..6)
+            .map(|idx| json!({ "_id": format!("test_{}", idx), "type": if idx % 2 == 0 { "important" } else { "other" } }))
+            .collect();
+        db.bulk_docs(&mut docs).await.expect("should insert 6 documents");
+
+        let mut changes = db.changes(None).with_filter("filters", "important");
+        let mut ids = vec![];
+        while let Some(change) = changes.next().await {
+            ids.push(change.unwrap().id);
+        }
+        ids.sort();
+
+        assert_eq!(ids, vec!["test_0", "test_2", "test_4"]);
+        client.destroy_db("should_get_filtered_changes").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_collect_n_changes() {
+        let client = Client::new_local_test().unwrap();
+        let db = client.db("should_collect_n_changes").await.unwrap();
+        let mut docs: Vec<Value> = (0..10).map(|idx| json!({ "_id": format!("test_{}", idx) })).collect();
+        db.bulk_docs(&mut docs).await.expect("should insert 10 documents");
+
+        let mut changes = db.changes(None);
+        let collected = changes.collect_n(5).await.expect("should collect changes");
+        assert_eq!(collected.len(), 5);
+
+        // the remainder of the finite feed
+        let collected = changes.collect_n(50).await.expect("should collect changes");
+        assert_eq!(collected.len(), 5);
+        client.destroy_db("should_collect_n_changes").await.unwrap();
+    }
 }
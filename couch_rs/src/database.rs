@@ -1,26 +1,59 @@
 use crate::{
     changes::ChangesStream,
     client::{is_accepted, is_ok, Client},
-    document::{DocumentCollection, TypedCouchDocument, ID_FIELD, REV_FIELD},
-    error::{CouchError, CouchResult, ErrorMessage},
+    document::{from_document_value, AllDocsResponse, DocumentCollection, TypedCouchDocument, ID_FIELD, REV_FIELD},
+    error::{CouchError, CouchResult, ErrorMessage, NoUsableIndexDetails},
     types::{
-        design::DesignCreated,
-        document::{DocumentCreatedDetails, DocumentCreatedResponse, DocumentCreatedResult, DocumentId},
-        find::{FindQuery, FindResult},
-        index::{DatabaseIndexList, DeleteIndexResponse, IndexFields, IndexType},
-        query::{QueriesCollection, QueriesParams, QueryParams},
-        view::ViewCollection,
+        attachment::{AttachmentInfo, Attachments},
+        design::{DesignCreated, DesignDocument},
+        document::{
+            BulkGetItem, BulkGetResponse, DocMeta, DocumentCreatedDetails, DocumentCreatedResponse,
+            DocumentCreatedResult, DocumentId, LeafRevision,
+        },
+        find::{ExplainResult, FindQuery, FindQueryRef, FindResult},
+        index::{DatabaseIndexList, Index, IndexDef, IndexFields, IndexType},
+        query::{QueriesCollection, QueriesParams, QueryParams, UpdateView},
+        system::{CouchResponse, DbInfo, PurgedInfos, ShardRange, Shards},
+        view::{CouchUpdate, CouchViews, RawViewCollection, ViewCollection},
     },
 };
-use futures_core::Future;
-use reqwest::StatusCode;
+use futures_core::{Future, Stream};
+use futures_util::StreamExt;
+use percent_encoding::{percent_decode_str, utf8_percent_encode, NON_ALPHANUMERIC};
+use reqwest::{
+    header::{ACCEPT, ACCEPT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, ETAG, IF_NONE_MATCH, LOCATION},
+    StatusCode,
+};
 use serde::{de::DeserializeOwned, Serialize};
 use serde_json::{from_value, json, to_string, Value};
-use std::{collections::HashMap, fmt::Debug, pin::Pin, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt::Debug,
+    io::Write,
+    pin::Pin,
+    sync::Arc,
+};
 use tokio::sync::mpsc::Sender;
 
-trait CouchJsonExt {
+const LOCAL_SEQ_FIELD: &str = "_local_seq";
+const DELETED_FIELD: &str = "_deleted";
+const REVISIONS_FIELD: &str = "_revisions";
+const ATTACHMENTS_FIELD: &str = "_attachments";
+const CONFLICTS_FIELD: &str = "_conflicts";
+const DESIGN_PREFIX: &str = "_design/";
+const BULK_UPSERT_CHUNK_SIZE: usize = 1000;
+/// Number of times `patch` retries after a conflict, before the conflict is returned.
+const PATCH_MAX_RETRIES: usize = 10;
+
+/// Default number of documents per request, used by the chunked bulk operations.
+pub const DEFAULT_CHUNK_SIZE: usize = 2000;
+
+pub(crate) trait CouchJsonExt: Sized {
     fn couch_json<T: DeserializeOwned>(self) -> Pin<Box<dyn Future<Output = Result<T, CouchError>> + Send>>;
+
+    /// Like `error_for_status`, but keeps the `error` and `reason` that `CouchDB` returns in the body of
+    /// a failed request, instead of only the status line.
+    fn couch_error_for_status(self) -> Pin<Box<dyn Future<Output = Result<Self, CouchError>> + Send>>;
 }
 
 impl CouchJsonExt for reqwest::Response {
@@ -40,6 +73,41 @@ impl CouchJsonExt for reqwest::Response {
 
         Box::pin(fut)
     }
+
+    fn couch_error_for_status(self) -> Pin<Box<dyn Future<Output = Result<Self, CouchError>> + Send>> {
+        let fut = async move {
+            let status = self.status();
+            if !status.is_client_error() && !status.is_server_error() {
+                return Ok(self);
+            }
+
+            let body = self.text().await.unwrap_or_default();
+            Err(couch_error_from_body(status, &body))
+        };
+
+        Box::pin(fut)
+    }
+}
+
+/// Builds the error for a failed request from the `error` and `reason` in the body returned by `CouchDB`.
+fn couch_error_from_body(status: StatusCode, body: &str) -> CouchError {
+    let response = serde_json::from_str::<CouchResponse>(body).ok();
+    let message = match &response {
+        Some(CouchResponse {
+            error: Some(error),
+            reason: Some(reason),
+            ..
+        }) => format!("{error}: {reason}"),
+        Some(CouchResponse {
+            error: Some(message), ..
+        })
+        | Some(CouchResponse {
+            reason: Some(message), ..
+        }) => message.clone(),
+        _ if !body.trim().is_empty() => s!(body.trim()),
+        _ => s!(status.canonical_reason().unwrap_or("request failed")),
+    };
+    CouchError::new(message, status).with_couch_error(response.and_then(|r| r.error))
 }
 
 /// Database operations on a `CouchDB` Database
@@ -71,6 +139,11 @@ impl Database {
         format!("{}/{}", self.name, encoded)
     }
 
+    fn create_attachment_path(&self, id: &str, name: &str) -> String {
+        let encoded_name = url_encode!(name);
+        format!("{}/{}", self.create_document_path(id), encoded_name)
+    }
+
     fn create_design_path(&self, id: &str) -> String {
         let encoded = url_encode!(id);
         format!("{}/_design/{}", self.name, encoded)
@@ -121,6 +194,109 @@ impl Database {
         is_accepted(request).await
     }
 
+    /// Gets information about this database.
+    /// See [common](https://docs.couchdb.org/en/stable/api/database/common.html) for more details.
+    pub async fn get_info(&self) -> CouchResult<DbInfo> {
+        self._client
+            .get(&self.name, None)
+            .send()
+            .await?
+            .couch_error_for_status()
+            .await?
+            .couch_json()
+            .await
+    }
+
+    /// Returns the number of documents in this database, excluding deleted documents.
+    pub async fn doc_count(&self) -> CouchResult<u64> {
+        Ok(self.get_info().await?.doc_count)
+    }
+
+    /// Returns the number of deleted documents in this database.
+    pub async fn deleted_count(&self) -> CouchResult<u64> {
+        Ok(self.get_info().await?.doc_del_count)
+    }
+
+    /// Returns the purge sequence of this database: the sequence of the last purge.
+    pub async fn purge_seq(&self) -> CouchResult<String> {
+        Ok(self.get_info().await?.purge_seq)
+    }
+
+    /// Returns the documents and revisions that were purged from this database, and the current purge sequence.
+    /// Only the most recent purges are kept; see `purged_infos_limit`.
+    ///
+    /// *Note*: the `_purged_infos` endpoint is only available since `CouchDB` 3.4; older versions respond
+    /// with an error, which is returned as is.
+    pub async fn purged_infos(&self) -> CouchResult<PurgedInfos> {
+        self._client
+            .get(&self.create_raw_path("_purged_infos"), None)
+            .send()
+            .await?
+            .couch_error_for_status()
+            .await?
+            .couch_json()
+            .await
+    }
+
+    /// Returns the maximum number of purges that are tracked for this database. Defaults to 1000.
+    /// Available since `CouchDB` 2.3.
+    pub async fn purged_infos_limit(&self) -> CouchResult<u64> {
+        self._client
+            .get(&self.create_raw_path("_purged_infos_limit"), None)
+            .send()
+            .await?
+            .couch_error_for_status()
+            .await?
+            .couch_json()
+            .await
+    }
+
+    /// Sets the maximum number of purges that are tracked for this database.
+    /// Available since `CouchDB` 2.3.
+    pub async fn set_purged_infos_limit(&self, limit: u64) -> CouchResult<bool> {
+        let response = self
+            ._client
+            .put(&self.create_raw_path("_purged_infos_limit"), limit.to_string())
+            .send()
+            .await?
+            .couch_error_for_status()
+            .await?;
+        let result: CouchResponse = response.couch_json().await?;
+        Ok(result.ok.unwrap_or(false))
+    }
+
+    /// Flushes pending writes of this database to disk, and returns the `instance_start_time`.
+    /// `CouchDB` 3.x and later flush writes immediately, and may no longer provide this endpoint; that is
+    /// reported as an error with status `501 Not Implemented`. A missing database is reported as a regular
+    /// not-found error.
+    pub async fn ensure_full_commit(&self) -> CouchResult<String> {
+        let response = self
+            ._client
+            .post(&self.create_raw_path("_ensure_full_commit"), String::new())
+            .send()
+            .await?;
+        let status = response.status();
+        if status == StatusCode::NOT_FOUND || status == StatusCode::GONE {
+            let body = response.text().await.unwrap_or_default();
+            // the endpoint is also not found when the database is missing, which is a real failure
+            if status == StatusCode::NOT_FOUND && !self.database_exists().await? {
+                return Err(couch_error_from_body(status, &body));
+            }
+            return Err(CouchError::new(
+                s!("_ensure_full_commit is not supported by this CouchDB version; writes are committed immediately, so it is a no-op"),
+                StatusCode::NOT_IMPLEMENTED,
+            ));
+        }
+
+        let result: Value = response.couch_error_for_status().await?.couch_json().await?;
+        Ok(result["instance_start_time"].as_str().unwrap_or_default().to_string())
+    }
+
+    async fn database_exists(&self) -> CouchResult<bool> {
+        let response = self._client.head(&self.name, None).send().await?;
+        Ok(response.status().is_success())
+    }
+
     /// Checks if a document ID exists
     ///
     /// Usage:
@@ -147,6 +323,51 @@ impl Database {
         is_ok(request).await
     }
 
+    /// Like `exists`, but returns the current revision and the size of the document, taken from the headers
+    /// of the same `HEAD` request. Returns `None` when the document does not exist.
+    ///
+    /// *Note*: `CouchDB` answers a `HEAD` request for a deleted document with a 404, like for a document that
+    /// never existed, so deleted documents are reported as `None`; use `get_including_deleted` to tell them apart.
+    pub async fn exists_meta(&self, id: &str) -> CouchResult<Option<DocMeta>> {
+        let response = self._client.head(&self.create_document_path(id), None).send().await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        let response = response.couch_error_for_status().await?;
+        let header = |name| response.headers().get(name).and_then(|value| value.to_str().ok());
+        let rev = header(ETAG)
+            .map(|etag| etag.trim_matches('"').to_string())
+            .ok_or_else(|| {
+                CouchError::new(
+                    s!("No ETag found in returned headers"),
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                )
+            })?;
+        let size = header(CONTENT_LENGTH).and_then(|length| length.parse().ok());
+        Ok(Some(DocMeta { rev, size }))
+    }
+
+    /// Gets the current revision of a document from the `ETag` of a `HEAD` request, without fetching
+    /// the document itself. Returns `None` when the document does not exist.
+    pub(crate) async fn head_rev(&self, id: &str) -> CouchResult<Option<String>> {
+        self.head_rev_at(&self.create_document_path(id)).await
+    }
+
+    async fn head_rev_at(&self, path: &str) -> CouchResult<Option<String>> {
+        let response = self._client.head(path, None).send().await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        let response = response.couch_error_for_status().await?;
+        Ok(response
+            .headers()
+            .get(ETAG)
+            .and_then(|etag| etag.to_str().ok())
+            .map(|etag| etag.trim_matches('"').to_string()))
+    }
+
     /// Convenience wrapper around `get::`<Value>(id)
     pub async fn get_raw(&self, id: &str) -> CouchResult<Value> {
         self.get(id).await
@@ -203,21 +424,290 @@ impl Database {
     /// }
     ///```
     pub async fn get<T: TypedCouchDocument>(&self, id: &str) -> CouchResult<T> {
-        let value: serde_json::Value = self
+        let value = self.get_value(id, None).await?;
+        to_typed_document(value)
+    }
+
+    /// Adds an attachment to a document, or replaces it, and returns the new revision of the document.
+    /// The attachment is sent as-is, with the given content type, e.g. `image/png`. Use an empty `rev`
+    /// to create a new document with just the attachment.
+    ///
+    /// Usage:
+    /// ```
+    /// use couch_rs::error::CouchResult;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> CouchResult<()> {
+    ///     let client = couch_rs::Client::new_local_test()?;
+    ///     let db = client.db("test_db").await?;
+    ///     let details = db
+    ///         .put_attachment("jdoe", "1-967a00dff5e02add41819138abb3284d", "notes.txt", "text/plain", b"hello".to_vec())
+    ///         .await?;
+    ///     println!("new revision: {}", details.rev);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn put_attachment(
+        &self,
+        doc_id: &str,
+        rev: &str,
+        name: &str,
+        content_type: &str,
+        data: Vec<u8>,
+    ) -> DocumentCreatedResult {
+        let mut request =
+            self._client
+                .put_with_content_type(&self.create_attachment_path(doc_id, name), data, content_type)?;
+        if !rev.is_empty() {
+            request = request.query(&[("rev", rev)]);
+        }
+        let response = request.send().await?;
+
+        let status = response.status();
+        let data: DocumentCreatedResponse = response.json().await?;
+
+        if let (Some(true), Some(id), Some(rev)) = (data.ok, data.id, data.rev) {
+            Ok(DocumentCreatedDetails { id, rev })
+        } else {
+            let err = data.error.clone().unwrap_or_else(|| s!("unspecified error"));
+            Err(CouchError::new_with_id(Some(s!(doc_id)), err, status).with_couch_error(data.error))
+        }
+    }
+
+    /// Gets the metadata of an attachment, without downloading it, through a `HEAD` request.
+    /// Returns `None` when the document or the attachment does not exist.
+    pub async fn attachment_info(&self, doc_id: &str, name: &str) -> CouchResult<Option<AttachmentInfo>> {
+        let response = self
+            ._client
+            .head(&self.create_attachment_path(doc_id, name), None)
+            // prevents a compressed response, so the content length is that of the attachment itself
+            .header(ACCEPT_ENCODING, "identity")
+            .send()
+            .await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        let response = response.couch_error_for_status().await?;
+        let header = |name| response.headers().get(name).and_then(|value| value.to_str().ok());
+        Ok(Some(AttachmentInfo {
+            content_type: header(CONTENT_TYPE).unwrap_or_default().to_string(),
+            length: header(CONTENT_LENGTH).and_then(|length| length.parse().ok()),
+            digest: header(ETAG).map(|etag| etag.trim_matches('"').to_string()),
+        }))
+    }
+
+    /// Gets one document, unless its current revision is `known_rev`. Sends the known revision as
+    /// `If-None-Match`, so when the document has not changed, `CouchDB` responds with a `304 Not Modified`
+    /// without transferring the document, and `Ok(None)` is returned.
+    ///
+    /// Usage:
+    /// ```
+    /// use couch_rs::error::CouchResult;
+    /// use couch_rs::document::TypedCouchDocument;
+    /// use serde_json::Value;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> CouchResult<()> {
+    ///     let client = couch_rs::Client::new_local_test()?;
+    ///     let db = client.db("test_db").await?;
+    ///     let doc: Value = db.get("jdoe").await?;
+    ///     if let Some(changed) = db.get_if_changed::<Value>("jdoe", &doc.get_rev()).await? {
+    ///         println!("the document changed: {changed}");
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn get_if_changed<T: TypedCouchDocument>(&self, id: &str, known_rev: &str) -> CouchResult<Option<T>> {
+        let response = self
+            ._client
+            .get(&self.create_document_path(id), None)
+            .header(IF_NONE_MATCH, format!("\"{known_rev}\""))
+            .send()
+            .await?;
+        if response.status() == StatusCode::NOT_MODIFIED {
+            return Ok(None);
+        }
+
+        let value = response.couch_error_for_status().await?.couch_json().await?;
+        to_typed_document(value).map(Some)
+    }
+
+    /// Gets one document, together with its `_local_seq`: the sequence number of the last update of
+    /// this document. This allows ordering documents by update order without maintaining a custom
+    /// timestamp field.
+    ///
+    /// *Note*: the `_local_seq` is local to this database (and, on a cluster, to the shard holding
+    /// the document). It is not a global sequence and can not be compared across databases.
+    pub async fn get_with_local_seq<T: TypedCouchDocument>(&self, id: &str) -> CouchResult<(T, u64)> {
+        let mut params = HashMap::new();
+        params.insert(s!("local_seq"), s!("true"));
+        let mut value = self.get_value(id, Some(&params)).await?;
+
+        let local_seq = value
+            .as_object_mut()
+            .and_then(|o| o.remove(LOCAL_SEQ_FIELD))
+            .and_then(|seq| match seq {
+                Value::Number(n) => n.as_u64(),
+                Value::String(s) => s.parse().ok(),
+                _ => None,
+            })
+            .ok_or_else(|| {
+                CouchError::new(
+                    format!("No valid {LOCAL_SEQ_FIELD} found in returned data"),
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                )
+            })?;
+
+        Ok((to_typed_document(value)?, local_seq))
+    }
+
+    /// Gets every leaf revision of a document, including conflicts and deleted tombstones, together
+    /// with their revision history. This is the basis for resolving conflicts.
+    pub async fn get_all_leaf_revs<T: TypedCouchDocument>(&self, id: &str) -> CouchResult<Vec<LeafRevision<T>>> {
+        self.get_leaf_values(id, true)
+            .await?
+            .into_iter()
+            .map(|mut value| {
+                let rev = get_mandatory_string_value(REV_FIELD, &value)?;
+                let deleted = value.get(DELETED_FIELD).and_then(Value::as_bool).unwrap_or(false);
+                let revisions = match value.as_object_mut().and_then(|doc| doc.remove(REVISIONS_FIELD)) {
+                    Some(revisions) => Some(from_value(revisions)?),
+                    None => None,
+                };
+                let doc = if deleted { None } else { Some(to_typed_document(value)?) };
+                Ok(LeafRevision {
+                    rev,
+                    deleted,
+                    revisions,
+                    doc,
+                })
+            })
+            .collect()
+    }
+
+    /// Gets the winning revision of a document, even when it has been deleted, together with a flag that
+    /// tells whether it is a deleted tombstone. Unlike `get`, this distinguishes a deleted document from one
+    /// that never existed, which returns `None`.
+    ///
+    /// A tombstone typically only has an `_id`, `_rev` and `_deleted` field, so `T` should be able to
+    /// deserialize from that; e.g. a `Value` or a struct with defaults for its other fields.
+    ///
+    /// Usage:
+    /// ```
+    /// use couch_rs::error::CouchResult;
+    /// use serde_json::Value;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> CouchResult<()> {
+    ///     let client = couch_rs::Client::new_local_test()?;
+    ///     let db = client.db("test_db").await?;
+    ///     match db.get_including_deleted::<Value>("jdoe").await? {
+    ///         Some((_, true)) => println!("jdoe has been deleted"),
+    ///         Some((doc, false)) => println!("jdoe: {doc}"),
+    ///         None => println!("jdoe never existed"),
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn get_including_deleted<T: TypedCouchDocument>(&self, id: &str) -> CouchResult<Option<(T, bool)>> {
+        let leaves = match self.get_leaf_values(id, false).await {
+            Ok(leaves) => leaves,
+            Err(err) if err.is_not_found() => return Ok(None),
+            Err(err) => return Err(err),
+        };
+
+        // CouchDB picks the winner from the leaves that are not deleted, if any; then the highest
+        // revision number, with the revision hash as a tiebreaker
+        let winner = leaves.into_iter().max_by_key(|value| {
+            let deleted = value.get(DELETED_FIELD).and_then(Value::as_bool).unwrap_or(false);
+            let rev = value.get_rev().into_owned();
+            let generation = rev
+                .split_once('-')
+                .and_then(|(generation, _)| generation.parse::<u64>().ok())
+                .unwrap_or(0);
+            (!deleted, generation, rev)
+        });
+
+        match winner {
+            Some(value) => {
+                let deleted = value.get(DELETED_FIELD).and_then(Value::as_bool).unwrap_or(false);
+                Ok(Some((to_typed_document(value)?, deleted)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Gets all leaf revisions of a document, through `open_revs=all`, skipping the missing ones.
+    async fn get_leaf_values(&self, id: &str, revs: bool) -> CouchResult<Vec<Value>> {
+        let mut params = HashMap::new();
+        params.insert(s!("open_revs"), s!("all"));
+        if revs {
+            params.insert(s!("revs"), s!("true"));
+        }
+
+        let results: Vec<Value> = self
+            ._client
+            .get(&self.create_document_path(id), Some(&params))
+            // avoid a multipart response
+            .header(ACCEPT, "application/json")
+            .send()
+            .await?
+            .couch_error_for_status()
+            .await?
+            .couch_json()
+            .await?;
+
+        Ok(results
+            .into_iter()
+            // skip the revisions that are reported as "missing"
+            .filter_map(|mut result| result.get_mut("ok").map(Value::take))
+            .collect())
+    }
+
+    /// Gets one document, including the data of its attachments and their encoding info.
+    /// Add an `_attachments: Attachments` field to `T` to access them.
+    pub async fn get_with_attachments<T: TypedCouchDocument>(&self, id: &str) -> CouchResult<T> {
+        let mut params = HashMap::new();
+        params.insert(s!("attachments"), s!("true"));
+        params.insert(s!("att_encoding_info"), s!("true"));
+        to_typed_document(self.get_value(id, Some(&params)).await?)
+    }
+
+    /// Gets the metadata of a document's attachments, from the stubs `CouchDB` returns by default.
+    /// Returns an empty map when the document has no attachments.
+    pub async fn get_attachment_stubs(&self, id: &str) -> CouchResult<Attachments> {
+        let mut doc = self.get_value(id, None).await?;
+        match doc.get_mut(ATTACHMENTS_FIELD) {
+            Some(attachments) => Ok(from_value(attachments.take())?),
+            None => Ok(Attachments::new()),
+        }
+    }
+
+    /// Gets one document as the untouched response body, without deserializing and re-serializing it.
+    /// This preserves the exact bytes `CouchDB` returned, e.g. for hashing.
+    pub async fn get_bytes(&self, id: &str) -> CouchResult<Vec<u8>> {
+        let bytes = self
             ._client
             .get(&self.create_document_path(id), None)
             .send()
             .await?
-            .error_for_status()?
+            .couch_error_for_status()
+            .await?
+            .bytes()
+            .await?;
+        Ok(bytes.to_vec())
+    }
+
+    async fn get_value(&self, id: &str, params: Option<&HashMap<String, String>>) -> CouchResult<Value> {
+        self._client
+            .get(&self.create_document_path(id), params)
+            .send()
+            .await?
+            .couch_error_for_status()
+            .await?
             .couch_json()
             .await
-            .map_err(CouchError::from)?;
-        let id = get_mandatory_string_value(ID_FIELD, &value)?;
-        let rev = get_mandatory_string_value(REV_FIELD, &value)?;
-        let mut document: T = from_value(value)?;
-        document.set_id(&id);
-        document.set_rev(&rev);
-        Ok(document)
     }
 
     /// Gets documents in bulk with provided IDs list
@@ -230,6 +720,70 @@ impl Database {
         self.get_bulk_params(ids, None).await
     }
 
+    /// Gets documents in bulk with provided IDs list, as a map keyed by id. Documents that do not exist
+    /// are absent from the map.
+    pub async fn get_map<T: TypedCouchDocument>(&self, ids: Vec<DocumentId>) -> CouchResult<HashMap<DocumentId, T>> {
+        Ok(self
+            .get_bulk_params::<T>(ids, None)
+            .await?
+            .rows
+            .into_iter()
+            .map(|doc| (doc.get_id().into_owned(), doc))
+            .collect())
+    }
+
+    /// Replaces the ids in the array `field` of `doc` with the documents they refer to, fetched in a single
+    /// request as `T`. Entries that are not ids, like documents populated before, are left alone, as are the
+    /// ids of documents that do not exist. A missing `field` is not an error; a `field` that is not an array is.
+    ///
+    /// Usage:
+    /// ```
+    /// use couch_rs::error::CouchResult;
+    /// use serde_json::{json, Value};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> CouchResult<()> {
+    ///     let client = couch_rs::Client::new_local_test()?;
+    ///     let db = client.db("family_db").await?;
+    ///     let mut parent = json!({"_id": "jane", "children": ["john", "jill"]});
+    ///     db.populate::<Value>(&mut parent, "children").await?;
+    ///     // parent["children"] now holds the documents of john and jill
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn populate<T: TypedCouchDocument>(&self, doc: &mut Value, field: &str) -> CouchResult<()> {
+        let Some(refs) = doc.get_mut(field) else {
+            return Ok(());
+        };
+        let Some(refs) = refs.as_array_mut() else {
+            return Err(CouchError::new(
+                format!("field {field} is not an array of document ids"),
+                StatusCode::BAD_REQUEST,
+            ));
+        };
+
+        let ids: Vec<DocumentId> = refs.iter().filter_map(|r| r.as_str().map(String::from)).collect();
+        if ids.is_empty() {
+            return Ok(());
+        }
+
+        let docs: HashMap<DocumentId, T> = self
+            .get_bulk::<T>(ids)
+            .await?
+            .rows
+            .into_iter()
+            .map(|d| (d.get_id().into_owned(), d))
+            .collect();
+
+        for r in refs.iter_mut() {
+            let Some(id) = r.as_str() else { continue };
+            if let Some(d) = docs.get(id) {
+                *r = serde_json::to_value(d)?;
+            }
+        }
+        Ok(())
+    }
+
     /// Each time a document is stored or updated in `CouchDB`, the internal B-tree is updated.
     /// Bulk insertion provides efficiency gains in both storage space, and time,
     /// by consolidating many of the updates to intermediate B-tree nodes.
@@ -276,8 +830,15 @@ impl Database {
             .post(&self.create_raw_path("_bulk_docs"), body)
             .send()
             .await?;
+        // a failure of the whole request, like a 413 for a too large batch, has no results per document;
+        // CouchDB does answer a 417 with the results per document when documents were rejected
+        let response = if response.status() == StatusCode::EXPECTATION_FAILED {
+            response
+        } else {
+            response.couch_error_for_status().await?
+        };
 
-        let data: Vec<DocumentCreatedResponse> = response.json().await?;
+        let data: Vec<DocumentCreatedResponse> = response.couch_json().await?;
 
         if raw_docs.len() != data.len() {
             return Err(CouchError::new(
@@ -291,7 +852,7 @@ impl Database {
         }
         let result = raw_docs
             .iter_mut()
-            .zip(data.into_iter())
+            .zip(data)
             .map(|(doc, response): (&mut T, DocumentCreatedResponse)| {
                 let result: DocumentCreatedResult = response.into();
                 match result {
@@ -307,51 +868,131 @@ impl Database {
         Ok(result)
     }
 
-    /// Gets documents in bulk with provided IDs list, with added params. Params description can be found here:
-    /// [_all_docs](https://docs.couchdb.org/en/latest/api/database/bulk-api.html?highlight=_all_docs)
+    /// Gets documents in bulk through `_bulk_get`, which, unlike `get_bulk`, can fetch specific revisions,
+    /// e.g. the conflicting revisions of a document. Documents (or revisions) that can not be found
+    /// are omitted from the result.
     ///
     /// Usage:
-    ///
     /// ```
-    /// use couch_rs::types::find::FindQuery;
     /// use couch_rs::error::CouchResult;
-    /// use serde_json::json;
+    /// use couch_rs::types::document::BulkGetItem;
     /// use serde_json::Value;
     ///
-    /// const TEST_DB: &str = "test_db";
-    ///
     /// #[tokio::main]
     /// async fn main() -> CouchResult<()> {
     ///     let client = couch_rs::Client::new_local_test()?;
-    ///     let db = client.db(TEST_DB).await?;
-    ///     let mut doc_1 = json!({
-    ///                     "_id": "john",
-    ///                     "first_name": "John",
-    ///                     "last_name": "Doe"
-    ///                 });
-    ///
-    ///     let mut doc_2 = json!({
-    ///                     "_id": "jane",
-    ///                     "first_name": "Jane",
-    ///                     "last_name": "Doe"
-    ///                 });
-    ///
-    ///     // Save these documents
-    ///     db.save(&mut doc_1).await?;
-    ///     db.save(&mut doc_2).await?;
-    ///
-    ///     // subsequent call updates the existing document
-    ///     let docs = db.get_bulk_params::<Value>(vec!["john".to_string(), "jane".to_string()], None).await?;
-    ///
-    ///     // verify that we received the 2 documents
-    ///     assert_eq!(docs.rows.len(), 2);
+    ///     let db = client.db("test_db").await?;
+    ///     let docs = db
+    ///         .bulk_get::<Value>(vec![BulkGetItem::new("jdoe"), BulkGetItem::new("asmith").rev("1-967a00dff5e02add41819138abb3284d")])
+    ///         .await?;
     ///     Ok(())
     /// }
     /// ```
-    pub async fn get_bulk_params<T: TypedCouchDocument>(
-        &self,
-        ids: Vec<DocumentId>,
-        params: Option<QueryParams<DocumentId>>,
+    pub async fn bulk_get<T: TypedCouchDocument>(&self, docs: Vec<BulkGetItem>) -> CouchResult<DocumentCollection<T>> {
+        let response = self
+            ._client
+            .post(&self.create_raw_path("_bulk_get"), js!(json!({ "docs": docs })))
+            .send()
+            .await?
+            .couch_error_for_status()
+            .await?;
+
+        let data: BulkGetResponse<T> = response.couch_json().await?;
+        let rows: Vec<T> = data
+            .results
+            .into_iter()
+            .flat_map(|result| result.docs)
+            .filter_map(|doc| doc.ok)
+            .collect();
+        Ok(DocumentCollection::new_from_documents(rows, None))
+    }
+
+    /// Gets documents in bulk through `_bulk_get`, as raw Values.
+    /// See [`Database::bulk_get`] for details.
+    pub async fn bulk_get_raw(&self, docs: Vec<BulkGetItem>) -> CouchResult<DocumentCollection<Value>> {
+        self.bulk_get(docs).await
+    }
+
+    /// Gets documents in bulk with provided IDs list, splitting the IDs over multiple requests of at most
+    /// `chunk_size` IDs each. This prevents a single huge `_all_docs` request for large sets of IDs.
+    /// A `chunk_size` of 0 means the default chunk size of 2000 is used.
+    pub async fn bulk_get_chunked<T: TypedCouchDocument>(
+        &self,
+        ids: Vec<DocumentId>,
+        chunk_size: usize,
+    ) -> CouchResult<DocumentCollection<T>> {
+        let mut rows = Vec::with_capacity(ids.len());
+        for chunk in ids.chunks(effective_chunk_size(chunk_size)) {
+            let collection = self.get_bulk::<T>(chunk.to_vec()).await?;
+            rows.extend(collection.rows);
+        }
+
+        Ok(DocumentCollection::new_from_documents(rows, None))
+    }
+
+    /// Creates or updates documents in bulk, splitting the documents over multiple `_bulk_docs` requests
+    /// of at most `chunk_size` documents each. A `chunk_size` of 0 means the default chunk size of 2000 is used.
+    /// The results are returned in the same order as the provided documents.
+    ///
+    /// *Note*: when a request for one of the chunks fails, the chunks before it have already been written.
+    pub async fn bulk_docs_chunked<T: TypedCouchDocument>(
+        &self,
+        raw_docs: &mut [T],
+        chunk_size: usize,
+    ) -> CouchResult<Vec<DocumentCreatedResult>> {
+        let mut results = Vec::with_capacity(raw_docs.len());
+        for chunk in raw_docs.chunks_mut(effective_chunk_size(chunk_size)) {
+            results.extend(self.bulk_docs(chunk).await?);
+        }
+
+        Ok(results)
+    }
+
+    /// Gets documents in bulk with provided IDs list, with added params. Params description can be found here:
+    /// [_all_docs](https://docs.couchdb.org/en/latest/api/database/bulk-api.html?highlight=_all_docs)
+    ///
+    /// Usage:
+    ///
+    /// ```
+    /// use couch_rs::types::find::FindQuery;
+    /// use couch_rs::error::CouchResult;
+    /// use serde_json::json;
+    /// use serde_json::Value;
+    ///
+    /// const TEST_DB: &str = "test_db";
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> CouchResult<()> {
+    ///     let client = couch_rs::Client::new_local_test()?;
+    ///     let db = client.db(TEST_DB).await?;
+    ///     let mut doc_1 = json!({
+    ///                     "_id": "john",
+    ///                     "first_name": "John",
+    ///                     "last_name": "Doe"
+    ///                 });
+    ///
+    ///     let mut doc_2 = json!({
+    ///                     "_id": "jane",
+    ///                     "first_name": "Jane",
+    ///                     "last_name": "Doe"
+    ///                 });
+    ///
+    ///     // Save these documents
+    ///     db.save(&mut doc_1).await?;
+    ///     db.save(&mut doc_2).await?;
+    ///
+    ///     // subsequent call updates the existing document
+    ///     let docs = db.get_bulk_params::<Value>(vec!["john".to_string(), "jane".to_string()], None).await?;
+    ///
+    ///     // verify that we received the 2 documents
+    ///     assert_eq!(docs.rows.len(), 2);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn get_bulk_params<T: TypedCouchDocument>(
+        &self,
+        ids: Vec<DocumentId>,
+        params: Option<QueryParams<DocumentId>>,
     ) -> CouchResult<DocumentCollection<T>> {
         let mut options = params.unwrap_or_default();
 
@@ -363,9 +1004,81 @@ impl Database {
             .post(&self.create_raw_path("_all_docs"), to_string(&options)?)
             .send()
             .await?
-            .error_for_status()?;
+            .couch_error_for_status()
+            .await?;
 
-        Ok(DocumentCollection::new(response.couch_json().await?))
+        let all_docs: AllDocsResponse<Value> = response.couch_json().await?;
+        Ok(DocumentCollection::new(all_docs.into_typed()?))
+    }
+
+    /// Gets the current revision of each of the documents with the provided IDs, without fetching the
+    /// documents themselves. Missing documents are omitted from the result; deleted documents are included
+    /// with the revision of their deletion.
+    pub async fn get_bulk_revs(&self, ids: Vec<DocumentId>) -> CouchResult<HashMap<DocumentId, String>> {
+        let options = QueryParams::from_keys(ids).include_docs(false);
+        let response = self
+            ._client
+            .post(&self.create_raw_path("_all_docs"), to_string(&options)?)
+            .send()
+            .await?
+            .couch_error_for_status()
+            .await?;
+
+        let all_docs: AllDocsResponse<Value> = response.couch_json().await?;
+        Ok(all_docs
+            .rows
+            .into_iter()
+            .filter_map(|row| Some((row.id?, row.value?.rev)))
+            .collect())
+    }
+
+    /// Gets the conflicting revisions of the documents with the provided IDs, e.g. to resolve them after a
+    /// `bulk_docs`. Only documents that have conflicts are included in the result, mapped to the revisions
+    /// that lost (the `_conflicts`); missing documents are omitted.
+    ///
+    /// Usage:
+    /// ```
+    /// use couch_rs::error::CouchResult;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> CouchResult<()> {
+    ///     let client = couch_rs::Client::new_local_test()?;
+    ///     let db = client.db("test_db").await?;
+    ///     for (id, revs) in db.conflicts(vec!["jdoe".to_string(), "asmith".to_string()]).await? {
+    ///         println!("{id} has conflicting revisions {revs:?}");
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn conflicts(&self, ids: Vec<DocumentId>) -> CouchResult<HashMap<DocumentId, Vec<String>>> {
+        let options = QueryParams::from_keys(ids).include_docs(true).conflicts(true);
+        let response = self
+            ._client
+            .post(&self.create_raw_path("_all_docs"), to_string(&options)?)
+            .send()
+            .await?
+            .couch_error_for_status()
+            .await?;
+
+        let all_docs: AllDocsResponse<Value> = response.couch_json().await?;
+        Ok(all_docs
+            .rows
+            .into_iter()
+            .filter_map(|row| {
+                let revs: Vec<String> = row
+                    .doc?
+                    .get(CONFLICTS_FIELD)?
+                    .as_array()?
+                    .iter()
+                    .filter_map(|rev| rev.as_str().map(String::from))
+                    .collect();
+                if revs.is_empty() {
+                    None
+                } else {
+                    Some((row.id?, revs))
+                }
+            })
+            .collect())
     }
 
     /// Gets all the documents in database
@@ -396,11 +1109,120 @@ impl Database {
         self.find_batched(query, tx, batch_size, max_results).await
     }
 
+    /// Gets the shard ranges of this database, ordered by range, and the nodes that hold each shard.
+    /// The ranges partition the documents, so they can be read in parallel with
+    /// `get_all_batched_in_shard`.
+    /// See [_shards](https://docs.couchdb.org/en/stable/api/database/shard.html) for more details.
+    pub async fn shards(&self) -> CouchResult<Vec<ShardRange>> {
+        let response = self
+            ._client
+            .get(&self.create_raw_path("_shards"), None)
+            .send()
+            .await?
+            .couch_error_for_status()
+            .await?;
+        let shards: Shards = response.couch_json().await?;
+        let mut ranges = shards
+            .shards
+            .into_iter()
+            .map(|(range, nodes)| ShardRange::parse(range, nodes))
+            .collect::<CouchResult<Vec<_>>>()?;
+        ranges.sort_by_key(|shard| shard.begin);
+        Ok(ranges)
+    }
+
+    /// Gets the documents in a single shard of this database, in batches like `get_all_batched`, by reading
+    /// `_all_docs` of the shard on the first node that holds it. Reading every range returned by `shards`,
+    /// e.g. in parallel, reads every document exactly once.
+    ///
+    /// This uses the node local interface, which requires admin rights, and reads a single copy of the
+    /// shard; documents written to other copies may not have been synchronised to it yet.
+    pub async fn get_all_batched_in_shard<T: TypedCouchDocument>(
+        &self,
+        shard: &ShardRange,
+        tx: Sender<DocumentCollection<T>>,
+        batch_size: u64,
+        max_results: u64,
+    ) -> CouchResult<u64> {
+        let path = format!("{}/_all_docs", self.shard_path(shard).await?);
+        let limit = if batch_size > 0 { batch_size } else { 1000 };
+        let mut last_id: Option<DocumentId> = None;
+        let mut results: u64 = 0;
+
+        loop {
+            let mut options = QueryParams::default().include_docs(true).limit(limit);
+            if let Some(id) = last_id.take() {
+                options = options.start_key(id).skip(1);
+            }
+            let response = self
+                ._client
+                .post(&path, js!(&options))
+                .send()
+                .await?
+                .couch_error_for_status()
+                .await?;
+            let all_docs: AllDocsResponse<T> = response.couch_json().await?;
+            let page_size = all_docs.rows.len() as u64;
+            last_id = all_docs.rows.last().and_then(|row| row.id.clone());
+
+            let docs = DocumentCollection::new(all_docs);
+            results += u64::from(docs.total_rows);
+            if !docs.rows.is_empty() && tx.send(docs).await.is_err() {
+                break;
+            }
+            if page_size < limit || last_id.is_none() || (max_results > 0 && results >= max_results) {
+                break;
+            }
+        }
+        Ok(results)
+    }
+
+    /// The path of the copy of a shard on its first node; the name of a shard database includes a suffix
+    /// that is only found in the shard map of the database.
+    async fn shard_path(&self, shard: &ShardRange) -> CouchResult<String> {
+        let Some(node) = shard.nodes.first() else {
+            return Err(CouchError::new(
+                format!("shard {} is not on any node", shard.range),
+                StatusCode::NOT_FOUND,
+            ));
+        };
+
+        let shard_map: Value = self
+            ._client
+            .get(
+                &self._client.build_node_path("_local", &format!("_dbs/{}", self.name)),
+                None,
+            )
+            .send()
+            .await?
+            .couch_error_for_status()
+            .await?
+            .couch_json()
+            .await?;
+        let suffix: String = shard_map["shard_suffix"]
+            .as_array()
+            .map(|chars| {
+                chars
+                    .iter()
+                    .filter_map(|c| c.as_u64())
+                    .filter_map(|c| char::from_u32(c as u32))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let dbname = percent_decode_str(&self.name).decode_utf8_lossy();
+        let shard_name = format!("shards/{}/{}{}", shard.range, dbname, suffix);
+        Ok(self
+            ._client
+            .build_node_path(node, &utf8_percent_encode(&shard_name, NON_ALPHANUMERIC).to_string()))
+    }
+
     /// Finds documents in the database, using bookmarks to iterate through all the documents.
     /// Results are returned through an mpcs channel for async processing. Use this for very large
     /// databases only. Batch size can be requested. A value of 0, means the default `batch_size` of
     /// 1000 is used. `max_results` of 0 means all documents will be returned. A given `max_results` is
     /// always rounded *up* to the nearest multiplication of `batch_size`.
+    /// A `limit` on the query caps the total number of results exactly; the last batch is smaller when needed.
     ///
     /// Check out the `async_batch_read` example for usage details
     pub async fn find_batched<T: TypedCouchDocument>(
@@ -412,29 +1234,22 @@ impl Database {
     ) -> CouchResult<u64> {
         let mut bookmark = Option::None;
         let limit = if batch_size > 0 { batch_size } else { 1000 };
+        let total_limit = query.limit.take();
 
         let mut results: u64 = 0;
-        query.limit = Option::Some(limit);
 
         let maybe_err = loop {
-            let mut segment_query = query.clone();
-            segment_query.bookmark.clone_from(&bookmark);
-            let all_docs = match self.find(&segment_query).await {
-                Ok(docs) => docs,
-                Err(err) => break Some(err),
+            query.limit = match total_limit {
+                Some(total) if results >= total => break None,
+                Some(total) => Some(limit.min(total - results)),
+                None => Some(limit),
             };
 
-            if all_docs.total_rows == 0 {
-                // no more rows
-                break None;
-            }
-
-            if all_docs.bookmark.is_some() && all_docs.bookmark != bookmark {
-                bookmark.replace(all_docs.bookmark.clone().unwrap_or_default());
-            } else {
-                // no bookmark, break the query loop
-                break None;
-            }
+            let all_docs = match self.find_next_page(&query, &mut bookmark).await {
+                Ok(Some(docs)) => docs,
+                Ok(None) => break None,
+                Err(err) => break Some(err),
+            };
 
             results += u64::from(all_docs.total_rows);
 
@@ -454,6 +1269,267 @@ impl Database {
         }
     }
 
+    /// Finds all documents matching the query, following the bookmarks until the results are exhausted,
+    /// and collects them into a single `Vec`. Documents are requested in pages of `page_size`; a value of 0
+    /// means the default page size of 1000 is used. A `limit` on the query caps the total number of results,
+    /// and a `bookmark` on the query is used as the starting point.
+    ///
+    /// Use `find_batched` instead to process very large result sets without holding them in memory.
+    pub async fn find_all<T: TypedCouchDocument>(&self, mut query: FindQuery, page_size: u64) -> CouchResult<Vec<T>> {
+        let max_results = query.limit.take();
+        let page_size = if page_size > 0 { page_size } else { 1000 };
+        let mut bookmark = query.bookmark.take();
+        let mut results: Vec<T> = vec![];
+
+        loop {
+            query.limit = match max_results {
+                Some(max) => Some(page_size.min(max - results.len() as u64)),
+                None => Some(page_size),
+            };
+
+            match self.find_next_page(&query, &mut bookmark).await? {
+                Some(page) => results.extend(page.rows),
+                None => break,
+            }
+
+            if max_results.is_some_and(|max| results.len() as u64 >= max) {
+                break;
+            }
+        }
+
+        Ok(results)
+    }
+
+    /// Finds documents matching the query as a stream, following the bookmarks page by page, and skips
+    /// documents that were already returned. Documents that are updated while iterating can show up in a
+    /// later page again (e.g. when the updated field is part of the index); those repeats are dropped.
+    /// Pages are requested lazily, of `batch_size` documents each; a value of 0 means the default
+    /// `batch_size` of 1000 is used. A `bookmark` on the query is used as the starting point, and a `limit`
+    /// on the query caps the total number of returned documents.
+    ///
+    /// *Note*: the ids of the returned documents are kept in a `HashSet` for the lifetime of the stream,
+    /// so memory grows with the number of results. `max_tracked` bounds the number of ids kept: once it
+    /// is reached, the oldest ids are forgotten, and repeats of those documents are no longer detected.
+    /// A `max_tracked` of 0 means all ids are kept.
+    ///
+    /// Usage:
+    /// ```
+    /// use couch_rs::types::find::FindQuery;
+    /// use couch_rs::error::CouchResult;
+    /// use futures_util::StreamExt;
+    /// use serde_json::{json, Value};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> CouchResult<()> {
+    ///     let client = couch_rs::Client::new_local_test()?;
+    ///     let db = client.db("test_db").await?;
+    ///     let query = FindQuery::new(json!({"rank": {"$gte": 0}}));
+    ///     let mut docs = Box::pin(db.find_stream_dedup::<Value>(query, 100, 0));
+    ///     while let Some(doc) = docs.next().await {
+    ///         println!("{}", doc?);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn find_stream_dedup<'a, T: TypedCouchDocument + 'a>(
+        &'a self,
+        mut query: FindQuery,
+        batch_size: u64,
+        max_tracked: usize,
+    ) -> impl Stream<Item = CouchResult<T>> + 'a {
+        let page_size = if batch_size > 0 { batch_size } else { 1000 };
+        let remaining = query.limit.take();
+        let bookmark = query.bookmark.take();
+        let state = DedupState {
+            query,
+            bookmark,
+            page_size,
+            remaining,
+            buffer: VecDeque::new(),
+            seen: SeenIds::new(max_tracked),
+            done: false,
+        };
+
+        futures_util::stream::unfold(state, move |mut state| async move {
+            loop {
+                if state.remaining == Some(0) {
+                    return None;
+                }
+                if let Some(doc) = state.buffer.pop_front() {
+                    state.remaining = state.remaining.map(|remaining| remaining - 1);
+                    return Some((Ok(doc), state));
+                }
+                if state.done {
+                    return None;
+                }
+
+                state.query.limit = Some(match state.remaining {
+                    Some(remaining) => state.page_size.min(remaining),
+                    None => state.page_size,
+                });
+                match self.find_next_page::<T>(&state.query, &mut state.bookmark).await {
+                    Ok(Some(page)) => {
+                        let seen = &mut state.seen;
+                        state
+                            .buffer
+                            .extend(page.rows.into_iter().filter(|doc| seen.insert(&doc.get_id())));
+                    }
+                    Ok(None) => state.done = true,
+                    Err(err) => {
+                        state.done = true;
+                        return Some((Err(err), state));
+                    }
+                }
+            }
+        })
+    }
+
+    /// Streams every leaf revision of every document in the database, including conflicting revisions,
+    /// deleted tombstones and design documents, for a lossless backup. Each revision carries its
+    /// `_revisions` history, so the dump can be written back as-is with `new_edits=false`, like
+    /// `Client::import_from` does.
+    ///
+    /// The documents are found through `_changes?style=all_docs`, which lists all leaf revisions of a
+    /// document (unlike `_all_docs`, which only knows the winners); the leaves themselves are then fetched
+    /// with `open_revs=all`, one request per document. The stream ends after the first error.
+    ///
+    /// *Note*: attachments are included as stubs, without their data.
+    ///
+    /// Usage:
+    /// ```
+    /// use couch_rs::error::CouchResult;
+    /// use futures_util::StreamExt;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> CouchResult<()> {
+    ///     let client = couch_rs::Client::new_local_test()?;
+    ///     let db = client.db("test_db").await?;
+    ///     let mut dump = Box::pin(db.dump());
+    ///     while let Some(doc) = dump.next().await {
+    ///         println!("{}", doc?);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn dump(&self) -> impl Stream<Item = CouchResult<Value>> + '_ {
+        let mut params = HashMap::new();
+        params.insert(s!("feed"), s!("continuous"));
+        params.insert(s!("timeout"), s!("0"));
+        params.insert(s!("style"), s!("all_docs"));
+        let changes = ChangesStream::with_params(self._client.clone(), self.name.clone(), None, params);
+
+        futures_util::stream::unfold(
+            (Some(changes), VecDeque::new()),
+            move |(mut changes, mut leaves)| async move {
+                loop {
+                    if let Some(leaf) = leaves.pop_front() {
+                        return Some((Ok(leaf), (changes, leaves)));
+                    }
+                    let change = match changes.as_mut()?.next().await? {
+                        Ok(change) => change,
+                        Err(err) => return Some((Err(err), (None, leaves))),
+                    };
+                    match self.get_leaf_values(&change.id, true).await {
+                        Ok(values) => leaves.extend(values),
+                        // purged after the change was listed
+                        Err(err) if err.is_not_found() => {}
+                        Err(err) => return Some((Err(err), (None, leaves))),
+                    }
+                }
+            },
+        )
+    }
+
+    /// Exports all documents of the database as NDJSON: one document per line, including its `_id` and
+    /// `_rev`. Returns the number of exported documents. Use `Client::import_from` to restore the export,
+    /// e.g. into a fresh database for test fixtures.
+    ///
+    /// Attachments are exported inline, base64 encoded, so the exported revisions are complete; documents with
+    /// attachments are fetched one by one to get their data.
+    ///
+    /// *Note*: design documents are not exported, and only the winning revision of each document is. The
+    /// writer is written to synchronously; wrap it in a `BufWriter` when appropriate.
+    pub async fn export_to<W: Write>(&self, mut writer: W) -> CouchResult<u64> {
+        let write_error = |err: std::io::Error| {
+            CouchError::new(
+                format!("can not write export: {err}"),
+                StatusCode::INTERNAL_SERVER_ERROR,
+            )
+        };
+
+        let query = FindQuery::find_all().limit(1000);
+        let mut bookmark = None;
+        let mut exported = 0;
+        while let Some(page) = self.find_next_page::<Value>(&query, &mut bookmark).await? {
+            for doc in page.rows {
+                // the revision is imported as-is, so it needs the attachment data instead of the stubs
+                let doc = if doc.get(ATTACHMENTS_FIELD).is_some() {
+                    let mut params = HashMap::new();
+                    params.insert(s!("attachments"), s!("true"));
+                    match self.get_value(&doc.get_id(), Some(&params)).await {
+                        Ok(doc) => doc,
+                        // deleted after the page was listed
+                        Err(err) if err.is_not_found() => continue,
+                        Err(err) => return Err(err),
+                    }
+                } else {
+                    doc
+                };
+                writeln!(writer, "{}", to_string(&doc)?).map_err(write_error)?;
+                exported += 1;
+            }
+        }
+        writer.flush().map_err(write_error)?;
+
+        Ok(exported)
+    }
+
+    /// Writes documents as-is, including their `_rev`, with `new_edits=false`. This is how replication
+    /// writes documents: the revisions are preserved instead of new revisions being created.
+    pub(crate) async fn bulk_docs_replicated(&self, docs: &[Value]) -> CouchResult<()> {
+        let body = json!({ "docs": docs, "new_edits": false });
+        let response = self
+            ._client
+            .post(&self.create_raw_path("_bulk_docs"), to_string(&body)?)
+            .send()
+            .await?
+            .couch_error_for_status()
+            .await?;
+
+        // only documents that could not be written are listed in the response
+        let data: Vec<DocumentCreatedResponse> = response.couch_json().await?;
+        for result in data {
+            if result.error.is_some() {
+                DocumentCreatedResult::from(result)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Requests the page of `query` that follows `bookmark`, and advances the bookmark.
+    /// Returns `None` when there are no more results.
+    async fn find_next_page<T: TypedCouchDocument>(
+        &self,
+        query: &FindQuery,
+        bookmark: &mut Option<String>,
+    ) -> CouchResult<Option<DocumentCollection<T>>> {
+        let segment_query = FindQueryRef::from(query).bookmark(bookmark.as_deref());
+        let all_docs: DocumentCollection<T> = self.find(segment_query).await?;
+
+        if all_docs.total_rows == 0 {
+            // no more rows
+            return Ok(None);
+        }
+
+        if all_docs.bookmark.is_some() && all_docs.bookmark != *bookmark {
+            bookmark.clone_from(&all_docs.bookmark);
+            Ok(Some(all_docs))
+        } else {
+            // no bookmark, stop iterating
+            Ok(None)
+        }
+    }
+
     /// Executes multiple specified built-in view queries of all documents in this database.
     /// This enables you to request multiple queries in a single request, in place of multiple POST /{db}/_`all_docs` requests.
     /// [More information](https://docs.couchdb.org/en/stable/api/database/bulk-api.html#sending-multiple-queries-to-a-database)
@@ -505,21 +1581,39 @@ impl Database {
             .await
     }
 
-    /// Executes multiple queries against a view.
-    pub async fn query_many(
+    /// Executes multiple queries against a view, in a single request. The keys of the queries can be of any
+    /// type the view emits, e.g. numbers:
+    ///
+    /// ```
+    /// use couch_rs::error::CouchResult;
+    /// use couch_rs::types::query::{QueriesParams, QueryParams};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> CouchResult<()> {
+    ///     let client = couch_rs::Client::new_local_test()?;
+    ///     let db = client.db("sales_db").await?;
+    ///     let queries = QueriesParams::new(vec![
+    ///         QueryParams::default().start_key(2022).end_key(2022),
+    ///         QueryParams::default().start_key(2023).end_key(2023),
+    ///     ]);
+    ///     let results = db.query_many("reports", "sales_by_year", queries).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn query_many<K: Serialize + PartialEq + std::fmt::Debug + Clone>(
         &self,
         design_name: &str,
         view_name: &str,
-        queries: QueriesParams,
+        queries: QueriesParams<K>,
     ) -> CouchResult<Vec<ViewCollection<Value, Value, Value>>> {
         self.query_view_many(&self.create_query_view_path(design_name, view_name), queries)
             .await
     }
 
-    async fn query_view_many(
+    async fn query_view_many<K: Serialize + PartialEq + std::fmt::Debug + Clone>(
         &self,
         view_path: &str,
-        queries: QueriesParams,
+        queries: QueriesParams<K>,
     ) -> CouchResult<Vec<ViewCollection<Value, Value, Value>>> {
         // we use POST here, because this allows for a larger set of keys to be provided, compared
         // to a GET call. It provides the same functionality
@@ -528,7 +1622,8 @@ impl Database {
             .post(view_path, js!(&queries))
             .send()
             .await?
-            .error_for_status()?;
+            .couch_error_for_status()
+            .await?;
 
         let results: QueriesCollection<Value, Value, Value> = response.json().await?;
         Ok(results.results)
@@ -558,7 +1653,8 @@ impl Database {
             .post(&self.create_raw_path("_all_docs"), js!(&options))
             .send()
             .await?
-            .error_for_status()?;
+            .couch_error_for_status()
+            .await?;
 
         Ok(DocumentCollection::new(response.couch_json().await?))
     }
@@ -588,6 +1684,7 @@ impl Database {
     }
 
     /// Finds a document in the database through a Mango query.
+    /// Accepts a `&FindQuery`, or a [`FindQueryRef`] to send a query without owning (and cloning) its selector.
     ///
     /// Usage:
     /// ```
@@ -621,11 +1718,15 @@ impl Database {
     ///     Ok(())
     /// }
     /// ```
-    pub async fn find<T: TypedCouchDocument>(&self, query: &FindQuery) -> CouchResult<DocumentCollection<T>> {
+    pub async fn find<'q, T: TypedCouchDocument>(
+        &self,
+        query: impl Into<FindQueryRef<'q>>,
+    ) -> CouchResult<DocumentCollection<T>> {
+        let query = query.into();
         let path = self.create_raw_path("_find");
-        let response = self._client.post(&path, js!(query)).send().await?;
+        let response = self._client.post(&path, js!(&query)).send().await?;
         let status = response.status();
-        let data: FindResult<T> = response.couch_json().await?;
+        let data: FindResult<Value> = response.couch_json().await?;
 
         if let Some(doc_val) = data.docs {
             let documents: Vec<T> = doc_val
@@ -635,7 +1736,8 @@ impl Database {
                     let id: String = d.get_id().into_owned();
                     !id.starts_with('_')
                 })
-                .collect();
+                .map(from_document_value)
+                .collect::<CouchResult<_>>()?;
 
             let mut bookmark = Option::None;
             let returned_bookmark = data.bookmark.unwrap_or_default();
@@ -647,13 +1749,134 @@ impl Database {
 
             Ok(DocumentCollection::new_from_documents(documents, bookmark))
         } else if let Some(err) = data.error {
+            let sort_fields = query.sort_fields();
+            if err == "no_usable_index" && !sort_fields.is_empty() {
+                return Err(CouchError::NoUsableIndex(NoUsableIndexDetails {
+                    status,
+                    sort_fields,
+                    message: data.reason.unwrap_or(err),
+                }));
+            }
             Err(CouchError::new(err, status))
         } else {
             Ok(DocumentCollection::default())
         }
     }
 
-    /// Saves a document to `CouchDB`. When the provided document includes both an `_id` and a `_rev`
+    /// Finds the single document matching the query, for lookups by a field that should be unique.
+    /// Returns `None` when no document matches, and an error with status `409 Conflict` when more than one
+    /// does. To detect this, the query is always executed with a limit of 2: a `limit` set on the query is
+    /// overridden.
+    ///
+    /// Usage:
+    /// ```
+    /// use couch_rs::error::CouchResult;
+    /// use couch_rs::types::find::FindQuery;
+    /// use serde_json::{json, Value};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> CouchResult<()> {
+    ///     let client = couch_rs::Client::new_local_test()?;
+    ///     let db = client.db("user_db").await?;
+    ///     let query = FindQuery::new(json!({"email": "jdoe@example.com"}));
+    ///     let user: Option<Value> = db.find_one(&query).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn find_one<'q, T: TypedCouchDocument>(
+        &self,
+        query: impl Into<FindQueryRef<'q>>,
+    ) -> CouchResult<Option<T>> {
+        let query = query.into().limit(2);
+        let mut docs: DocumentCollection<T> = self.find(query).await?;
+        if docs.rows.len() > 1 {
+            return Err(CouchError::new(
+                s!("expected at most one document, but the query matched more"),
+                StatusCode::CONFLICT,
+            ));
+        }
+        Ok(docs.rows.pop())
+    }
+
+    /// Finds documents through a Mango query, and deserializes only the fields selected by the `fields`
+    /// of the query into `P`. Unlike `find`, `P` does not need to be a `TypedCouchDocument`, so it can
+    /// leave out the `_id` and `_rev`.
+    ///
+    /// Usage:
+    /// ```
+    /// use couch_rs::types::find::FindQuery;
+    /// use couch_rs::error::CouchResult;
+    /// use serde::Deserialize;
+    /// use serde_json::json;
+    ///
+    /// #[derive(Deserialize)]
+    /// pub struct LastName {
+    ///     pub last_name: String,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> CouchResult<()> {
+    ///     let client = couch_rs::Client::new_local_test()?;
+    ///     let db = client.db("user_db").await?;
+    ///     let query = FindQuery::new(json!({"first_name": "John"})).fields(vec!["last_name".to_string()]);
+    ///     let last_names: Vec<LastName> = db.find_projected(&query).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn find_projected<'q, P: DeserializeOwned>(
+        &self,
+        query: impl Into<FindQueryRef<'q>>,
+    ) -> CouchResult<Vec<P>> {
+        let docs: DocumentCollection<Value> = self.find(query).await?;
+        docs.rows
+            .into_iter()
+            .map(|doc| from_value(doc).map_err(CouchError::from))
+            .collect()
+    }
+
+    /// Shows which index would be used by `CouchDB` to execute the query, without executing it.
+    /// See [_explain](https://docs.couchdb.org/en/stable/api/database/find.html#db-explain) for more details.
+    pub async fn explain(&self, query: &FindQuery) -> CouchResult<ExplainResult> {
+        let path = self.create_raw_path("_explain");
+        let response = self
+            ._client
+            .post(&path, js!(query))
+            .send()
+            .await?
+            .couch_error_for_status()
+            .await?;
+        response.couch_json().await
+    }
+
+    /// Fails when the query can not use an index, and would fall back to a full scan
+    /// of the built-in `_all_docs` primary index.
+    ///
+    /// This is a lint-style helper, meant to be used in tests to catch unindexed queries early:
+    /// ```
+    /// use couch_rs::types::find::FindQuery;
+    /// use couch_rs::error::CouchResult;
+    /// use serde_json::json;
+    ///
+    /// #[tokio::test]
+    /// async fn should_use_an_index() -> CouchResult<()> {
+    ///     let client = couch_rs::Client::new_local_test()?;
+    ///     let db = client.db("test_db").await?;
+    ///     db.assert_indexed(&FindQuery::new(json!({"first_name": "John"}))).await
+    /// }
+    /// ```
+    pub async fn assert_indexed(&self, query: &FindQuery) -> CouchResult<()> {
+        let explained = self.explain(query).await?;
+        if explained.index.name == "_all_docs" {
+            return Err(CouchError::new(
+                format!("query is not indexed: {}", explained.selector),
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Saves a document to `CouchDB`. When the provided document includes both an `_id` and a `_rev`
     /// `CouchDB` will attempt to update the document. When only an `_id` is provided, the `save`
     /// method behaves like `create` and will attempt to create the document.
     ///
@@ -708,19 +1931,32 @@ impl Database {
         let id = doc.get_id().to_string();
         let body = to_string(&doc)?;
         let response = self._client.put(&self.create_document_path(&id), body).send().await?;
-        let status = response.status();
-        let data: DocumentCreatedResponse = response.json().await?;
+        let (status, data) = document_created_response(response, Some(&id)).await?;
 
         if let (Some(true), Some(id), Some(rev)) = (data.ok, data.id, data.rev) {
             doc.set_id(&id);
             doc.set_rev(&rev);
             Ok(DocumentCreatedDetails { id, rev })
+        } else if status == StatusCode::CONFLICT && doc.get_rev().is_empty() {
+            Err(CouchError::new_with_id(
+                Some(id),
+                s!("document update conflict: a document with this id already exists, and a _rev is required to update it; use `upsert` to update without a _rev, or `create` to store a new document"),
+                status,
+            ))
         } else {
-            let err = data.error.unwrap_or_else(|| s!("unspecified error"));
-            Err(CouchError::new(err, status))
+            let err = data.error.clone().unwrap_or_else(|| s!("unspecified error"));
+            Err(CouchError::new(err, status).with_couch_error(data.error))
         }
     }
 
+    /// Saves a document like `save`, and returns both the revision the document had before saving
+    /// and the new revision, as `(old_rev, new_rev)`. The old revision is empty for a new document.
+    pub async fn save_returning_revs<T: TypedCouchDocument>(&self, doc: &mut T) -> CouchResult<(String, String)> {
+        let old_rev = doc.get_rev().into_owned();
+        let details = self.save(doc).await?;
+        Ok((old_rev, details.rev))
+    }
+
     /// Creates a document from a raw JSON document Value.
     /// Usage:
     ///
@@ -751,9 +1987,7 @@ impl Database {
     pub async fn create<T: TypedCouchDocument>(&self, doc: &mut T) -> DocumentCreatedResult {
         let value = to_create_value(doc)?;
         let response = self._client.post(&self.name, to_string(&value)?).send().await?;
-
-        let status = response.status();
-        let data: DocumentCreatedResponse = response.json().await?;
+        let (status, data) = document_created_response(response, None).await?;
 
         if let Some(true) = data.ok {
             let id = data.id.ok_or_else(|| CouchError::new(s!("invalid id"), status))?;
@@ -763,11 +1997,119 @@ impl Database {
             doc.set_rev(&rev);
             Ok(DocumentCreatedDetails { id, rev })
         } else {
-            let err = data.error.unwrap_or_else(|| s!("unspecified error"));
-            Err(CouchError::new(err, status))
+            let err = data.error.clone().unwrap_or_else(|| s!("unspecified error"));
+            Err(CouchError::new(err, status).with_couch_error(data.error))
+        }
+    }
+
+    /// Creates a document under the given id, with a `PUT` to `/{db}/{id}`. Any `_id` or `_rev` on
+    /// the document itself is ignored; on success the id and new revision are set on the document.
+    /// Fails with a conflict when a document with this id already exists.
+    ///
+    /// Usage:
+    ///
+    /// ```
+    /// use couch_rs::error::CouchResult;
+    /// use serde_json::json;
+    ///
+    /// const TEST_DB: &str = "test_db";
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> CouchResult<()> {
+    ///     let client = couch_rs::Client::new_local_test()?;
+    ///     let db = client.db(TEST_DB).await?;
+    ///     let mut doc = json!({"first_name": "John"});
+    ///
+    ///     let details = db.create_with_id("john", &mut doc).await?;
+    ///     assert_eq!(details.id, "john");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn create_with_id<T: TypedCouchDocument>(&self, id: &str, doc: &mut T) -> DocumentCreatedResult {
+        let mut value = get_value_map(doc)?;
+        value.remove(ID_FIELD);
+        value.remove(REV_FIELD);
+        let response = self
+            ._client
+            .put(&self.create_document_path(id), to_string(&value)?)
+            .send()
+            .await?;
+        let (status, data) = document_created_response(response, Some(id)).await?;
+
+        if let (Some(true), Some(id), Some(rev)) = (data.ok, data.id, data.rev) {
+            doc.set_id(&id);
+            doc.set_rev(&rev);
+            Ok(DocumentCreatedDetails { id, rev })
+        } else {
+            let err = data.error.clone().unwrap_or_else(|| s!("unspecified error"));
+            Err(CouchError::new_with_id(Some(s!(id)), err, status).with_couch_error(data.error))
         }
     }
 
+    /// Moves a document to a new id: copies its current revision to `new_id` with a server-side `COPY`, then
+    /// deletes the original. Attachments and all other fields are preserved. Fails with a `409 Conflict`
+    /// when a document with `new_id` already exists, in which case the original is left untouched.
+    ///
+    /// The two steps are not atomic: when deleting the original fails, both documents exist and the
+    /// error is returned.
+    ///
+    /// Usage:
+    /// ```
+    /// use couch_rs::error::CouchResult;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> CouchResult<()> {
+    ///     let client = couch_rs::Client::new_local_test()?;
+    ///     let db = client.db("test_db").await?;
+    ///     let details = db.move_doc("jdoe", "john.doe").await?;
+    ///     assert_eq!(details.id, "john.doe");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn move_doc(&self, old_id: &str, new_id: &str) -> DocumentCreatedResult {
+        let old_rev = self.head_rev(old_id).await?.ok_or_else(|| {
+            CouchError::new_with_id(Some(s!(old_id)), s!("document not found"), StatusCode::NOT_FOUND)
+        })?;
+
+        // copy the revision that is deleted afterwards, in case the document is updated in the meantime
+        let mut h = HashMap::new();
+        h.insert(s!("rev"), old_rev.clone());
+        // encoded, so non-ascii ids and a `?` (which starts a destination revision) are sent intact
+        let destination = utf8_percent_encode(new_id, NON_ALPHANUMERIC).to_string();
+        let response = self
+            ._client
+            .copy(&self.create_document_path(old_id), Some(&h))
+            .header("Destination", destination)
+            .send()
+            .await?;
+        let (status, data) = document_created_response(response, Some(new_id)).await?;
+        let details = match (data.ok, data.id, data.rev) {
+            (Some(true), Some(id), Some(rev)) => DocumentCreatedDetails { id, rev },
+            _ if status == StatusCode::CONFLICT => {
+                return Err(CouchError::new_with_id(
+                    Some(s!(new_id)),
+                    format!("can not move document {old_id}: a document with id {new_id} already exists"),
+                    StatusCode::CONFLICT,
+                )
+                .with_couch_error(data.error));
+            }
+            _ => {
+                let err = data.error.clone().unwrap_or_else(|| s!("unspecified error"));
+                return Err(CouchError::new_with_id(Some(s!(old_id)), err, status).with_couch_error(data.error));
+            }
+        };
+
+        let mut h = HashMap::new();
+        h.insert(s!("rev"), old_rev);
+        self._client
+            .delete(&self.create_document_path(old_id), Some(&h))
+            .send()
+            .await?
+            .couch_error_for_status()
+            .await?;
+        Ok(details)
+    }
+
     /// The upsert function combines a `get` with a `save` function. If the document with the
     /// provided `_id` can be found it will be merged with the provided Document's value, otherwise
     /// the document will be created.
@@ -824,10 +2166,53 @@ impl Database {
         }
     }
 
+    /// Updates a document in place: fetches the current document, applies `patch` to it, and saves it.
+    /// When the document is modified in the meantime, the save fails with a conflict and the whole cycle is
+    /// repeated on the new revision, up to 10 times; so `patch` can be called more than once, and should
+    /// only depend on the document it is given. The `_id` and `_rev` are restored after `patch` is applied.
+    ///
+    /// Usage:
+    /// ```
+    /// use couch_rs::error::CouchResult;
+    /// use serde_json::json;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> CouchResult<()> {
+    ///     let client = couch_rs::Client::new_local_test()?;
+    ///     let db = client.db("test_db").await?;
+    ///     let details = db
+    ///         .patch("counter", |doc| {
+    ///             let count = doc["count"].as_u64().unwrap_or(0);
+    ///             doc["count"] = json!(count + 1);
+    ///         })
+    ///         .await?;
+    ///     println!("updated to {}", details.rev);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn patch<F: Fn(&mut Value)>(&self, id: &str, patch: F) -> DocumentCreatedResult {
+        let mut attempt = 0;
+        loop {
+            let mut doc = self.get_value(id, None).await?;
+            let rev = doc.get_rev().into_owned();
+            patch(&mut doc);
+            doc.set_id(id);
+            doc.set_rev(&rev);
+
+            match self.save(&mut doc).await {
+                Err(err) if err.status() == Some(StatusCode::CONFLICT) && attempt < PATCH_MAX_RETRIES => {
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
     /// Bulk upsert a list of documents.
     ///
-    /// This will first fetch the latest rev for each document that does not have a rev set. It
-    /// will then insert all documents into the database.
+    /// This will first fetch the latest rev for each document that does not have a rev set, in chunks
+    /// of 1000 documents. Documents that do not exist yet will be created. It will then insert all
+    /// documents into the database.
     pub async fn bulk_upsert<T: TypedCouchDocument + Clone>(
         &self,
         docs: &mut [T],
@@ -840,25 +2225,22 @@ impl Database {
             }
         }
 
-        // Fetch the latest rev for the docs that do not have a rev set.
+        // Fetch the latest rev for the docs that do not have a rev set. This is done in chunks, to
+        // prevent a single huge _all_docs request for large sets of documents.
+        // Revs are matched by id, so documents that do not exist yet are simply created.
         let ids_without_rev: Vec<String> = docs_without_rev.iter().map(|(id, _)| id.to_string()).collect();
-        let bulk_get = self.get_bulk::<Value>(ids_without_rev).await?;
-        for (req_idx, (sent_id, doc_idx)) in docs_without_rev.iter().enumerate() {
-            let result = bulk_get.get_data().get(req_idx);
-            let rev = match result {
-                Some(doc) if doc.get_id().as_ref() == sent_id => doc.get_rev().to_string(),
-                _ => {
-                    return Err(CouchError::new(
-                        "Response does not match request".to_string(),
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                    ));
-                }
-            };
+        let bulk_get = self
+            .bulk_get_chunked::<Value>(ids_without_rev, BULK_UPSERT_CHUNK_SIZE)
+            .await?;
+        let revs: HashMap<DocumentId, String> = bulk_get
+            .rows
+            .iter()
+            .map(|doc| (doc.get_id().into_owned(), doc.get_rev().into_owned()))
+            .collect();
 
-            if let Some(docs) = docs.get_mut(*doc_idx) {
-                docs.set_rev(&rev);
-            } else {
-                // todo: do we need a warning here?
+        for (sent_id, doc_idx) in &docs_without_rev {
+            if let (Some(rev), Some(doc)) = (revs.get(sent_id), docs.get_mut(*doc_idx)) {
+                doc.set_rev(rev);
             }
         }
 
@@ -914,6 +2296,129 @@ impl Database {
         }
     }
 
+    /// Creates or updates the views of a design document, keeping anything else in it, like update functions.
+    /// Nothing is written when the design document already has exactly these views, so this is cheap to call
+    /// at every startup. Returns whether the design document was written.
+    pub async fn upsert_views(&self, design_name: &str, views: &CouchViews) -> CouchResult<bool> {
+        let path = self.create_design_path(design_name);
+        let new_design = serde_json::to_value(views)?;
+        let mut design = match self
+            ._client
+            .get(&path, None)
+            .send()
+            .await?
+            .couch_error_for_status()
+            .await
+        {
+            Ok(response) => response.couch_json().await?,
+            Err(err) if err.is_not_found() => json!({}),
+            Err(err) => return Err(err),
+        };
+
+        if design.get("views") == new_design.get("views") && design.get("language") == new_design.get("language") {
+            return Ok(false);
+        }
+        design["views"] = new_design["views"].clone();
+        design["language"] = new_design["language"].clone();
+        self.create_view(design_name, design).await?;
+        Ok(true)
+    }
+
+    /// Installs a complete design document, with its views, filters, update functions and
+    /// `validate_doc_update` function, in a single request. An existing design document with the same name
+    /// is replaced entirely. See [`DesignDocument`] for an example.
+    pub async fn create_design(&self, design_name: &str, design: DesignDocument) -> CouchResult<DesignCreated> {
+        let mut doc: Value = design.into();
+        if let Some(rev) = self.head_rev_at(&self.create_design_path(design_name)).await? {
+            doc[REV_FIELD] = Value::String(rev);
+        }
+        self.create_view(design_name, doc).await
+    }
+
+    /// Installs update functions in a design document, so they can be called through `execute_update`.
+    /// Views and update functions already present in the design document are preserved.
+    ///
+    /// Usage:
+    /// ```
+    /// use couch_rs::types::view::CouchUpdate;
+    /// use couch_rs::error::CouchResult;
+    ///
+    /// const TEST_DB: &str = "test_db";
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> CouchResult<()> {
+    ///     let client = couch_rs::Client::new_local_test()?;
+    ///     let db = client.db(TEST_DB).await?;
+    ///
+    ///     let update = CouchUpdate::new("touch", "function (doc, req) { doc.touched = true; return [doc, 'ok']; }");
+    ///     db.create_update_functions("clip_design", update).await?;
+    ///     db.execute_update("clip_design", "touch", "some_doc_id", None).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn create_update_functions(&self, design_name: &str, updates: CouchUpdate) -> CouchResult<DesignCreated> {
+        let response = self
+            ._client
+            .get(&self.create_design_path(design_name), None)
+            .send()
+            .await?;
+
+        let mut design: Value = if response.status() == StatusCode::NOT_FOUND {
+            json!({})
+        } else {
+            response.couch_error_for_status().await?.couch_json().await?
+        };
+
+        let updates: Value = updates.into();
+        if !design["updates"].is_object() {
+            design["updates"] = json!({});
+        }
+        if let (Some(existing), Some(new)) = (design["updates"].as_object_mut(), updates["updates"].as_object()) {
+            existing.extend(new.clone());
+        }
+
+        self.create_view(design_name, design).await
+    }
+
+    /// Lists the names of all design documents in the database, without the `_design/` prefix; e.g. the
+    /// `design_name`s to pass to `query`.
+    ///
+    /// Usage:
+    /// ```
+    /// use couch_rs::error::CouchResult;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> CouchResult<()> {
+    ///     let client = couch_rs::Client::new_local_test()?;
+    ///     let db = client.db("test_db").await?;
+    ///     for design in db.list_designs().await? {
+    ///         println!("_design/{design}");
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn list_designs(&self) -> CouchResult<Vec<DocumentId>> {
+        // all design document ids sort between these keys
+        let options = QueryParams::default()
+            .start_key(s!(DESIGN_PREFIX))
+            .end_key(s!("_design0"))
+            .include_docs(false);
+        let response = self
+            ._client
+            .post(&self.create_raw_path("_all_docs"), js!(&options))
+            .send()
+            .await?
+            .couch_error_for_status()
+            .await?;
+
+        let all_docs: AllDocsResponse<Value> = response.couch_json().await?;
+        Ok(all_docs
+            .rows
+            .into_iter()
+            .filter_map(|row| row.id?.strip_prefix(DESIGN_PREFIX).map(String::from))
+            .collect())
+    }
+
     /// Executes a query against a view, returning untyped Values
     pub async fn query_raw(
         &self,
@@ -981,67 +2486,258 @@ impl Database {
         view_name: &str,
         mut options: Option<QueryParams<K>>,
     ) -> CouchResult<ViewCollection<K, V, T>> {
-        if options.is_none() {
-            options = Some(QueryParams::default());
+        match &options {
+            Some(options) => options.validate()?,
+            None => options = Some(QueryParams::default()),
         }
 
         self._client
             .post(&self.create_query_view_path(design_name, view_name), js!(&options))
             .send()
             .await?
-            .error_for_status()?
-            .json()
-            .await
-            .map_err(CouchError::from)
-    }
-
-    /// Executes an update function.
-    pub async fn execute_update(
-        &self,
-        design_id: &str,
-        name: &str,
-        document_id: &str,
-        body: Option<Value>,
-    ) -> CouchResult<String> {
-        let body = match body {
-            Some(v) => to_string(&v)?,
-            None => String::default(),
-        };
-
-        self._client
-            .put(&self.create_execute_update_path(design_id, name, document_id), body)
-            .send()
+            .couch_error_for_status()
             .await?
-            .error_for_status()?
-            .text()
+            .json()
             .await
             .map_err(CouchError::from)
     }
 
-    /// Removes a document from the database. Returns success in a `bool`
+    /// Executes a query against a reduce view, grouping the results at the given `group_level`.
+    /// This is useful for views with array keys like `[year, month, day]`, where a `group_level` of 1
+    /// reduces per year, 2 per month, etc. The optional `range` is used as the `start_key` and `end_key`
+    /// of the query, so it can be used to page through the grouped keys.
+    ///
     /// Usage:
     /// ```
-    /// use couch_rs::types::find::FindQuery;
-    /// use serde_json::{from_value, to_value, Value};
-    /// use couch_rs::types::document::DocumentId;
     /// use couch_rs::error::CouchResult;
-    ///
-    /// const TEST_DB: &str = "test_db";
+    /// use couch_rs::types::view::RawViewCollection;
+    /// use serde_json::{json, Value};
     ///
     /// #[tokio::main]
     /// async fn main() -> CouchResult<()> {
     ///     let client = couch_rs::Client::new_local_test()?;
-    ///     let db = client.db(TEST_DB).await?;
-    ///
-    ///     // first we need to get the document, because we need both the _id and _rev in order
-    ///     // to delete
-    ///     if let Some(doc) = db.get::<Value>("123").await.ok() {
-    ///         db.remove(&doc).await;
+    ///     let db = client.db("sales_db").await?;
+    ///
+    ///     // totals per month of 2023
+    ///     let result: RawViewCollection<Value, u64> = db
+    ///         .query_grouped("reports", "sales_by_date", 2, Some((json!([2023]), json!([2023, {}]))))
+    ///         .await?;
+    ///     for row in result.rows {
+    ///         println!("{}: {}", row.key, row.value);
     ///     }
-    ///
     ///     Ok(())
     /// }
-    ///```
+    /// ```
+    pub async fn query_grouped<
+        K: Serialize + DeserializeOwned + PartialEq + std::fmt::Debug + Clone,
+        V: DeserializeOwned,
+    >(
+        &self,
+        design_name: &str,
+        view_name: &str,
+        group_level: u32,
+        range: Option<(K, K)>,
+    ) -> CouchResult<RawViewCollection<K, V>> {
+        let mut options = QueryParams::default().reduce(true).group_level(group_level);
+        if let Some((start_key, end_key)) = range {
+            options = options.start_key(start_key).end_key(end_key);
+        }
+        self.query(design_name, view_name, Some(options)).await
+    }
+
+    /// Executes a query against a view that has a reduce function, reduced over all rows, and returns the
+    /// single reduced value; `None` when the view has no rows. Handy for "total count" views.
+    ///
+    /// Usage:
+    /// ```
+    /// use couch_rs::error::CouchResult;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> CouchResult<()> {
+    ///     let client = couch_rs::Client::new_local_test()?;
+    ///     let db = client.db("sales_db").await?;
+    ///     let total: u64 = db.query_reduce_value("reports", "count").await?.unwrap_or_default();
+    ///     println!("{total} sales");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn query_reduce_value<V: DeserializeOwned>(
+        &self,
+        design_name: &str,
+        view_name: &str,
+    ) -> CouchResult<Option<V>> {
+        let options = QueryParams::<Value>::default().reduce(true).group(false);
+        let result: RawViewCollection<Value, V> = self.query(design_name, view_name, Some(options)).await?;
+        Ok(result.rows.into_iter().next().map(|row| row.value))
+    }
+
+    /// Executes a query against a view that has a reduce function, returning the rows of the map function
+    /// with their documents. A view with a reduce function is reduced by default, and `CouchDB` rejects
+    /// `include_docs` for a reduced query, so this sets both `reduce=false` and `include_docs=true` on the
+    /// (optional) `options`.
+    ///
+    /// Usage:
+    /// ```
+    /// use couch_rs::error::CouchResult;
+    /// use couch_rs::types::view::ViewCollection;
+    /// use serde_json::Value;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> CouchResult<()> {
+    ///     let client = couch_rs::Client::new_local_test()?;
+    ///     let db = client.db("sales_db").await?;
+    ///     let sales: ViewCollection<Value, u64, Value> =
+    ///         db.query_no_reduce_with_docs("reports", "total_by_year", None).await?;
+    ///     for row in sales.rows {
+    ///         println!("{}: {:?}", row.key, row.doc);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn query_no_reduce_with_docs<
+        K: Serialize + DeserializeOwned + PartialEq + std::fmt::Debug + Clone,
+        V: DeserializeOwned,
+        T: TypedCouchDocument,
+    >(
+        &self,
+        design_name: &str,
+        view_name: &str,
+        options: Option<QueryParams<K>>,
+    ) -> CouchResult<ViewCollection<K, V, T>> {
+        let options = options.unwrap_or_default().reduce(false).include_docs(true);
+        self.query(design_name, view_name, Some(options)).await
+    }
+
+    /// Executes a query against a view without waiting for the view index to be brought up to date, by
+    /// setting `update=lazy` on the (optional) `params`. The index is updated after the response is sent.
+    ///
+    /// This is much faster for views with many pending changes, and it does not block on a (re)build of
+    /// the index, but the rows can lag behind the latest writes; use it for reads that tolerate slightly
+    /// stale data, like dashboards. On a freshly created view, the result can even be empty.
+    ///
+    /// Usage:
+    /// ```
+    /// use couch_rs::error::CouchResult;
+    /// use couch_rs::types::view::RawViewCollection;
+    /// use serde_json::Value;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> CouchResult<()> {
+    ///     let client = couch_rs::Client::new_local_test()?;
+    ///     let db = client.db("sales_db").await?;
+    ///     let sales: RawViewCollection<Value, u64> = db.query_stale("reports", "sales_by_date", None).await?;
+    ///     println!("{} (possibly stale) rows", sales.rows.len());
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn query_stale<
+        K: Serialize + DeserializeOwned + PartialEq + std::fmt::Debug + Clone,
+        V: DeserializeOwned,
+        T: TypedCouchDocument,
+    >(
+        &self,
+        design_name: &str,
+        view_name: &str,
+        params: Option<QueryParams<K>>,
+    ) -> CouchResult<ViewCollection<K, V, T>> {
+        let params = params.unwrap_or_default().update(UpdateView::Lazy);
+        self.query(design_name, view_name, Some(params)).await
+    }
+
+    /// Executes an update function.
+    pub async fn execute_update(
+        &self,
+        design_id: &str,
+        name: &str,
+        document_id: &str,
+        body: Option<Value>,
+    ) -> CouchResult<String> {
+        let body = match body {
+            Some(v) => to_string(&v)?,
+            None => String::default(),
+        };
+
+        self._client
+            .put(&self.create_execute_update_path(design_id, name, document_id), body)
+            .send()
+            .await?
+            .couch_error_for_status()
+            .await?
+            .text()
+            .await
+            .map_err(CouchError::from)
+    }
+
+    /// Increments a counter document through an update function, and returns its new value. As the
+    /// update function runs on the server, concurrent increments do not conflict with each other.
+    /// The update function receives `{"by": by}` as the request body and has to respond with the new
+    /// value, e.g.:
+    ///
+    /// ```javascript
+    /// function (doc, req) {
+    ///     if (!doc) {
+    ///         doc = { _id: req.id, value: 0 };
+    ///     }
+    ///     doc.value += JSON.parse(req.body).by;
+    ///     return [doc, String(doc.value)];
+    /// }
+    /// ```
+    ///
+    /// Usage:
+    /// ```
+    /// use couch_rs::error::CouchResult;
+    /// use couch_rs::types::view::CouchUpdate;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> CouchResult<()> {
+    ///     let client = couch_rs::Client::new_local_test()?;
+    ///     let db = client.db("test_db").await?;
+    ///     let update = CouchUpdate::new(
+    ///         "increment",
+    ///         "function (doc, req) { if (!doc) { doc = { _id: req.id, value: 0 }; } doc.value += JSON.parse(req.body).by; return [doc, String(doc.value)]; }",
+    ///     );
+    ///     db.create_update_functions("counters", update).await?;
+    ///
+    ///     let invoice_number = db.increment("counters", "increment", "invoice_number", 1).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn increment(&self, design_id: &str, update_fn: &str, counter_id: &str, by: i64) -> CouchResult<i64> {
+        let result = self
+            .execute_update(design_id, update_fn, counter_id, Some(json!({ "by": by })))
+            .await?;
+        result.trim().parse().map_err(|err| {
+            CouchError::InvalidJson(ErrorMessage {
+                message: format!("update function {update_fn} did not return a number: {result}"),
+                upstream: Some(Arc::new(err)),
+            })
+        })
+    }
+
+    /// Removes a document from the database. Returns success in a `bool`
+    /// Usage:
+    /// ```
+    /// use couch_rs::types::find::FindQuery;
+    /// use serde_json::{from_value, to_value, Value};
+    /// use couch_rs::types::document::DocumentId;
+    /// use couch_rs::error::CouchResult;
+    ///
+    /// const TEST_DB: &str = "test_db";
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> CouchResult<()> {
+    ///     let client = couch_rs::Client::new_local_test()?;
+    ///     let db = client.db(TEST_DB).await?;
+    ///
+    ///     // first we need to get the document, because we need both the _id and _rev in order
+    ///     // to delete
+    ///     if let Some(doc) = db.get::<Value>("123").await.ok() {
+    ///         db.remove(&doc).await;
+    ///     }
+    ///
+    ///     Ok(())
+    /// }
+    ///```
     pub async fn remove<T: TypedCouchDocument>(&self, doc: &T) -> bool {
         let mut h = HashMap::new();
         h.insert(s!("rev"), doc.get_rev().into_owned());
@@ -1055,18 +2751,27 @@ impl Database {
     /// Arguments to this function include name, index specification, index type, and the
     /// design document to which the index will be written. See [CouchDB docs](https://docs.couchdb.org/en/latest/api/database/find.html#db-index)
     /// for more explanation on parameters for indices. The `index_type` and design doc
-    /// fields are optional.
+    /// fields are optional. The index specification is either an [`IndexFields`] for a json index,
+    /// or a [`crate::types::index::TextIndexDef`] for a text index, which implies the text index type.
     ///
     /// Indexes do not have unique names, so no index can be "edited". If `insert_index` is called
     /// where there is an existing index with the same name but a different definition, then
-    /// a new index is created and the [`DesignCreated`] return value's result field will be "exists".
+    /// a new index is created and the [`DesignCreated`] return value's result field will be "created".
     /// If `insert_index` is called where there is an existing index with
     /// both the same name and same definition, no new index is created, and the [`DesignCreated`]
-    /// return value's result field will be "created".
+    /// return value's result field will be "exists".
+    ///
+    /// Without a `ddoc`, `CouchDB` writes the index to a design document named after its definition. With
+    /// a `ddoc` that already exists, the index is added to that design document, next to the indexes in
+    /// there; an index with the same name in that design document is replaced. Use
+    /// [`DesignCreated::index_status`] to tell whether the design document was created or reused.
+    /// This is checked with a `HEAD` request before the index is created, so it is racy: when another
+    /// client creates or deletes the design document in between, the status can be wrong.
+    ///
     /// Usage:
     /// ```rust
     /// use couch_rs::error::CouchResult;
-    /// use couch_rs::types::{find::SortSpec, index::{Index, IndexFields}};
+    /// use couch_rs::types::{design::IndexStatus, find::SortSpec, index::{Index, IndexFields}};
     ///
     /// const TEST_DB: &str = "test_db";
     ///
@@ -1083,13 +2788,11 @@ impl Database {
     ///         }
     ///     };
     ///
-    ///     match db.insert_index(index_name, index_def, None, None).await {
-    ///         Ok(doc_created) => match doc_created.result {
-    ///             // Expected value of 'r' is 'created' if the index did not previously exist or
-    ///             // "exists" otherwise.
-    ///             Some(r) => println!("Index {} {}", index_name, r),  
-    ///             // This shold not happen!
-    ///             None => println!("Index {} validated", index_name),
+    ///     match db.insert_index(index_name, index_def, None, Some("people".to_string())).await {
+    ///         Ok(doc_created) => match doc_created.index_status()? {
+    ///             IndexStatus::CreatedDesign => println!("Index {} created in a new design", index_name),
+    ///             IndexStatus::AddedToDesign => println!("Index {} added to the design", index_name),
+    ///             IndexStatus::Exists => println!("Index {} validated", index_name),
     ///         },
     ///         Err(e) => {
     ///             println!("Unable to validate index {}: {}", index_name, e);
@@ -1105,10 +2808,17 @@ impl Database {
     pub async fn insert_index(
         &self,
         name: &str,
-        def: IndexFields,
+        def: impl Into<IndexDef>,
         index_type: Option<IndexType>,
         ddoc: Option<DocumentId>,
     ) -> CouchResult<DesignCreated> {
+        let def = def.into();
+        // a text index definition implies the text index type
+        let index_type = match (&def, index_type) {
+            (IndexDef::Text(_), None) => Some(IndexType::Text),
+            (_, index_type) => index_type,
+        };
+
         let mut base_body = json!({
             "name": name,
             "index": def
@@ -1120,8 +2830,12 @@ impl Database {
             body.insert("type".to_string(), Value::String(t.to_string()));
         }
 
-        // add ddoc if it is not None
+        // add ddoc if it is not None; CouchDB adds the index to the ddoc when it already exists, so we check
+        // that first, to be able to tell the caller
+        let mut ddoc_existed = None;
         if let Some(d) = ddoc {
+            let design_name = d.strip_prefix(DESIGN_PREFIX).unwrap_or(&d);
+            ddoc_existed = Some(self.head_rev_at(&self.create_design_path(design_name)).await?.is_some());
             body.insert("ddoc".to_string(), Value::String(d));
         }
 
@@ -1132,11 +2846,14 @@ impl Database {
             .await?;
 
         let status = response.status();
-        let data: DesignCreated = response.json().await?;
+        let mut data: DesignCreated = response.json().await?;
 
         if let Some(err) = data.error {
             Err(CouchError::new(err, status))
         } else {
+            // without a ddoc, CouchDB names the ddoc after the index definition, so a created index is
+            // always in a new ddoc
+            data.ddoc_existed = Some(ddoc_existed.unwrap_or(false));
             Ok(data)
         }
     }
@@ -1152,21 +2869,59 @@ impl Database {
             .map_err(CouchError::from)
     }
 
-    /// Deletes a db index. Returns true if successful, false otherwise.
-    pub async fn delete_index(&self, ddoc: DocumentId, name: String) -> CouchResult<bool> {
-        let uri = format!("_index/{ddoc}/json/{name}");
+    /// Reads the indexes in the given design document; `ddoc` may be given with or without the `_design/` prefix.
+    /// `CouchDB` can not filter indexes server side, so all indexes are read and filtered client side.
+    pub async fn read_indexes_for_ddoc(&self, ddoc: &str) -> CouchResult<Vec<Index>> {
+        let ddoc = if ddoc.starts_with("_design/") {
+            ddoc.to_string()
+        } else {
+            format!("_design/{ddoc}")
+        };
 
-        match self
-            ._client
-            .delete(&self.create_raw_path(&uri), None)
-            .send()
+        Ok(self
+            .read_indexes()
             .await?
-            .json::<DeleteIndexResponse>()
-            .await
-            .map_err(CouchError::from)
-        {
-            Ok(d) => Ok(d.ok),
-            Err(e) => Err(e),
+            .indexes
+            .into_iter()
+            .filter(|index| index.ddoc.as_deref() == Some(ddoc.as_str()))
+            .collect())
+    }
+
+    /// Deletes a db index. Returns true if successful, false when the index does not exist.
+    pub async fn delete_index(&self, ddoc: DocumentId, name: String) -> CouchResult<bool> {
+        self.delete_index_of_type(&ddoc, &IndexType::Json, &name).await
+    }
+
+    /// Deletes an index, as returned by `read_indexes`. Returns true if successful, false otherwise.
+    /// Fails for an index without a design document, like the built-in `_all_docs` index.
+    pub async fn delete_index_spec(&self, index: &Index) -> CouchResult<bool> {
+        let Some(ddoc) = &index.ddoc else {
+            return Err(CouchError::new(
+                format!("index {} has no design document and can not be deleted", index.name),
+                StatusCode::BAD_REQUEST,
+            ));
+        };
+
+        let index_type = index.index_type.clone().unwrap_or(IndexType::Json);
+        self.delete_index_of_type(ddoc, &index_type, &index.name).await
+    }
+
+    async fn delete_index_of_type(&self, ddoc: &str, index_type: &IndexType, name: &str) -> CouchResult<bool> {
+        let uri = format!("_index/{ddoc}/{index_type}/{name}");
+
+        let response = self._client.delete(&self.create_raw_path(&uri), None).send().await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            // nothing to delete
+            return Ok(false);
+        }
+
+        let status = response.status();
+        let result: CouchResponse = response.couch_json().await?;
+        if status.is_success() {
+            Ok(result.ok.unwrap_or(false))
+        } else {
+            let err = result.error.unwrap_or_else(|| s!("unspecified error"));
+            Err(CouchError::new(err, status))
         }
     }
 
@@ -1204,6 +2959,53 @@ impl Database {
     }
 }
 
+/// State of the stream returned by `find_stream_dedup`
+struct DedupState<T> {
+    query: FindQuery,
+    bookmark: Option<String>,
+    page_size: u64,
+    /// Number of documents still to return, when the query has a `limit`
+    remaining: Option<u64>,
+    buffer: VecDeque<T>,
+    seen: SeenIds,
+    done: bool,
+}
+
+/// Set of document ids, optionally bounded; when full, the oldest id is forgotten.
+struct SeenIds {
+    max: usize,
+    ids: HashSet<DocumentId>,
+    order: VecDeque<DocumentId>,
+}
+
+impl SeenIds {
+    fn new(max: usize) -> SeenIds {
+        SeenIds {
+            max,
+            ids: HashSet::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// Returns `true` when the id was not seen before.
+    fn insert(&mut self, id: &str) -> bool {
+        if self.ids.contains(id) {
+            return false;
+        }
+
+        if self.max > 0 {
+            if self.order.len() >= self.max {
+                if let Some(oldest) = self.order.pop_front() {
+                    self.ids.remove(&oldest);
+                }
+            }
+            self.order.push_back(s!(id));
+        }
+        self.ids.insert(s!(id));
+        true
+    }
+}
+
 fn get_mandatory_string_value(key: &str, value: &Value) -> CouchResult<String> {
     let id = if let Some(serde_json::Value::String(id)) = value.get(key) {
         id.to_owned()
@@ -1216,6 +3018,23 @@ fn get_mandatory_string_value(key: &str, value: &Value) -> CouchResult<String> {
     Ok(id)
 }
 
+fn effective_chunk_size(chunk_size: usize) -> usize {
+    if chunk_size > 0 {
+        chunk_size
+    } else {
+        DEFAULT_CHUNK_SIZE
+    }
+}
+
+pub(crate) fn to_typed_document<T: TypedCouchDocument>(value: Value) -> CouchResult<T> {
+    let id = get_mandatory_string_value(ID_FIELD, &value)?;
+    let rev = get_mandatory_string_value(REV_FIELD, &value)?;
+    let mut document: T = from_value(value)?;
+    document.set_id(&id);
+    document.set_rev(&rev);
+    Ok(document)
+}
+
 fn to_create_value(doc: &impl TypedCouchDocument) -> CouchResult<serde_json::Map<String, Value>> {
     let mut value = get_value_map(doc)?;
     set_if_not_empty(ID_FIELD, doc.get_id().as_ref(), &mut value);
@@ -1230,6 +3049,45 @@ fn to_upsert_value(doc: &impl TypedCouchDocument) -> CouchResult<serde_json::Map
     Ok(value)
 }
 
+/// Reads the response to creating or saving a document. Some proxies strip the body of a successful
+/// response, so a missing `rev` is read from the `ETag` header, and a missing `id` from the `Location`
+/// header, or else taken from `known_id`.
+async fn document_created_response(
+    response: reqwest::Response,
+    known_id: Option<&str>,
+) -> CouchResult<(StatusCode, DocumentCreatedResponse)> {
+    let status = response.status();
+    let headers = response.headers().clone();
+    let body = response.bytes().await?;
+    let mut data: DocumentCreatedResponse = if body.iter().all(u8::is_ascii_whitespace) {
+        DocumentCreatedResponse::default()
+    } else {
+        serde_json::from_slice(&body)?
+    };
+
+    if status.is_success() {
+        if data.rev.is_none() {
+            data.rev = headers
+                .get(ETAG)
+                .and_then(|etag| etag.to_str().ok())
+                .map(|etag| etag.trim_matches('"').to_string());
+        }
+        if data.id.is_none() {
+            data.id = headers
+                .get(LOCATION)
+                .and_then(|location| location.to_str().ok())
+                .and_then(|location| location.rsplit('/').next())
+                .and_then(|id| percent_decode_str(id).decode_utf8().ok())
+                .map(String::from)
+                .or_else(|| known_id.map(String::from));
+        }
+        if data.ok.is_none() && data.error.is_none() {
+            data.ok = Some(true);
+        }
+    }
+    Ok((status, data))
+}
+
 fn get_value_map(doc: &impl TypedCouchDocument) -> CouchResult<serde_json::Map<String, Value>> {
     let value = serde_json::to_value(doc)?;
     let serde_json::Value::Object(value) = value else {
@@ -1252,13 +3110,39 @@ fn set_if_not_empty(field_name: &str, field_value: &str, value: &mut serde_json:
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate as couch_rs;
+    use crate::mock_server::{mock_server, mock_server_sequence, mock_server_with_headers};
+    use crate::types::view::CouchFunc;
     use http::response::Builder;
     use reqwest::{Response, ResponseBuilderExt, Url};
+    use serde::Deserialize;
+
+    #[test]
+    fn test_seen_ids() {
+        let mut seen = SeenIds::new(0);
+        assert!(seen.insert("1"));
+        assert!(seen.insert("2"));
+        assert!(!seen.insert("1"));
+    }
+
+    #[test]
+    fn test_bounded_seen_ids_forget_the_oldest() {
+        let mut seen = SeenIds::new(2);
+        assert!(seen.insert("1"));
+        assert!(seen.insert("2"));
+        assert!(seen.insert("3"));
+        assert!(!seen.insert("3"));
+        // "1" was forgotten to make room for "3"
+        assert!(seen.insert("1"));
+        assert!(!seen.insert("3"));
+    }
 
     #[test]
     fn test_document_paths() {
         let client = Client::new_local_test().unwrap();
         let db = Database::new("testdb".to_string(), client);
+        let p = db.create_attachment_path("1 2", "notes/a.txt");
+        assert_eq!(p, "testdb/1+2/notes%2Fa.txt");
         let p = db.create_raw_path("123");
         assert_eq!(p, "testdb/123");
         let p = db.create_document_path("1+3");
@@ -1275,6 +3159,893 @@ mod tests {
         assert_eq!(p, "testdb/_compact/view1");
     }
 
+    #[tokio::test]
+    async fn test_ensure_full_commit() {
+        let (uri, server) = mock_server(201, r#"{"ok":true,"instance_start_time":"0"}"#).await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+
+        assert_eq!(db.ensure_full_commit().await.unwrap(), "0");
+        assert!(server.await.unwrap().starts_with("POST /testdb/_ensure_full_commit "));
+    }
+
+    #[tokio::test]
+    async fn test_ensure_full_commit_not_supported() {
+        let (uri, server) = mock_server_sequence(&[
+            (404, &[], r#"{"error":"not_found","reason":"missing"}"#),
+            (200, &[], ""),
+        ])
+        .await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+
+        let err = db.ensure_full_commit().await.unwrap_err();
+        assert_eq!(err.status(), Some(StatusCode::NOT_IMPLEMENTED));
+        assert!(err.to_string().contains("no-op"));
+        let requests = server.await.unwrap();
+        assert!(requests[1].starts_with("HEAD /testdb "));
+    }
+
+    #[tokio::test]
+    async fn test_ensure_full_commit_gone() {
+        let (uri, _server) = mock_server(410, r#"{"error":"gone","reason":"removed"}"#).await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+
+        let err = db.ensure_full_commit().await.unwrap_err();
+        assert_eq!(err.status(), Some(StatusCode::NOT_IMPLEMENTED));
+    }
+
+    #[tokio::test]
+    async fn test_ensure_full_commit_missing_database() {
+        let (uri, _server) = mock_server_sequence(&[
+            (404, &[], r#"{"error":"not_found","reason":"Database does not exist."}"#),
+            (404, &[], ""),
+        ])
+        .await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+
+        let err = db.ensure_full_commit().await.unwrap_err();
+        assert!(err.is_not_found());
+        assert_eq!(err.to_string(), "404 Not Found: not_found: Database does not exist.");
+    }
+
+    #[tokio::test]
+    async fn test_conflicts() {
+        let (uri, server) = mock_server(
+            200,
+            r#"{"total_rows":3,"offset":0,"rows":[
+                {"id":"1","key":"1","value":{"rev":"2-b"},"doc":{"_id":"1","_rev":"2-b","_conflicts":["2-a"]}},
+                {"id":"2","key":"2","value":{"rev":"1-c"},"doc":{"_id":"2","_rev":"1-c"}},
+                {"key":"3","error":"not_found"}
+            ]}"#,
+        )
+        .await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+
+        let conflicts = db.conflicts(vec![s!("1"), s!("2"), s!("3")]).await.unwrap();
+        assert_eq!(conflicts, HashMap::from([(s!("1"), vec![s!("2-a")])]));
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /testdb/_all_docs "));
+        assert!(request.contains(r#""conflicts":true"#));
+        assert!(request.contains(r#""include_docs":true"#));
+    }
+
+    #[tokio::test]
+    async fn test_save_reads_rev_from_headers_without_body() {
+        let (uri, _server) = mock_server_with_headers(
+            201,
+            &[("ETag", "\"2-b\""), ("Location", "http://localhost:5984/testdb/john")],
+            "",
+        )
+        .await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+
+        let mut doc = json!({"_id": "john", "_rev": "1-a"});
+        let details = db.save(&mut doc).await.unwrap();
+        assert_eq!(details.id, "john");
+        assert_eq!(details.rev, "2-b");
+        assert_eq!(doc.get_rev(), "2-b");
+    }
+
+    #[tokio::test]
+    async fn test_create_reads_id_and_rev_from_headers_without_body() {
+        let (uri, server) = mock_server_with_headers(
+            201,
+            &[
+                ("ETag", "\"1-a\""),
+                ("Location", "http://localhost:5984/testdb/jane%2Bdoe"),
+            ],
+            "",
+        )
+        .await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+
+        let mut doc = json!({"name": "Jane"});
+        let details = db.create(&mut doc).await.unwrap();
+        assert_eq!(details.id, "jane+doe");
+        assert_eq!(details.rev, "1-a");
+        assert_eq!(doc.get_id(), "jane+doe");
+        assert!(server.await.unwrap().starts_with("POST /testdb "));
+    }
+
+    #[tokio::test]
+    async fn test_create_with_id_falls_back_to_the_requested_id() {
+        let (uri, _server) = mock_server_with_headers(201, &[("ETag", "\"1-a\"")], "").await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+
+        let details = db.create_with_id("john", &mut json!({})).await.unwrap();
+        assert_eq!(details.id, "john");
+        assert_eq!(details.rev, "1-a");
+    }
+
+    #[tokio::test]
+    async fn test_save_rejected_by_validation() {
+        let (uri, _server) = mock_server(403, r#"{"error":"forbidden","reason":"name is required"}"#).await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+
+        let err = db.save(&mut json!({"_id": "john"})).await.unwrap_err();
+        assert!(err.is_validation_error());
+        assert_eq!(err.status(), Some(StatusCode::FORBIDDEN));
+    }
+
+    #[tokio::test]
+    async fn test_conflict_is_no_validation_error() {
+        let (uri, _server) = mock_server(409, r#"{"error":"conflict","reason":"Document update conflict."}"#).await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+
+        let err = db.save(&mut json!({"_id": "john", "_rev": "1-a"})).await.unwrap_err();
+        assert!(!err.is_validation_error());
+    }
+
+    #[tokio::test]
+    async fn test_bulk_docs_rejected_by_validation() {
+        let (uri, _server) = mock_server(
+            201,
+            r#"[{"id":"john","rev":"1-a","ok":true},{"id":"jane","error":"forbidden","reason":"name is required"}]"#,
+        )
+        .await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+
+        let mut docs = vec![json!({"_id": "john", "name": "John"}), json!({"_id": "jane"})];
+        let results = db.bulk_docs(&mut docs).await.unwrap();
+        assert!(results[0].is_ok());
+        let err = results[1].as_ref().unwrap_err();
+        assert!(err.is_validation_error());
+        assert_eq!(err.to_string(), "jane -> 403 Forbidden: name is required");
+    }
+
+    #[tokio::test]
+    async fn test_bulk_docs_rejected_as_a_whole() {
+        let (uri, _server) = mock_server(
+            413,
+            r#"{"error":"too_large","reason":"the request entity is too large"}"#,
+        )
+        .await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+
+        let err = db.bulk_docs(&mut [json!({"thing": true})]).await.unwrap_err();
+        assert_eq!(err.status(), Some(StatusCode::PAYLOAD_TOO_LARGE));
+        assert_eq!(
+            err.to_string(),
+            "413 Payload Too Large: too_large: the request entity is too large"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_bulk_docs_with_expectation_failed() {
+        let (uri, _server) = mock_server(
+            417,
+            r#"[{"id":"jane","error":"forbidden","reason":"name is required"}]"#,
+        )
+        .await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+
+        let results = db.bulk_docs(&mut [json!({"_id": "jane"})]).await.unwrap();
+        assert!(results[0].as_ref().unwrap_err().is_validation_error());
+    }
+
+    #[tokio::test]
+    async fn test_shards() {
+        let (uri, server) = mock_server(
+            200,
+            r#"{"shards":{
+                "80000000-ffffffff":["couchdb@node2","couchdb@node3"],
+                "00000000-7fffffff":["couchdb@node1","couchdb@node2"]
+            }}"#,
+        )
+        .await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+
+        let shards = db.shards().await.unwrap();
+        assert_eq!(shards.len(), 2);
+        assert_eq!(shards[0].range, "00000000-7fffffff");
+        assert_eq!((shards[0].begin, shards[0].end), (0, 0x7fff_ffff));
+        assert_eq!(shards[0].nodes, vec![s!("couchdb@node1"), s!("couchdb@node2")]);
+        assert_eq!(shards[1].begin, 0x8000_0000);
+        assert!(server.await.unwrap().starts_with("GET /testdb/_shards "));
+    }
+
+    #[tokio::test]
+    async fn test_upsert_views_without_changes() {
+        let (uri, server) = mock_server(
+            200,
+            r#"{"_id":"_design/users","_rev":"1-a","language":"javascript",
+                "views":{"by_email":{"map":"function (doc) { emit(doc.email, null); }"}},
+                "updates":{"touch":"function (doc, req) { return [doc, 'ok']; }"}}"#,
+        )
+        .await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+
+        let views = CouchViews::new(
+            "by_email",
+            CouchFunc::new("function (doc) { emit(doc.email, null); }", None),
+        );
+        // the mock server answers a single request, so a write would fail
+        assert!(!db.upsert_views("users", &views).await.unwrap());
+        assert!(server.await.unwrap().starts_with("GET /testdb/_design/users "));
+    }
+
+    #[tokio::test]
+    async fn test_get_map() {
+        let (uri, server) = mock_server(
+            200,
+            r#"{"total_rows":3,"offset":0,"rows":[
+                {"id":"john","key":"john","value":{"rev":"1-a"},"doc":{"_id":"john","_rev":"1-a","age":8}},
+                {"key":"jack","error":"not_found"},
+                {"id":"jill","key":"jill","value":{"rev":"1-b"},"doc":{"_id":"jill","_rev":"1-b","age":6}}
+            ]}"#,
+        )
+        .await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+
+        let docs: HashMap<DocumentId, Value> = db.get_map(vec![s!("john"), s!("jack"), s!("jill")]).await.unwrap();
+        let mut ids: Vec<_> = docs.keys().cloned().collect();
+        ids.sort();
+        assert_eq!(ids, vec![s!("jill"), s!("john")]);
+        assert_eq!(docs["john"]["age"], json!(8));
+        assert!(server.await.unwrap().contains(r#""keys":["john","jack","jill"]"#));
+    }
+
+    #[tokio::test]
+    async fn test_query_reduce_value() {
+        let (uri, server) = mock_server(200, r#"{"rows":[{"key":null,"value":3}]}"#).await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+
+        let count: Option<u64> = db.query_reduce_value("reports", "count").await.unwrap();
+        assert_eq!(count, Some(3));
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /testdb/_design/reports/_view/count "));
+        assert!(request.ends_with(r#"{"group":false,"reduce":true}"#));
+
+        let (uri, _server) = mock_server(200, r#"{"rows":[]}"#).await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+        let count: Option<u64> = db.query_reduce_value("reports", "count").await.unwrap();
+        assert_eq!(count, None);
+    }
+
+    #[tokio::test]
+    async fn test_query_stale() {
+        let (uri, server) = mock_server(
+            200,
+            r#"{"total_rows":1,"offset":0,"rows":[{"id":"a","key":"2024-01-01","value":10}]}"#,
+        )
+        .await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+
+        let options = QueryParams::default().limit(10).update(UpdateView::True);
+        let result: RawViewCollection<String, u64> =
+            db.query_stale("reports", "sales_by_date", Some(options)).await.unwrap();
+        assert_eq!(result.rows[0].value, 10);
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /testdb/_design/reports/_view/sales_by_date HTTP/1.1"));
+        assert!(request.ends_with(r#"{"limit":10,"update":"lazy"}"#));
+    }
+
+    #[tokio::test]
+    async fn test_query_no_reduce_with_docs() {
+        let (uri, server) = mock_server(
+            200,
+            r#"{"total_rows":1,"offset":0,"rows":[
+                {"id":"a","key":2024,"value":10,"doc":{"_id":"a","_rev":"1-a","year":2024,"amount":10}}
+            ]}"#,
+        )
+        .await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+
+        let options = QueryParams::default().key(2024).reduce(true);
+        let result: ViewCollection<u32, u64, Value> = db
+            .query_no_reduce_with_docs("reports", "total_by_year", Some(options))
+            .await
+            .unwrap();
+        assert_eq!(result.rows[0].value, 10);
+        assert_eq!(result.rows[0].doc.as_ref().unwrap()["amount"], json!(10));
+        let request = server.await.unwrap();
+        assert!(request.ends_with(r#"{"include_docs":true,"key":2024,"reduce":false}"#));
+    }
+
+    #[tokio::test]
+    async fn test_populate() {
+        let (uri, server) = mock_server(
+            200,
+            r#"{"total_rows":3,"offset":0,"rows":[
+                {"id":"john","key":"john","value":{"rev":"1-a"},"doc":{"_id":"john","_rev":"1-a","age":8}},
+                {"id":"jill","key":"jill","value":{"rev":"1-b"},"doc":{"_id":"jill","_rev":"1-b","age":6}},
+                {"key":"jack","error":"not_found"}
+            ]}"#,
+        )
+        .await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+
+        let mut parent = json!({"_id": "jane", "children": ["john", "jill", "jack"]});
+        db.populate::<Value>(&mut parent, "children").await.unwrap();
+        assert_eq!(
+            parent["children"],
+            json!([
+                {"_id": "john", "_rev": "1-a", "age": 8},
+                {"_id": "jill", "_rev": "1-b", "age": 6},
+                "jack"
+            ])
+        );
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /testdb/_all_docs "));
+        assert!(request.contains(r#""keys":["john","jill","jack"]"#));
+    }
+
+    #[tokio::test]
+    async fn test_populate_requires_an_array() {
+        let db = Database::new(
+            "testdb".to_string(),
+            Client::new_no_auth("http://localhost:5984").unwrap(),
+        );
+
+        let mut parent = json!({"_id": "jane", "children": "john"});
+        let err = db.populate::<Value>(&mut parent, "children").await.unwrap_err();
+        assert_eq!(err.status(), Some(StatusCode::BAD_REQUEST));
+
+        let mut parent = json!({"_id": "jane"});
+        db.populate::<Value>(&mut parent, "children").await.unwrap();
+        assert_eq!(parent, json!({"_id": "jane"}));
+    }
+
+    #[tokio::test]
+    async fn test_find_projected() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct LastName {
+            last_name: String,
+        }
+
+        let (uri, server) = mock_server(200, r#"{"docs":[{"last_name":"Doe"},{"last_name":"Roe"}]}"#).await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+
+        let query = FindQuery::new(json!({"first_name": "John"})).fields(vec![s!("last_name")]);
+        let names: Vec<LastName> = db.find_projected(&query).await.unwrap();
+        assert_eq!(
+            names,
+            vec![LastName { last_name: s!("Doe") }, LastName { last_name: s!("Roe") }]
+        );
+        let request = server.await.unwrap();
+        assert!(request.contains(r#""fields":["last_name"]"#));
+    }
+
+    #[tokio::test]
+    async fn test_move_doc_missing_document() {
+        let (uri, server) = mock_server(404, "").await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+
+        let err = db.move_doc("1", "2").await.unwrap_err();
+        assert!(err.is_not_found());
+        assert!(server.await.unwrap().starts_with("HEAD /testdb/1 "));
+    }
+
+    #[tokio::test]
+    async fn test_move_doc_encodes_non_ascii_destination() {
+        let (uri, server) = mock_server_sequence(&[
+            (200, &[("ETag", "\"1-a\"")], ""),
+            (201, &[], r#"{"ok":true,"id":"café","rev":"1-b"}"#),
+            (200, &[], r#"{"ok":true,"id":"1","rev":"2-a"}"#),
+        ])
+        .await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+
+        let details = db.move_doc("1", "café").await.unwrap();
+        assert_eq!(details.id, "café");
+        let requests = server.await.unwrap();
+        assert!(requests[1].starts_with("COPY /testdb/1?rev=1-a "));
+        assert!(requests[1].contains("destination: caf%C3%A9\r\n"));
+        assert!(requests[2].starts_with("DELETE /testdb/1?rev=1-a "));
+    }
+
+    #[tokio::test]
+    async fn test_move_doc_encodes_reserved_characters_in_destination() {
+        let (uri, server) = mock_server_sequence(&[
+            (200, &[("ETag", "\"1-a\"")], ""),
+            (201, &[], r#"{"ok":true,"id":"a?rev=1%","rev":"1-b"}"#),
+            (200, &[], r#"{"ok":true,"id":"1","rev":"2-a"}"#),
+        ])
+        .await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+
+        db.move_doc("1", "a?rev=1%").await.unwrap();
+        let requests = server.await.unwrap();
+        assert!(requests[1].contains("destination: a%3Frev%3D1%25\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_exists_meta() {
+        let (uri, server) = mock_server_with_headers(200, &[("ETag", "\"2-b\"")], "").await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+
+        let meta = db.exists_meta("1").await.unwrap().unwrap();
+        assert_eq!(meta.rev, "2-b");
+        assert_eq!(meta.size, Some(0));
+        assert!(server.await.unwrap().starts_with("HEAD /testdb/1 "));
+
+        let (uri, _server) = mock_server(404, "").await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+        assert_eq!(db.exists_meta("1").await.unwrap(), None);
+    }
+
+    #[derive(serde::Serialize, Deserialize, couch_rs_derive::CouchDocument, Debug)]
+    struct Person {
+        _id: DocumentId,
+        _rev: String,
+        age: u32,
+    }
+
+    #[tokio::test]
+    async fn test_find_names_the_invalid_document() {
+        let (uri, _server) = mock_server(
+            200,
+            r#"{"docs":[{"_id":"1","_rev":"1-a","age":8},{"_id":"2","_rev":"1-b","age":"six"}]}"#,
+        )
+        .await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+
+        let err = db.find::<Person>(&FindQuery::find_all()).await.unwrap_err();
+        assert!(matches!(err, CouchError::InvalidJson(_)));
+        assert!(err.to_string().starts_with("document 2: "), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_get_bulk_names_the_invalid_document() {
+        let (uri, _server) = mock_server(
+            200,
+            r#"{"total_rows":2,"offset":0,"rows":[
+                {"id":"1","key":"1","value":{"rev":"1-a"},"doc":{"_id":"1","_rev":"1-a"}},
+                {"id":"2","key":"2","value":{"rev":"1-b"},"doc":{"_id":"2","_rev":"1-b","age":6}}
+            ]}"#,
+        )
+        .await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+
+        let err = db.get_bulk::<Person>(vec![s!("1"), s!("2")]).await.unwrap_err();
+        assert!(err.to_string().starts_with("document 1: missing field `age`"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_find_one_without_match() {
+        let (uri, server) = mock_server(200, r#"{"docs":[]}"#).await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+
+        let query = FindQuery::new(json!({"email": "jdoe@example.com"}));
+        assert_eq!(db.find_one::<Value>(&query).await.unwrap(), None);
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /testdb/_find "));
+        assert!(request.contains(r#""limit":2"#));
+    }
+
+    #[tokio::test]
+    async fn test_export_includes_attachment_data() {
+        let (uri, server) = mock_server_sequence(&[
+            (
+                200,
+                &[],
+                r#"{"docs":[{"_id":"1","_rev":"1-a","_attachments":{"a.txt":{"stub":true,"content_type":"text/plain","length":2}}},{"_id":"2","_rev":"1-b"}],"bookmark":"b"}"#,
+            ),
+            (
+                200,
+                &[],
+                r#"{"_id":"1","_rev":"1-a","_attachments":{"a.txt":{"content_type":"text/plain","data":"aGk="}}}"#,
+            ),
+            (200, &[], r#"{"docs":[],"bookmark":"b"}"#),
+        ])
+        .await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+
+        let mut export = vec![];
+        assert_eq!(db.export_to(&mut export).await.unwrap(), 2);
+        let lines: Vec<Value> = export
+            .split(|b| *b == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice(line).unwrap())
+            .collect();
+        assert_eq!(lines[0]["_attachments"]["a.txt"]["data"], "aGk=");
+        assert_eq!(lines[1], json!({"_id": "2", "_rev": "1-b"}));
+        assert!(server.await.unwrap()[1].starts_with("GET /testdb/1?attachments=true "));
+    }
+
+    #[tokio::test]
+    async fn test_find_one_with_one_match() {
+        let (uri, _server) = mock_server(200, r#"{"docs":[{"_id":"1","_rev":"1-a"}]}"#).await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+
+        let query = FindQuery::new(json!({"email": "jdoe@example.com"}));
+        let doc: Value = db.find_one(&query).await.unwrap().unwrap();
+        assert_eq!(doc.get_id(), "1");
+    }
+
+    #[tokio::test]
+    async fn test_find_one_with_two_matches() {
+        let (uri, server) = mock_server(200, r#"{"docs":[{"_id":"1","_rev":"1-a"},{"_id":"2","_rev":"1-b"}]}"#).await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+
+        let query = FindQuery::new(json!({"email": "jdoe@example.com"})).limit(10);
+        let err = db.find_one::<Value>(&query).await.unwrap_err();
+        assert_eq!(err.status(), Some(StatusCode::CONFLICT));
+        assert!(err.to_string().contains("expected at most one document"));
+        // the limit of the query is overridden
+        assert!(server.await.unwrap().contains(r#""limit":2"#));
+    }
+
+    #[tokio::test]
+    async fn test_get_including_deleted() {
+        let (uri, server) = mock_server(
+            200,
+            r#"[
+                {"ok":{"_id":"1","_rev":"3-c","_deleted":true}},
+                {"ok":{"_id":"1","_rev":"2-b","thing":true}},
+                {"missing":"1-a"}
+            ]"#,
+        )
+        .await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+
+        // a leaf that is not deleted wins from a deleted one
+        let (doc, deleted) = db.get_including_deleted::<Value>("1").await.unwrap().unwrap();
+        assert_eq!(doc.get_rev(), "2-b");
+        assert!(!deleted);
+        let request = server.await.unwrap();
+        assert!(request.starts_with("GET /testdb/1?open_revs=all "));
+    }
+
+    #[tokio::test]
+    async fn test_get_including_deleted_tombstone() {
+        let (uri, _server) = mock_server(
+            200,
+            r#"[{"ok":{"_id":"1","_rev":"10-a","_deleted":true}},{"ok":{"_id":"1","_rev":"9-b","_deleted":true}}]"#,
+        )
+        .await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+
+        let (doc, deleted) = db.get_including_deleted::<Value>("1").await.unwrap().unwrap();
+        assert_eq!(doc.get_rev(), "10-a");
+        assert!(deleted);
+    }
+
+    #[tokio::test]
+    async fn test_get_including_deleted_never_existed() {
+        let (uri, _server) = mock_server(404, r#"{"error":"not_found","reason":"missing"}"#).await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+
+        assert_eq!(db.get_including_deleted::<Value>("1").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_increment() {
+        let (uri, server) = mock_server(201, "42").await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+
+        assert_eq!(db.increment("counters", "increment", "invoices", 2).await.unwrap(), 42);
+        let request = server.await.unwrap();
+        assert!(request.starts_with("PUT /testdb/_design/counters/_update/increment/invoices "));
+        assert!(request.ends_with(r#"{"by":2}"#));
+    }
+
+    #[tokio::test]
+    async fn test_increment_without_number() {
+        let (uri, _server) = mock_server(201, "ok").await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+
+        let err = db.increment("counters", "increment", "invoices", 1).await.unwrap_err();
+        assert!(matches!(err, CouchError::InvalidJson(_)));
+    }
+
+    #[tokio::test]
+    async fn test_find_batched_with_total_limit() {
+        let (uri, server) = mock_server(
+            200,
+            r#"{"docs":[{"_id":"1","_rev":"1-a"},{"_id":"2","_rev":"1-a"},{"_id":"3","_rev":"1-a"}],"bookmark":"b1"}"#,
+        )
+        .await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+        let (tx, mut rx) = tokio::sync::mpsc::channel::<DocumentCollection<Value>>(10);
+
+        // the total limit is reached with the first page, so no second page is requested
+        let results = db
+            .find_batched(FindQuery::find_all().limit(3), tx, 10, 0)
+            .await
+            .unwrap();
+        assert_eq!(results, 3);
+        assert_eq!(rx.recv().await.unwrap().rows.len(), 3);
+        assert!(rx.recv().await.is_none());
+        assert!(server.await.unwrap().contains(r#""limit":3"#));
+    }
+
+    #[tokio::test]
+    async fn test_list_designs() {
+        let (uri, server) = mock_server(
+            200,
+            r#"{"total_rows":5,"offset":1,"rows":[
+                {"id":"_design/orders","key":"_design/orders","value":{"rev":"1-a"}},
+                {"id":"_design/users","key":"_design/users","value":{"rev":"3-b"}}
+            ]}"#,
+        )
+        .await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+
+        assert_eq!(db.list_designs().await.unwrap(), vec![s!("orders"), s!("users")]);
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /testdb/_all_docs "));
+        assert!(request.contains(r#""start_key":"_design/""#));
+        assert!(request.contains(r#""end_key":"_design0""#));
+    }
+
+    #[tokio::test]
+    async fn test_put_attachment_content_type() {
+        let (uri, server) = mock_server(201, r#"{"ok":true,"id":"1","rev":"2-b"}"#).await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+
+        let details = db
+            .put_attachment("1", "1-a", "notes.txt", "text/plain", b"hello".to_vec())
+            .await
+            .unwrap();
+        assert_eq!(details.rev, "2-b");
+        let request = server.await.unwrap();
+        assert!(request.starts_with("PUT /testdb/1/notes.txt?rev=1-a "));
+        assert!(request.contains("content-type: text/plain\r\n"));
+        assert!(!request.contains("application/json"));
+        assert!(request.ends_with("\r\n\r\nhello"));
+    }
+
+    #[tokio::test]
+    async fn test_create_content_type() {
+        let (uri, server) = mock_server(201, r#"{"ok":true,"id":"1","rev":"1-a"}"#).await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+
+        db.create_with_id("1", &mut json!({"thing": true})).await.unwrap();
+        let request = server.await.unwrap();
+        assert!(request.contains("content-type: application/json\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_put_attachment_invalid_content_type() {
+        let db = Database::new(
+            "testdb".to_string(),
+            Client::new_no_auth("http://localhost:5984").unwrap(),
+        );
+
+        let err = db
+            .put_attachment("1", "1-a", "notes.txt", "text/plain\n", vec![])
+            .await
+            .unwrap_err();
+        assert_eq!(err.status(), Some(StatusCode::BAD_REQUEST));
+    }
+
+    #[tokio::test]
+    async fn test_query_many_with_integer_keys() {
+        let (uri, server) = mock_server(
+            200,
+            r#"{"results":[
+                {"total_rows":3,"offset":0,"rows":[{"id":"a","key":2022,"value":10}]},
+                {"total_rows":3,"offset":1,"rows":[{"id":"b","key":2023,"value":20},{"id":"c","key":2023,"value":30}]}
+            ]}"#,
+        )
+        .await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+
+        let queries = QueriesParams::new(vec![
+            QueryParams::from_keys(vec![2022]),
+            QueryParams::from_keys(vec![2023]),
+        ]);
+        let results = db.query_many("reports", "sales_by_year", queries).await.unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[1].rows[1].key, json!(2023));
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /testdb/_design/reports/_view/sales_by_year HTTP/1.1"));
+        assert!(request.ends_with(r#"{"queries":[{"keys":[2022]},{"keys":[2023]}]}"#));
+    }
+
+    #[tokio::test]
+    async fn test_failed_query_includes_reason() {
+        let (uri, _server) = mock_server(
+            400,
+            r#"{"error":"query_parse_error","reason":"Invalid value for integer: \"abc\""}"#,
+        )
+        .await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+
+        let err = db
+            .query::<Value, Value, Value>("design", "view", None)
+            .await
+            .unwrap_err();
+        assert_eq!(err.status(), Some(StatusCode::BAD_REQUEST));
+        assert_eq!(
+            err,
+            CouchError::new(
+                s!(r#"query_parse_error: Invalid value for integer: "abc""#),
+                StatusCode::BAD_REQUEST
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failed_get_without_body() {
+        let (uri, _server) = mock_server(500, "").await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+
+        let err = db.get::<Value>("1").await.unwrap_err();
+        assert_eq!(
+            err,
+            CouchError::new(s!("Internal Server Error"), StatusCode::INTERNAL_SERVER_ERROR)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_if_changed_not_modified() {
+        let (uri, server) = mock_server(304, "").await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+
+        assert_eq!(db.get_if_changed::<Value>("1", "1-a").await.unwrap(), None);
+        let request = server.await.unwrap();
+        assert!(request.starts_with("GET /testdb/1 "));
+        assert!(request.contains("if-none-match: \"1-a\"\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_get_if_changed_modified() {
+        let (uri, _server) = mock_server(200, r#"{"_id":"1","_rev":"2-b","thing":true}"#).await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+
+        let doc: Value = db.get_if_changed("1", "1-a").await.unwrap().unwrap();
+        assert_eq!(doc.get_rev(), "2-b");
+    }
+
+    #[tokio::test]
+    async fn test_attachment_info_missing() {
+        let (uri, server) = mock_server(404, "").await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+
+        assert_eq!(db.attachment_info("1", "notes.txt").await.unwrap(), None);
+        let request = server.await.unwrap();
+        assert!(request.starts_with("HEAD /testdb/1/notes.txt "));
+        assert!(request.contains("accept-encoding: identity\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_purged_infos() {
+        let (uri, server) = mock_server(
+            200,
+            r#"{"purge_seq":"2-abc","purged_infos":[{"id":"doc","revs":["1-a","2-b"]}]}"#,
+        )
+        .await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+
+        let infos = db.purged_infos().await.unwrap();
+        assert_eq!(infos.purge_seq, json!("2-abc"));
+        assert_eq!(infos.purged_infos[0].id, "doc");
+        assert_eq!(infos.purged_infos[0].revs, vec![s!("1-a"), s!("2-b")]);
+        assert!(server.await.unwrap().starts_with("GET /testdb/_purged_infos "));
+    }
+
+    #[tokio::test]
+    async fn test_purged_infos_limit() {
+        let (uri, server) = mock_server(200, "1000").await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+
+        assert_eq!(db.purged_infos_limit().await.unwrap(), 1000);
+        assert!(server.await.unwrap().starts_with("GET /testdb/_purged_infos_limit "));
+    }
+
+    #[tokio::test]
+    async fn test_set_purged_infos_limit() {
+        let (uri, server) = mock_server(200, r#"{"ok":true}"#).await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+
+        assert!(db.set_purged_infos_limit(2000).await.unwrap());
+        let request = server.await.unwrap();
+        assert!(request.starts_with("PUT /testdb/_purged_infos_limit "));
+        assert!(request.ends_with("\r\n\r\n2000"));
+    }
+
+    #[tokio::test]
+    async fn test_get_bulk_revs() {
+        let (uri, server) = mock_server(
+            200,
+            r#"{"total_rows":2,"rows":[
+                {"id":"1","key":"1","value":{"rev":"1-a"}},
+                {"key":"2","error":"not_found"}
+            ]}"#,
+        )
+        .await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+
+        let revs = db.get_bulk_revs(vec![s!("1"), s!("2")]).await.unwrap();
+        assert_eq!(revs, HashMap::from([(s!("1"), s!("1-a"))]));
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /testdb/_all_docs "));
+        assert!(request.contains(r#""include_docs":false"#));
+        assert!(request.contains(r#""keys":["1","2"]"#));
+    }
+
+    #[tokio::test]
+    async fn test_bulk_get_raw() {
+        let (uri, server) = mock_server(
+            200,
+            r#"{"results":[
+                {"id":"1","docs":[{"ok":{"_id":"1","_rev":"1-a"}}]},
+                {"id":"2","docs":[{"error":{"id":"2","rev":"1-b","error":"not_found","reason":"missing"}}]}
+            ]}"#,
+        )
+        .await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+
+        let docs = db
+            .bulk_get_raw(vec![BulkGetItem::new("1"), BulkGetItem::new("2").rev("1-b")])
+            .await
+            .unwrap();
+        assert_eq!(docs.rows, vec![json!({"_id": "1", "_rev": "1-a"})]);
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /testdb/_bulk_get "));
+        assert!(request.contains(r#"{"docs":[{"id":"1"},{"id":"2","rev":"1-b"}]}"#));
+    }
+
+    #[tokio::test]
+    async fn test_patch_missing_document() {
+        let (uri, server) = mock_server(404, r#"{"error":"not_found","reason":"missing"}"#).await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+
+        let err = db.patch("1", |doc| doc["name"] = json!("john")).await.unwrap_err();
+        assert!(err.is_not_found());
+        assert!(server.await.unwrap().starts_with("GET /testdb/1 "));
+    }
+
+    #[tokio::test]
+    async fn test_dump_ends_after_error() {
+        let (uri, server) = mock_server(404, r#"{"error":"not_found","reason":"Database does not exist."}"#).await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+
+        let mut dump = Box::pin(db.dump());
+        assert!(dump.next().await.unwrap().unwrap_err().is_not_found());
+        assert!(dump.next().await.is_none());
+        let request = server.await.unwrap();
+        assert!(request.starts_with("GET /testdb/_changes?"));
+        assert!(request.lines().next().unwrap().contains("style=all_docs"));
+    }
+
+    #[tokio::test]
+    async fn test_bulk_docs_replicated() {
+        let (uri, server) = mock_server(201, "[]").await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+
+        db.bulk_docs_replicated(&[json!({"_id": "1", "_rev": "1-a"})])
+            .await
+            .unwrap();
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /testdb/_bulk_docs "));
+        assert!(request.contains(r#""new_edits":false"#));
+    }
+
+    #[tokio::test]
+    async fn test_bulk_docs_replicated_returns_failures() {
+        let (uri, _server) = mock_server(201, r#"[{"id":"1","error":"forbidden","reason":"read only"}]"#).await;
+        let db = Database::new("testdb".to_string(), Client::new_no_auth(&uri).unwrap());
+
+        let err = db
+            .bulk_docs_replicated(&[json!({"_id": "1", "_rev": "1-a"})])
+            .await
+            .unwrap_err();
+        assert_eq!(err.status(), Some(StatusCode::FORBIDDEN));
+        assert_eq!(err.to_string(), "1 -> 403 Forbidden: read only");
+    }
+
     fn build_json_response(body: &'static str) -> Response {
         let url = Url::parse("http://example.com").unwrap();
         let response = Builder::new().status(200).url(url).body(body).unwrap();
@@ -1,8 +1,10 @@
+use crate::error::{CouchError, CouchResult, ErrorMessage};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_json::Value;
 use std::{
     borrow::Cow,
     ops::{Index, IndexMut},
+    sync::Arc,
 };
 
 pub const ID_FIELD: &str = "_id";
@@ -14,9 +16,9 @@ pub const REV_FIELD: &str = "_rev";
 /// because `set_rev` will be called before returning the document to the user, so the user will always see the correct value.
 pub trait TypedCouchDocument: DeserializeOwned + Serialize + Sized {
     /// get the _id field
-    fn get_id(&self) -> Cow<str>;
+    fn get_id(&self) -> Cow<'_, str>;
     /// get the _rev field
-    fn get_rev(&self) -> Cow<str>;
+    fn get_rev(&self) -> Cow<'_, str>;
     /// set the _rev field
     fn set_rev(&mut self, rev: &str);
     /// set the _id field
@@ -27,12 +29,12 @@ pub trait TypedCouchDocument: DeserializeOwned + Serialize + Sized {
 
 /// Allows dealing with _id and _rev fields in untyped (Value) documents
 impl TypedCouchDocument for Value {
-    fn get_id(&self) -> Cow<str> {
+    fn get_id(&self) -> Cow<'_, str> {
         let id: String = json_extr!(self[ID_FIELD]);
         Cow::from(id)
     }
 
-    fn get_rev(&self) -> Cow<str> {
+    fn get_rev(&self) -> Cow<'_, str> {
         let rev: String = json_extr!(self[REV_FIELD]);
         Cow::from(rev)
     }
@@ -64,6 +66,8 @@ pub struct DocumentCollection<T: TypedCouchDocument> {
     pub rows: Vec<T>,
     pub total_rows: u32,
     pub bookmark: Option<String>,
+    /// The update sequence of the database, if requested through `update_seq=true`
+    pub update_seq: Option<Value>,
 }
 
 impl<T: TypedCouchDocument> Default for DocumentCollection<T> {
@@ -73,6 +77,7 @@ impl<T: TypedCouchDocument> Default for DocumentCollection<T> {
             rows: vec![],
             total_rows: 0,
             bookmark: None,
+            update_seq: None,
         }
     }
 }
@@ -83,6 +88,46 @@ pub struct AllDocsResponse<T: TypedCouchDocument> {
     pub total_rows: Option<u32>,
     pub offset: Option<u32>,
     pub rows: Vec<DocResponse<T>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub update_seq: Option<Value>,
+}
+
+impl AllDocsResponse<Value> {
+    /// Converts the documents to `T`; a document that does not match `T` fails with its id in the error.
+    pub(crate) fn into_typed<T: TypedCouchDocument>(self) -> CouchResult<AllDocsResponse<T>> {
+        let rows = self
+            .rows
+            .into_iter()
+            .map(|row| {
+                Ok(DocResponse {
+                    id: row.id,
+                    key: row.key,
+                    value: row.value,
+                    error: row.error,
+                    doc: row.doc.map(from_document_value).transpose()?,
+                })
+            })
+            .collect::<CouchResult<_>>()?;
+        Ok(AllDocsResponse {
+            total_rows: self.total_rows,
+            offset: self.offset,
+            rows,
+            update_seq: self.update_seq,
+        })
+    }
+}
+
+/// Deserializes a document to `T`. Unlike a plain `from_value`, the error names the id of the document,
+/// to tell which document of a batch does not match `T`.
+pub(crate) fn from_document_value<T: DeserializeOwned>(value: Value) -> CouchResult<T> {
+    let id = value.get(ID_FIELD).and_then(Value::as_str).map(str::to_string);
+    serde_json::from_value(value).map_err(|err| match id {
+        Some(id) => CouchError::InvalidJson(ErrorMessage {
+            message: format!("document {id}: {err}"),
+            upstream: Some(Arc::new(err)),
+        }),
+        None => err.into(),
+    })
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
@@ -126,6 +171,7 @@ impl<T: TypedCouchDocument> DocumentCollection<T> {
             total_rows: u32::try_from(items.len()).expect("total_rows > u32::MAX is not supported"),
             rows: items,
             bookmark: Option::None,
+            update_seq: doc.update_seq,
         }
     }
 
@@ -141,6 +187,7 @@ impl<T: TypedCouchDocument> DocumentCollection<T> {
             total_rows: len,
             rows: docs,
             bookmark,
+            update_seq: None,
         }
     }
 
@@ -160,6 +207,7 @@ impl<T: TypedCouchDocument> DocumentCollection<T> {
                 .filter_map(|d| serde_json::from_value::<T>(d).ok())
                 .collect(),
             bookmark,
+            update_seq: None,
         }
     }
 
@@ -187,9 +235,11 @@ impl<T: TypedCouchDocument> IndexMut<usize> for DocumentCollection<T> {
 #[cfg(test)]
 mod tests {
     use crate as couch_rs;
-    use crate::document::TypedCouchDocument;
+    use crate::document::{AllDocsResponse, DocumentCollection, TypedCouchDocument};
+    use crate::error::CouchError;
     use couch_rs_derive::CouchDocument;
     use serde::{Deserialize, Serialize};
+    use serde_json::{json, Value};
 
     #[derive(Serialize, Deserialize, CouchDocument, Debug, Default)]
     struct TestDocument {
@@ -199,6 +249,38 @@ mod tests {
         pub _rev: String,
     }
 
+    #[test]
+    fn test_document_collection_with_update_seq() {
+        let response: AllDocsResponse<Value> = serde_json::from_value(json!({
+            "total_rows": 1,
+            "offset": 0,
+            "update_seq": "5-g1AAAAB",
+            "rows": [
+                {"id": "1", "key": "1", "value": {"rev": "1-abc"}, "doc": {"_id": "1", "_rev": "1-abc"}}
+            ]
+        }))
+        .unwrap();
+        let collection = DocumentCollection::new(response);
+        assert_eq!(collection.rows.len(), 1);
+        assert_eq!(collection.update_seq, Some(json!("5-g1AAAAB")));
+    }
+
+    #[test]
+    fn test_into_typed_names_the_invalid_document() {
+        let response: AllDocsResponse<Value> = serde_json::from_value(json!({
+            "total_rows": 2,
+            "offset": 0,
+            "rows": [
+                {"id": "1", "key": "1", "value": {"rev": "1-a"}, "doc": {"_id": "1", "_rev": "1-a"}},
+                {"id": "2", "key": "2", "value": {"rev": "1-b"}, "doc": {"_id": "2", "_rev": 7}}
+            ]
+        }))
+        .unwrap();
+        let err = response.into_typed::<TestDocument>().unwrap_err();
+        assert!(matches!(err, CouchError::InvalidJson(_)));
+        assert!(err.to_string().starts_with("document 2: invalid type"), "{err}");
+    }
+
     #[test]
     fn test_derive_couch_document() {
         let doc = TestDocument {
@@ -210,4 +292,24 @@ mod tests {
         assert_eq!(id, "1");
         assert_eq!(rev, "2");
     }
+
+    #[test]
+    fn test_merge_ids_from_borrowed_document() {
+        let stored = TestDocument {
+            _id: "1".to_string(),
+            _rev: "2-abc".to_string(),
+        };
+        let mut doc = TestDocument::default();
+        doc.merge_ids(&stored);
+        assert_eq!(doc.get_id(), "1");
+        assert_eq!(doc.get_rev(), "2-abc");
+        // the other document is only borrowed, so it can still be used
+        assert_eq!(stored.get_rev(), "2-abc");
+
+        let stored = json!({"_id": "1", "_rev": "2-abc"});
+        let mut doc = json!({"name": "john"});
+        doc.merge_ids(&stored);
+        assert_eq!(doc, json!({"_id": "1", "_rev": "2-abc", "name": "john"}));
+        assert_eq!(stored.get_id(), "1");
+    }
 }
@@ -3,7 +3,7 @@ use std::{error, fmt, sync::Arc};
 // Define our error types. These may be customized for our error handling cases.
 // Now we will be able to write our own errors, defer to an underlying error
 // implementation, or do something in between.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum CouchError {
     /// A `CouchDB` operation failed, typically indicated by a specific HTTP error status that was returned.
     OperationFailed(ErrorDetails),
@@ -13,6 +13,8 @@ pub enum CouchError {
     MalformedUrl(ErrorMessage),
     /// A design document could not be created.
     CreateDesignFailed(ErrorMessage),
+    /// A find query sorts on fields that are not covered by any index.
+    NoUsableIndex(NoUsableIndexDetails),
 }
 
 #[derive(Debug, Clone)]
@@ -23,6 +25,8 @@ pub struct ErrorDetails {
     pub status: http::StatusCode,
     /// Detailed error message
     pub message: String,
+    /// The `error` returned by `CouchDB`, like "conflict" or "forbidden"
+    error: Option<String>,
     upstream: Option<UpstreamError>,
 }
 
@@ -33,6 +37,34 @@ pub struct ErrorMessage {
     pub(crate) upstream: Option<UpstreamError>,
 }
 
+// The upstream error can not be compared, and the `CouchDB` error can not be set outside this crate; errors are
+// considered equal when their public fields are equal.
+impl PartialEq for ErrorDetails {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id && self.status == other.status && self.message == other.message
+    }
+}
+
+impl Eq for ErrorDetails {}
+
+impl PartialEq for ErrorMessage {
+    fn eq(&self, other: &Self) -> bool {
+        self.message == other.message
+    }
+}
+
+impl Eq for ErrorMessage {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NoUsableIndexDetails {
+    /// HTTP Status Code
+    pub status: http::StatusCode,
+    /// The fields the query sorts on, which need to be covered by an index
+    pub sort_fields: Vec<String>,
+    /// Detailed error message
+    pub message: String,
+}
+
 type UpstreamError = Arc<dyn error::Error + Send + Sync + 'static>;
 pub type CouchResult<T> = Result<T, CouchError>;
 
@@ -43,6 +75,7 @@ impl CouchError {
             id: None,
             message,
             status,
+            error: None,
             upstream: None,
         })
     }
@@ -53,19 +86,59 @@ impl CouchError {
             id,
             message,
             status,
+            error: None,
             upstream: None,
         })
     }
 
+    /// Records the `error` returned by `CouchDB`, like "conflict" or "forbidden".
+    #[must_use]
+    pub(crate) fn with_couch_error(mut self, error: Option<String>) -> CouchError {
+        if let CouchError::OperationFailed(details) = &mut self {
+            details.error = error;
+        }
+        self
+    }
+
     #[must_use]
     pub fn is_not_found(&self) -> bool {
         self.status() == Some(http::StatusCode::NOT_FOUND)
     }
 
+    /// Whether the server (or a proxy in front of it) is temporarily unavailable, e.g. during a rolling
+    /// upgrade: a 502, 503 or 504 status. These errors are typically worth retrying, unlike a 404.
+    #[must_use]
+    pub fn is_server_unavailable(&self) -> bool {
+        matches!(
+            self.status(),
+            Some(
+                http::StatusCode::BAD_GATEWAY
+                    | http::StatusCode::SERVICE_UNAVAILABLE
+                    | http::StatusCode::GATEWAY_TIMEOUT
+            )
+        )
+    }
+
+    /// Whether `CouchDB` rejected a write with a `forbidden` or `unauthorized` error, which is how a
+    /// `validate_doc_update` function rejects a document; the message holds the reason it gave.
+    ///
+    /// Note that `CouchDB` returns the same errors when the user lacks the permissions for the request, so
+    /// this can only tell a validation error apart from errors like conflicts or unavailable servers.
+    #[must_use]
+    pub fn is_validation_error(&self) -> bool {
+        match self {
+            CouchError::OperationFailed(details) => {
+                matches!(details.error.as_deref(), Some("forbidden" | "unauthorized"))
+            }
+            _ => false,
+        }
+    }
+
     #[must_use]
     pub fn status(&self) -> Option<http::StatusCode> {
         match self {
             CouchError::OperationFailed(details) => Some(details.status),
+            CouchError::NoUsableIndex(details) => Some(details.status),
             _ => None,
         }
     }
@@ -104,6 +177,15 @@ impl fmt::Display for CouchError {
             CouchError::InvalidJson(err) | CouchError::MalformedUrl(err) | CouchError::CreateDesignFailed(err) => {
                 write!(f, "{}", err.message)
             }
+            CouchError::NoUsableIndex(details) => {
+                write!(
+                    f,
+                    "{}: {} (sort fields: {})",
+                    details.status,
+                    details.message,
+                    details.sort_fields.join(", ")
+                )
+            }
         }
     }
 }
@@ -117,6 +199,7 @@ impl error::Error for CouchError {
             CouchError::InvalidJson(err) | CouchError::MalformedUrl(err) | CouchError::CreateDesignFailed(err) => {
                 err.upstream.as_ref().map(|e| &**e as _)
             }
+            CouchError::NoUsableIndex(_) => None,
         }
     }
 }
@@ -127,6 +210,7 @@ impl std::convert::From<reqwest::Error> for CouchError {
             id: None,
             status: err.status().unwrap_or(http::StatusCode::NOT_IMPLEMENTED),
             message: err.to_string(),
+            error: None,
             upstream: Some(Arc::new(err)),
         })
     }
@@ -149,3 +233,70 @@ impl std::convert::From<url::ParseError> for CouchError {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare_errors() {
+        let conflict = CouchError::new(s!("conflict"), http::StatusCode::CONFLICT);
+        assert_eq!(conflict, CouchError::new(s!("conflict"), http::StatusCode::CONFLICT));
+        assert_ne!(conflict, CouchError::new(s!("conflict"), http::StatusCode::NOT_FOUND));
+        assert_ne!(
+            conflict,
+            CouchError::new_with_id(Some(s!("1")), s!("conflict"), http::StatusCode::CONFLICT)
+        );
+    }
+
+    #[test]
+    fn test_is_server_unavailable() {
+        for status in [
+            http::StatusCode::BAD_GATEWAY,
+            http::StatusCode::SERVICE_UNAVAILABLE,
+            http::StatusCode::GATEWAY_TIMEOUT,
+        ] {
+            let err = CouchError::new(s!("unavailable"), status);
+            assert!(err.is_server_unavailable(), "{status}");
+            assert!(!err.is_not_found());
+        }
+
+        for status in [
+            http::StatusCode::NOT_FOUND,
+            http::StatusCode::INTERNAL_SERVER_ERROR,
+            http::StatusCode::CONFLICT,
+        ] {
+            assert!(
+                !CouchError::new(s!("error"), status).is_server_unavailable(),
+                "{status}"
+            );
+        }
+        assert!(!CouchError::from(serde_json::from_str::<serde_json::Value>("{").unwrap_err()).is_server_unavailable());
+    }
+
+    #[test]
+    fn test_is_validation_error() {
+        let forbidden = CouchError::new(s!("name is required"), http::StatusCode::FORBIDDEN);
+        assert!(!forbidden.is_validation_error());
+        assert!(forbidden.with_couch_error(Some(s!("forbidden"))).is_validation_error());
+        let unauthorized = CouchError::new(s!("admins only"), http::StatusCode::UNAUTHORIZED)
+            .with_couch_error(Some(s!("unauthorized")));
+        assert!(unauthorized.is_validation_error());
+        let conflict =
+            CouchError::new(s!("conflict"), http::StatusCode::CONFLICT).with_couch_error(Some(s!("conflict")));
+        assert!(!conflict.is_validation_error());
+    }
+
+    #[test]
+    fn test_compare_errors_ignores_upstream() {
+        let json_err = serde_json::from_str::<serde_json::Value>("{").unwrap_err();
+        let message = json_err.to_string();
+        let err = CouchError::from(json_err);
+        let expected = ErrorMessage {
+            message,
+            upstream: None,
+        };
+        assert_eq!(err, CouchError::InvalidJson(expected.clone()));
+        assert_ne!(err, CouchError::MalformedUrl(expected));
+    }
+}
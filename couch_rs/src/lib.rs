@@ -185,6 +185,8 @@ mod macros {
     }
 }
 
+/// Database wrapper with an in-memory cache for documents that rarely change.
+pub mod cached;
 mod client;
 /// Database operations on a `CouchDB` Database.
 pub mod database;
@@ -197,10 +199,19 @@ pub mod management;
 /// Trait that provides methods that can be used to switch between abstract Document and
 /// concrete Model implementors (such as your custom data models)
 pub mod model;
+/// Temporary database that is destroyed when it goes out of scope.
+pub mod temp;
+/// TLS settings, like custom root certificates and client certificates.
+#[cfg(feature = "__tls")]
+pub mod tls;
+/// Typed wrapper around a `CouchDB` Database, bound to a single document type.
+pub mod typed;
 /// Data types to support `CouchDB` operations.
 pub mod types;
 
 mod changes;
+#[cfg(test)]
+mod mock_server;
 
 pub use client::Client;
 
@@ -225,6 +236,15 @@ mod couch_rs_tests {
         pub last_name: String,
     }
 
+    #[derive(Serialize, Deserialize, CouchDocument, Default, Debug)]
+    pub struct LastNameOnly {
+        #[serde(skip_serializing_if = "String::is_empty")]
+        pub _id: DocumentId,
+        #[serde(skip_serializing_if = "String::is_empty")]
+        pub _rev: String,
+        pub last_name: String,
+    }
+
     #[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
     struct TestDocImplementing {
         my_id: String,
@@ -233,11 +253,11 @@ mod couch_rs_tests {
         last_name: String,
     }
     impl TypedCouchDocument for TestDocImplementing {
-        fn get_id(&self) -> Cow<str> {
+        fn get_id(&self) -> Cow<'_, str> {
             Cow::Borrowed(&self.my_id)
         }
 
-        fn get_rev(&self) -> Cow<str> {
+        fn get_rev(&self) -> Cow<'_, str> {
             Cow::Borrowed(&self.my_rev)
         }
 
@@ -260,6 +280,7 @@ mod couch_rs_tests {
         use crate::couch_rs_tests::TestDocImplementing;
         use crate::document::TypedCouchDocument;
         use crate::error::CouchError;
+        use crate::types::document::BulkDocsReport;
         use reqwest::StatusCode;
         use serde_json::json;
 
@@ -271,6 +292,13 @@ mod couch_rs_tests {
             assert_eq!("The Apache Software Foundation", status.unwrap().vendor.name);
         }
 
+        #[tokio::test]
+        async fn should_ping() {
+            let client = Client::new_local_test().unwrap();
+            let latency = client.ping().await.expect("can not ping");
+            assert!(latency > std::time::Duration::ZERO);
+        }
+
         #[tokio::test]
         async fn should_create_test_db() {
             let client = Client::new_local_test().unwrap();
@@ -548,6 +576,12 @@ mod couch_rs_tests {
             assert!(second_result.is_err());
             assert_eq!(second_result.err().unwrap().status(), Some(StatusCode::CONFLICT));
 
+            let mut docs = vec![json!({"_id":"second"}), json!({"_id":"first"})];
+            let report = BulkDocsReport::from(db.bulk_docs(&mut docs).await.expect("can not bulk save"));
+            assert_eq!(report.succeeded().len(), 1);
+            assert_eq!(report.conflicts(), vec!["first"]);
+            assert_eq!(report.errors().len(), 1);
+
             client.destroy_db(dbname).await.expect("can not destroy db");
         }
 
@@ -558,20 +592,123 @@ mod couch_rs_tests {
 
             assert!(client.destroy_db("should_destroy_the_db").await.unwrap());
         }
+
+        #[tokio::test]
+        async fn should_get_information_on_multiple_dbs() {
+            let client = Client::new_local_test().unwrap();
+            let dbnames = vec![
+                s!("should_get_info_on_multiple_dbs_1"),
+                s!("should_get_info_on_multiple_dbs_2"),
+            ];
+            for dbname in &dbnames {
+                client.db(dbname).await.expect("can not create db");
+            }
+
+            let mut names = dbnames.clone();
+            names.push(s!("should_get_info_on_multiple_dbs_missing"));
+            let infos = client.dbs_info(names).await.expect("can not get dbs info");
+            assert_eq!(infos.len(), 2);
+            assert_eq!(infos[0].db_name, dbnames[0]);
+            assert_eq!(infos[1].db_name, dbnames[1]);
+
+            for dbname in &dbnames {
+                client.destroy_db(dbname).await.expect("can not destroy db");
+            }
+        }
+
+        #[tokio::test]
+        async fn should_list_dbs_paged() {
+            let client = Client::new_local_test().unwrap();
+            let dbnames = vec![
+                s!("should_list_dbs_paged_a"),
+                s!("should_list_dbs_paged_b"),
+                s!("should_list_dbs_paged_c"),
+            ];
+            for dbname in &dbnames {
+                client.db(dbname).await.expect("can not create db");
+            }
+
+            let page = client
+                .list_dbs_paged(Some("should_list_dbs_paged_"), 2)
+                .await
+                .expect("can not list dbs");
+            assert_eq!(page, dbnames[0..2]);
+
+            let page = client
+                .list_dbs_paged(Some(&dbnames[1]), 2)
+                .await
+                .expect("can not list dbs");
+            assert_eq!(page, dbnames[1..3]);
+
+            for dbname in &dbnames {
+                client.destroy_db(dbname).await.expect("can not destroy db");
+            }
+        }
+
+        #[tokio::test]
+        async fn should_destroy_a_closed_temp_db() {
+            let client = Client::new_local_test().unwrap();
+            let dbname = "should_destroy_a_closed_temp_db";
+            let db = client.temp_db(dbname).await.expect("can not create temp db");
+            let mut doc = json!({"first_name": "John"});
+            db.create(&mut doc).await.expect("can not create document");
+            assert!(client.exists(dbname).await.unwrap());
+
+            assert!(db.close().await.unwrap());
+            assert!(!client.exists(dbname).await.unwrap());
+        }
+
+        #[tokio::test]
+        async fn should_destroy_a_dropped_temp_db() {
+            let client = Client::new_local_test().unwrap();
+            let dbname = "should_destroy_a_dropped_temp_db";
+            let db = client.temp_db(dbname).await.expect("can not create temp db");
+            drop(db);
+
+            // destruction is scheduled on the runtime; give it a moment to complete
+            for _ in 0..50 {
+                if !client.exists(dbname).await.unwrap() {
+                    return;
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            }
+            panic!("temp db was not destroyed");
+        }
+
+        #[tokio::test]
+        async fn should_destroy_a_db_if_it_exists() {
+            let client = Client::new_local_test().unwrap();
+            let dbname = "should_destroy_a_db_if_it_exists";
+            client.db(dbname).await.expect("can not create db");
+
+            assert!(client.destroy_db_if_exists(dbname).await.expect("can not destroy db"));
+            assert!(!client
+                .destroy_db_if_exists(dbname)
+                .await
+                .expect("missing db should be ok"));
+        }
     }
 
     mod database_tests {
+        use crate::cached::CachedDatabase;
         use crate::document::{DocumentCollection, TypedCouchDocument};
-        use crate::error::CouchResultExt;
+        use crate::error::{CouchError, CouchResultExt};
         use crate::management::ClusterSetup;
         use crate::management::EnsureDbsExist;
         use crate::types;
+        use crate::types::design::{DesignDocument, IndexStatus};
+        use crate::types::document::DocumentId;
         use crate::types::find::FindQuery;
-        use crate::types::query::{QueriesParams, QueryParams};
-        use crate::types::view::{CouchFunc, CouchViews};
+        use crate::types::query::{max_key, min_key, QueriesParams, QueryParams};
+        use crate::types::selector::Selector;
+        use crate::types::view::{CouchFunc, CouchUpdate, CouchViews, RawViewCollection, Reduce};
         use crate::{client::Client, types::view::ViewCollection};
         use crate::{database::Database, error::CouchResult};
+        use futures_util::StreamExt;
+        use reqwest::StatusCode;
+        use serde::Deserialize;
         use serde_json::{json, Value};
+        use std::collections::HashMap;
         use tokio::sync::mpsc;
         use tokio::sync::mpsc::{Receiver, Sender};
 
@@ -1112,7 +1249,7 @@ mod couch_rs_tests {
             assert!(db
                 .create_view(
                     view_name,
-                    CouchViews::new(view_name, CouchFunc::new(count_by_id, Some("_count"))),
+                    CouchViews::new(view_name, CouchFunc::with_builtin_reduce(count_by_id, Reduce::Count)),
                 )
                 .await
                 .is_ok());
@@ -1289,5 +1426,1459 @@ mod couch_rs_tests {
                 .expect("unable to retrieve cluster setup status");
             assert_eq!(cluster_setup, ClusterSetup::ClusterEnabled);
         }
+
+        #[tokio::test]
+        async fn should_get_a_document_with_local_seq() {
+            let dbname = "should_get_a_document_with_local_seq";
+            let (client, db, mut doc) = setup(dbname).await;
+            let id = doc.get_id().into_owned();
+
+            let (first, first_seq): (Value, u64) = db.get_with_local_seq(&id).await.unwrap();
+            assert_eq!(first.get_id(), id);
+            assert!(first.get("_local_seq").is_none());
+
+            doc["thing"] = json!(false);
+            db.save(&mut doc).await.unwrap();
+
+            let (_, second_seq): (Value, u64) = db.get_with_local_seq(&id).await.unwrap();
+            assert!(second_seq > first_seq);
+
+            teardown(client, dbname).await;
+        }
+
+        #[tokio::test]
+        async fn should_bulk_upsert_many_docs_without_rev() {
+            let dbname = "should_bulk_upsert_many_docs_without_rev";
+            let (client, db, _doc) = setup(dbname).await;
+            let count = 3000;
+            let mut docs: Vec<Value> = (0..count)
+                .map(|idx| json!({"_id": format!("bd_{}", idx), "count": idx}))
+                .collect();
+            db.bulk_docs(&mut docs).await.expect("should insert documents");
+
+            // strip the revs, so bulk_upsert has to fetch all of them
+            let mut docs: Vec<Value> = (0..count)
+                .map(|idx| json!({"_id": format!("bd_{}", idx), "count": idx, "updated": true}))
+                .collect();
+            let res = db.bulk_upsert(&mut docs).await.expect("should upsert documents");
+
+            assert_eq!(res.len(), count);
+            for (result, doc) in res.iter().zip(docs.iter()) {
+                let details = result.as_ref().expect("upsert should succeed");
+                assert!(details.rev.starts_with("2-"));
+                assert_eq!(details.rev, doc.get_rev());
+            }
+
+            teardown(client, dbname).await;
+        }
+
+        #[tokio::test]
+        async fn should_bulk_upsert_existing_and_new_docs() {
+            let dbname = "should_bulk_upsert_existing_and_new_docs";
+            let (client, db, _doc) = setup(dbname).await;
+            let mut docs = vec![json!({"_id": "existing_1"}), json!({"_id": "existing_2"})];
+            db.bulk_docs(&mut docs).await.expect("should insert documents");
+
+            let mut docs = vec![
+                json!({"_id": "existing_1", "updated": true}),
+                json!({"_id": "does_not_exist_yet", "updated": true}),
+                json!({"_id": "existing_2", "updated": true}),
+            ];
+            let res = db.bulk_upsert(&mut docs).await.expect("should upsert documents");
+
+            let revs: Vec<String> = res.into_iter().map(|r| r.expect("upsert should succeed").rev).collect();
+            assert!(revs[0].starts_with("2-"));
+            assert!(revs[1].starts_with("1-"));
+            assert!(revs[2].starts_with("2-"));
+
+            teardown(client, dbname).await;
+        }
+
+        #[tokio::test]
+        async fn should_bulk_insert_and_get_docs_in_chunks() {
+            let dbname = "should_bulk_insert_and_get_docs_in_chunks";
+            let (client, db, _doc) = setup(dbname).await;
+            let count = 25;
+            let mut docs: Vec<Value> = (0..count)
+                .map(|idx| json!({"_id": format!("bd_{:02}", idx), "count": idx}))
+                .collect();
+
+            // 25 documents in chunks of 10 does not divide evenly
+            let res = db
+                .bulk_docs_chunked(&mut docs, 10)
+                .await
+                .expect("should insert documents");
+            assert_eq!(res.len(), count);
+            assert!(res.iter().all(Result::is_ok));
+            assert!(docs.iter().all(|doc| !doc.get_rev().is_empty()));
+
+            let ids: Vec<String> = (0..count).map(|idx| format!("bd_{:02}", idx)).collect();
+            let collection = db
+                .bulk_get_chunked::<Value>(ids.clone(), 7)
+                .await
+                .expect("should get documents");
+            assert_eq!(collection.rows.len(), count);
+            let fetched: Vec<String> = collection.rows.iter().map(|doc| doc.get_id().into_owned()).collect();
+            assert_eq!(fetched, ids);
+
+            teardown(client, dbname).await;
+        }
+
+        #[tokio::test]
+        async fn should_snapshot_and_tail_changes_from_update_seq() {
+            let dbname = "should_snapshot_and_tail_changes_from_update_seq";
+            let (client, db, _doc) = setup(dbname).await;
+
+            let snapshot = db
+                .get_all_params_raw(Some(QueryParams::default().update_seq(true)))
+                .await
+                .expect("can not get all docs");
+            assert_eq!(snapshot.rows.len(), 1);
+            let update_seq = snapshot.update_seq.expect("update_seq should be returned");
+
+            let mut new_doc = json!({"_id": "after_snapshot"});
+            db.create(&mut new_doc).await.expect("can not create doc");
+
+            let mut changes = db.changes(Some(update_seq));
+            let change = changes
+                .next()
+                .await
+                .expect("should have a change")
+                .expect("change should be ok");
+            assert_eq!(change.id, "after_snapshot");
+            assert!(changes.next().await.is_none());
+
+            teardown(client, dbname).await;
+        }
+
+        #[tokio::test]
+        async fn should_find_all_docs_following_bookmarks() {
+            let dbname = "should_find_all_docs_following_bookmarks";
+            let client = Client::new_local_test().unwrap();
+            let db = client.db(dbname).await.expect("can not create db");
+
+            let mut docs: Vec<Value> = (0..2500).map(|i| json!({"thing": true, "index": i})).collect();
+            db.bulk_docs_chunked(&mut docs, 0).await.expect("can not insert docs");
+
+            let query = FindQuery::new(json!({"thing": true}));
+            let found: Vec<Value> = db.find_all(query.clone(), 1000).await.expect("can not find docs");
+            assert_eq!(found.len(), 2500);
+
+            let capped: Vec<Value> = db.find_all(query.limit(1500), 1000).await.expect("can not find docs");
+            assert_eq!(capped.len(), 1500);
+
+            teardown(client, dbname).await;
+        }
+
+        #[tokio::test]
+        async fn should_assert_a_query_is_indexed() {
+            let dbname = "should_assert_a_query_is_indexed";
+            let (client, db, _) = setup_create_indexes(dbname).await;
+
+            let indexed = FindQuery::new(json!({"thing": true}));
+            let explained = db.explain(&indexed).await.expect("can not explain query");
+            assert_eq!(explained.index.name, "thing-index");
+            assert!(db.assert_indexed(&indexed).await.is_ok());
+
+            let unindexed = FindQuery::new(json!({"other_thing": true}));
+            assert!(db.assert_indexed(&unindexed).await.is_err());
+
+            teardown(client, dbname).await;
+        }
+
+        #[tokio::test]
+        async fn should_get_a_document_as_bytes() {
+            let dbname = "should_get_a_document_as_bytes";
+            let (client, db, doc) = setup(dbname).await;
+            let id = doc.get_id().into_owned();
+
+            let bytes = db.get_bytes(&id).await.expect("can not get document bytes");
+            let value: Value = serde_json::from_slice(&bytes).expect("can not parse document bytes");
+            assert_eq!(value, db.get_raw(&id).await.unwrap());
+            // CouchDB terminates its responses with a newline, which a re-serialized Value would not contain
+            assert!(bytes.ends_with(b"\n"));
+            assert_ne!(bytes, serde_json::to_vec(&value).unwrap());
+
+            teardown(client, dbname).await;
+        }
+
+        #[tokio::test]
+        async fn should_report_the_fields_of_an_unindexed_sort() {
+            let dbname = "should_report_the_fields_of_an_unindexed_sort";
+            let (client, db, _) = setup(dbname).await;
+
+            let query = FindQuery::new(json!({"thing": true})).sort(vec![types::find::SortSpec::Simple(s!("thing"))]);
+            let err = db
+                .find_raw(&query)
+                .await
+                .expect_err("sort should not be covered by an index");
+            match err {
+                CouchError::NoUsableIndex(details) => {
+                    assert_eq!(details.status, StatusCode::BAD_REQUEST);
+                    assert_eq!(details.sort_fields, vec![s!("thing")]);
+                }
+                _ => panic!("unexpected error: {err}"),
+            }
+
+            teardown(client, dbname).await;
+        }
+
+        #[tokio::test]
+        async fn should_query_multiple_views_of_one_design() {
+            let dbname = "should_query_multiple_views_of_one_design";
+            let (client, db, _) = setup_multiple(dbname, 3).await;
+
+            let views = CouchViews::default()
+                .view("by_id", CouchFunc::new("function (doc) { emit(doc._id, null); }", None))
+                .view(
+                    "count",
+                    CouchFunc::with_builtin_reduce("function (doc) { emit(doc._id, null); }", Reduce::Count),
+                );
+            db.create_view("multi", views).await.expect("can not create views");
+
+            let by_id = db.query_raw("multi", "by_id", None).await.expect("can not query by_id");
+            assert_eq!(by_id.rows.len(), 3);
+            let count = db.query_raw("multi", "count", None).await.expect("can not query count");
+            assert_eq!(count.rows[0].value, json!(3));
+
+            teardown(client, dbname).await;
+        }
+
+        #[tokio::test]
+        async fn should_install_and_execute_an_update_function() {
+            let dbname = "should_install_and_execute_an_update_function";
+            let (client, db, doc) = setup(dbname).await;
+            let id = doc.get_id().into_owned();
+
+            let views = CouchViews::new("by_id", CouchFunc::new("function (doc) { emit(doc._id, null); }", None));
+            db.create_view("design", views).await.expect("can not create view");
+
+            let update = CouchUpdate::new(
+                "touch",
+                "function (doc, req) { doc.touched = true; return [doc, 'touched']; }",
+            );
+            db.create_update_functions("design", update)
+                .await
+                .expect("can not create update function");
+
+            let result = db
+                .execute_update("design", "touch", &id, None)
+                .await
+                .expect("can not execute update function");
+            assert_eq!(result, "touched");
+            assert_eq!(db.get_raw(&id).await.unwrap()["touched"], json!(true));
+
+            // the existing view is preserved
+            assert!(db.query_raw("design", "by_id", None).await.is_ok());
+
+            teardown(client, dbname).await;
+        }
+
+        #[tokio::test]
+        async fn should_count_docs() {
+            let dbname = "should_count_docs";
+            let (client, db, mut docs) = setup_multiple(dbname, 5).await;
+            assert_eq!(db.doc_count().await.unwrap(), 5);
+            assert_eq!(db.deleted_count().await.unwrap(), 0);
+
+            assert!(db.remove(&docs.pop().unwrap()).await);
+            assert_eq!(db.doc_count().await.unwrap(), 4);
+            assert_eq!(db.deleted_count().await.unwrap(), 1);
+
+            teardown(client, dbname).await;
+        }
+
+        #[tokio::test]
+        #[ignore = "requires a CouchDB with search (Clouseau) enabled"]
+        async fn should_create_a_text_index() {
+            let dbname = "should_create_a_text_index";
+            let (client, db, _) = setup(dbname).await;
+
+            let def = types::index::TextIndexDef::new(vec![types::index::TextIndexField::new(
+                "first_name",
+                types::index::TextFieldType::String,
+            )])
+            .analyzer("standard");
+            db.insert_index("name-text-index", def, None, None)
+                .await
+                .expect("can not create text index");
+
+            let index_list = db.read_indexes().await.expect("can not read indexes");
+            let index = index_list
+                .indexes
+                .iter()
+                .find(|index| index.name == "name-text-index")
+                .expect("text index is not listed");
+            assert_eq!(index.index_type, Some(types::index::IndexType::Text));
+
+            teardown(client, dbname).await;
+        }
+
+        #[tokio::test]
+        async fn should_read_indexes_for_ddoc() {
+            let dbname = "should_read_indexes_for_ddoc";
+            let (client, db, _) = setup(dbname).await;
+
+            let spec = types::index::IndexFields::new(vec![types::find::SortSpec::Simple(s!("thing"))]);
+            db.insert_index("thing-index", spec.clone(), None, Some(s!("ddoc-a")))
+                .await
+                .expect("can not create index");
+            db.insert_index("other-index", spec, None, Some(s!("ddoc-b")))
+                .await
+                .expect("can not create index");
+
+            let indexes = db.read_indexes_for_ddoc("ddoc-a").await.expect("can not read indexes");
+            assert_eq!(indexes.len(), 1);
+            assert_eq!(indexes[0].name, "thing-index");
+
+            let indexes = db
+                .read_indexes_for_ddoc("_design/ddoc-b")
+                .await
+                .expect("can not read indexes");
+            assert_eq!(indexes.len(), 1);
+            assert_eq!(indexes[0].name, "other-index");
+
+            teardown(client, dbname).await;
+        }
+
+        #[tokio::test]
+        async fn should_delete_an_index_read_from_the_db() {
+            let dbname = "should_delete_an_index_read_from_the_db";
+            let (client, db, _) = setup_create_indexes(dbname).await;
+
+            let indexes = db.read_indexes().await.expect("can not read indexes").indexes;
+            let primary = indexes.iter().find(|index| index.name == "_all_docs").unwrap();
+            assert!(db.delete_index_spec(primary).await.is_err());
+
+            let index = indexes.iter().find(|index| index.name == "thing-index").unwrap();
+            assert!(db.delete_index_spec(index).await.expect("can not delete index"));
+
+            let indexes = db.read_indexes().await.expect("can not read indexes").indexes;
+            assert!(indexes.iter().all(|index| index.name != "thing-index"));
+
+            teardown(client, dbname).await;
+        }
+
+        #[tokio::test]
+        async fn should_not_delete_a_nonexistent_index() {
+            let dbname = "should_not_delete_a_nonexistent_index";
+            let (client, db, _) = setup(dbname).await;
+
+            let deleted = db.delete_index(s!("_design/missing"), s!("missing-index")).await;
+            assert_eq!(deleted, Ok(false));
+
+            teardown(client, dbname).await;
+        }
+
+        #[tokio::test]
+        async fn should_get_all_leaf_revs_of_a_conflicted_doc() {
+            let dbname = "should_get_all_leaf_revs_of_a_conflicted_doc";
+            let (client, db, _) = setup(dbname).await;
+
+            // create two conflicting revisions of the same document
+            let body = json!({
+                "new_edits": false,
+                "docs": [
+                    {"_id": "conflicted", "_rev": "1-aaa", "thing": "a"},
+                    {"_id": "conflicted", "_rev": "1-bbb", "thing": "b"}
+                ]
+            });
+            client
+                .req(reqwest::Method::POST, &format!("{}/_bulk_docs", db.name()), None)
+                .body(body.to_string())
+                .send()
+                .await
+                .expect("can not create conflicting revisions")
+                .error_for_status()
+                .expect("can not create conflicting revisions");
+
+            let mut leaves: Vec<types::document::LeafRevision<Value>> =
+                db.get_all_leaf_revs("conflicted").await.expect("can not get leaf revs");
+            leaves.sort_by(|a, b| a.rev.cmp(&b.rev));
+            assert_eq!(leaves.len(), 2);
+            assert_eq!(leaves[0].rev, "1-aaa");
+            assert_eq!(leaves[0].doc.as_ref().unwrap()["thing"], json!("a"));
+            assert_eq!(leaves[1].rev, "1-bbb");
+            assert!(!leaves[1].deleted);
+            assert_eq!(leaves[1].revisions.as_ref().unwrap().ids, vec![s!("bbb")]);
+
+            teardown(client, dbname).await;
+        }
+
+        #[tokio::test]
+        async fn should_save_returning_old_and_new_rev() {
+            let dbname = "should_save_returning_old_and_new_rev";
+            let (client, db, mut doc) = setup(dbname).await;
+
+            doc["thing"] = json!(false);
+            let (old_rev, new_rev) = db.save_returning_revs(&mut doc).await.expect("can not save document");
+            assert!(old_rev.starts_with("1-"));
+            assert!(new_rev.starts_with("2-"));
+            assert_eq!(doc.get_rev(), new_rev);
+
+            teardown(client, dbname).await;
+        }
+
+        #[tokio::test]
+        async fn should_read_attachment_stubs() {
+            let dbname = "should_read_attachment_stubs";
+            let (client, db, _) = setup(dbname).await;
+
+            let attachment = types::attachment::Attachment::new("text/plain", b"hello");
+            let mut doc = json!({
+                "_id": "with_attachment",
+                "_attachments": {"notes.txt": attachment}
+            });
+            db.create(&mut doc).await.expect("can not create document");
+
+            let stubs = db
+                .get_attachment_stubs("with_attachment")
+                .await
+                .expect("can not get attachment stubs");
+            let stub = &stubs["notes.txt"];
+            assert!(stub.stub);
+            assert_eq!(stub.length, Some(5));
+            assert_eq!(stub.content_type, "text/plain");
+            assert!(stub.digest.is_some());
+
+            let doc: Value = db
+                .get_with_attachments("with_attachment")
+                .await
+                .expect("can not get document with attachments");
+            let attachments: types::attachment::Attachments =
+                serde_json::from_value(doc["_attachments"].clone()).unwrap();
+            assert_eq!(attachments["notes.txt"].decoded_data(), Some(b"hello".to_vec()));
+
+            teardown(client, dbname).await;
+        }
+
+        #[tokio::test]
+        async fn should_explain_a_missing_rev_on_save() {
+            let dbname = "should_explain_a_missing_rev_on_save";
+            let (client, db, doc) = setup(dbname).await;
+
+            let mut without_rev = json!({"_id": doc.get_id(), "thing": false});
+            let err = db
+                .save(&mut without_rev)
+                .await
+                .expect_err("save without a rev should fail");
+            assert_eq!(err.status(), Some(StatusCode::CONFLICT));
+            assert!(err.to_string().contains("a _rev is required"));
+
+            teardown(client, dbname).await;
+        }
+
+        #[tokio::test]
+        async fn should_create_with_id() {
+            let dbname = "should_create_with_id";
+            let (client, db, _doc) = setup(dbname).await;
+
+            let mut doc = json!({"thing": true});
+            let details = db
+                .create_with_id("chosen-id", &mut doc)
+                .await
+                .expect("can not create doc");
+            assert_eq!(details.id, "chosen-id");
+            assert!(details.rev.starts_with('1'));
+            assert_eq!(doc.get_id(), "chosen-id");
+
+            let fetched: Value = db.get("chosen-id").await.expect("can not get doc");
+            assert_eq!(fetched["thing"], json!(true));
+
+            let err = db
+                .create_with_id("chosen-id", &mut json!({"thing": false}))
+                .await
+                .expect_err("creating twice should fail");
+            assert_eq!(err.status(), Some(StatusCode::CONFLICT));
+
+            teardown(client, dbname).await;
+        }
+
+        #[tokio::test]
+        async fn should_query_grouped() {
+            let dbname = "should_query_grouped";
+            let client = Client::new_local_test().unwrap();
+            let db = client.db(dbname).await.expect("can not create db");
+
+            let mut docs = vec![
+                json!({"year": 2022, "month": 12, "amount": 1}),
+                json!({"year": 2023, "month": 1, "amount": 2}),
+                json!({"year": 2023, "month": 2, "amount": 3}),
+                json!({"year": 2024, "month": 1, "amount": 4}),
+            ];
+            db.bulk_docs(&mut docs).await.expect("can not insert docs");
+
+            let couch_func = CouchFunc::with_builtin_reduce(
+                "function (doc) { emit([doc.year, doc.month], doc.amount); }",
+                Reduce::Sum,
+            );
+            db.create_view("reports", CouchViews::new("by_date", couch_func))
+                .await
+                .expect("can not create view");
+
+            let result: RawViewCollection<Value, u64> = db
+                .query_grouped("reports", "by_date", 1, None)
+                .await
+                .expect("can not query grouped");
+            let rows: Vec<_> = result.rows.into_iter().map(|r| (r.key, r.value)).collect();
+            assert_eq!(rows, vec![(json!([2022]), 1), (json!([2023]), 5), (json!([2024]), 4)]);
+
+            let result: RawViewCollection<Value, u64> = db
+                .query_grouped("reports", "by_date", 1, Some((json!([2023]), json!([2023, {}]))))
+                .await
+                .expect("can not query grouped range");
+            assert_eq!(result.rows.len(), 1);
+            assert_eq!(result.rows[0].key, json!([2023]));
+            assert_eq!(result.rows[0].value, 5);
+
+            client.destroy_db(dbname).await.expect("can not destroy db");
+        }
+
+        #[tokio::test]
+        async fn should_validate_cached_documents() {
+            let dbname = "should_validate_cached_documents";
+            let (client, db, mut doc) = setup(dbname).await;
+            let id = doc.get_id().into_owned();
+            let cached_db = CachedDatabase::new(db.clone(), 10).validate_with_etag(true);
+
+            let first: Value = cached_db.get(&id).await.expect("can not get doc");
+            assert_eq!(first.get_rev(), doc.get_rev());
+
+            doc["thing"] = json!(false);
+            db.save(&mut doc).await.expect("can not update doc");
+
+            let second: Value = cached_db.get(&id).await.expect("can not get doc");
+            assert_eq!(second.get_rev(), doc.get_rev());
+            assert_eq!(second["thing"], json!(false));
+
+            teardown(client, dbname).await;
+        }
+
+        #[tokio::test]
+        async fn should_find_stream_without_duplicates() {
+            let dbname = "should_find_stream_without_duplicates";
+            let client = Client::new_local_test().unwrap();
+            let db = client.db(dbname).await.expect("can not create db");
+
+            let mut docs: Vec<Value> = (0..6)
+                .map(|rank| json!({"_id": format!("doc{rank}"), "rank": rank}))
+                .collect();
+            db.bulk_docs(&mut docs).await.expect("can not insert docs");
+            db.insert_index(
+                "rank-index",
+                types::index::IndexFields::new(vec![types::find::SortSpec::Simple(s!("rank"))]),
+                None,
+                None,
+            )
+            .await
+            .expect("can not create index");
+
+            let query = FindQuery::new(json!({"rank": {"$gte": 0}}));
+            let mut stream = Box::pin(db.find_stream_dedup::<Value>(query, 2, 0));
+            let mut ids = vec![];
+            for _ in 0..2 {
+                let doc = stream.next().await.unwrap().expect("can not find docs");
+                ids.push(doc.get_id().into_owned());
+            }
+
+            // moves doc0 to the end of the index, so it shows up in a later page again
+            let mut first: Value = db.get("doc0").await.expect("can not get doc");
+            first["rank"] = json!(10);
+            db.save(&mut first).await.expect("can not update doc");
+
+            while let Some(doc) = stream.next().await {
+                ids.push(doc.expect("can not find docs").get_id().into_owned());
+            }
+            assert_eq!(ids.iter().filter(|id| *id == "doc0").count(), 1);
+            assert_eq!(ids.len(), 6);
+
+            client.destroy_db(dbname).await.expect("can not destroy db");
+        }
+
+        #[tokio::test]
+        async fn should_export_and_import_a_database() {
+            let dbname = "should_export_and_import_a_database";
+            let import_dbname = "should_export_and_import_a_database_copy";
+            let client = Client::new_local_test().unwrap();
+            let db = client.db(dbname).await.expect("can not create db");
+
+            let mut docs = vec![json!({"_id": "1", "n": 1}), json!({"_id": "2", "n": 2})];
+            db.bulk_docs(&mut docs).await.expect("can not insert docs");
+            db.save(&mut docs[0]).await.expect("can not update doc");
+            let rev = docs[1].get_rev().into_owned();
+            db.put_attachment("2", &rev, "notes.txt", "text/plain", b"hello".to_vec())
+                .await
+                .expect("can not put attachment");
+
+            let mut export = vec![];
+            assert_eq!(db.export_to(&mut export).await.expect("can not export"), 2);
+            assert_eq!(String::from_utf8(export.clone()).unwrap().lines().count(), 2);
+
+            let imported = client
+                .import_from(import_dbname, export.as_slice())
+                .await
+                .expect("can not import");
+            assert_eq!(imported, 2);
+
+            let copy = client.db(import_dbname).await.expect("can not open copy");
+            for doc in &docs {
+                let original: Value = db.get(&doc.get_id()).await.expect("can not get doc");
+                let copied: Value = copy.get(&doc.get_id()).await.expect("can not get copied doc");
+                assert_eq!(copied, original);
+            }
+            let copied: Value = copy.get("2").await.expect("can not get copied doc");
+            assert_eq!(copied["_attachments"]["notes.txt"]["length"], 5);
+
+            client.destroy_db(dbname).await.expect("can not destroy db");
+            client.destroy_db(import_dbname).await.expect("can not destroy db");
+        }
+
+        #[tokio::test]
+        async fn should_get_a_document_if_changed() {
+            let dbname = "should_get_a_document_if_changed";
+            let (client, db, mut doc) = setup(dbname).await;
+            let id = doc.get_id().into_owned();
+
+            let unchanged: Option<Value> = db.get_if_changed(&id, &doc.get_rev()).await.expect("can not get doc");
+            assert_eq!(unchanged, None);
+
+            let old_rev = doc.get_rev().into_owned();
+            doc["thing"] = json!(false);
+            db.save(&mut doc).await.expect("can not update doc");
+            let changed: Option<Value> = db.get_if_changed(&id, &old_rev).await.expect("can not get doc");
+            assert_eq!(changed.expect("doc should have changed")["thing"], json!(false));
+
+            teardown(client, dbname).await;
+        }
+
+        #[tokio::test]
+        async fn should_get_attachment_info() {
+            let dbname = "should_get_attachment_info";
+            let (client, db, _) = setup(dbname).await;
+
+            let attachment = types::attachment::Attachment::new("text/plain", b"hello");
+            let mut doc = json!({
+                "_id": "with_attachment",
+                "_attachments": {"notes.txt": attachment}
+            });
+            db.create(&mut doc).await.expect("can not create document");
+
+            let info = db
+                .attachment_info("with_attachment", "notes.txt")
+                .await
+                .expect("can not get attachment info")
+                .expect("attachment should exist");
+            assert_eq!(info.length, Some(5));
+            assert!(info.content_type.starts_with("text/plain"));
+            assert!(info.digest.is_some());
+
+            let missing = db
+                .attachment_info("with_attachment", "missing.txt")
+                .await
+                .expect("can not get attachment info");
+            assert_eq!(missing, None);
+
+            teardown(client, dbname).await;
+        }
+
+        #[tokio::test]
+        async fn should_get_bulk_revs() {
+            let dbname = "should_get_bulk_revs";
+            let (client, db, doc) = setup(dbname).await;
+            let id = doc.get_id().into_owned();
+
+            let revs = db
+                .get_bulk_revs(vec![id.clone(), s!("missing")])
+                .await
+                .expect("can not get revs");
+            assert_eq!(revs.len(), 1);
+            assert_eq!(revs[&id], doc.get_rev());
+
+            teardown(client, dbname).await;
+        }
+
+        #[tokio::test]
+        async fn should_bulk_get_raw_documents_at_a_revision() {
+            let dbname = "should_bulk_get_raw_documents_at_a_revision";
+            let (client, db, mut doc) = setup(dbname).await;
+            let id = doc.get_id().into_owned();
+            let first_rev = doc.get_rev().into_owned();
+            doc["thing"] = json!(false);
+            db.save(&mut doc).await.unwrap();
+
+            let mut other = json!({ "thing": "other" });
+            db.create(&mut other).await.unwrap();
+            let other_id = other.get_id().into_owned();
+
+            let collection = db
+                .bulk_get_raw(vec![
+                    types::document::BulkGetItem::new(&id).rev(&first_rev),
+                    types::document::BulkGetItem::new(&other_id),
+                ])
+                .await
+                .unwrap();
+            assert_eq!(collection.rows.len(), 2);
+            assert_eq!(collection.rows[0]["_rev"], first_rev);
+            assert_eq!(collection.rows[0]["thing"], true);
+            assert_eq!(collection.rows[1]["_id"], other_id);
+
+            teardown(client, dbname).await;
+        }
+
+        #[tokio::test]
+        async fn should_detect_conflicts() {
+            let dbname = "should_detect_conflicts";
+            let (client, db, doc) = setup(dbname).await;
+            let id = doc.get_id().into_owned();
+
+            // a competing revision, as a replication from another node could introduce
+            let competing = json!({ "_id": id, "_rev": "1-00000000000000000000000000000001", "thing": "competing" });
+            db.bulk_docs_replicated(&[competing]).await.unwrap();
+
+            let conflicts = db.conflicts(vec![id.clone(), s!("does_not_exist")]).await.unwrap();
+            assert_eq!(conflicts.len(), 1);
+            assert_eq!(conflicts[&id].len(), 1);
+
+            teardown(client, dbname).await;
+        }
+
+        #[tokio::test]
+        async fn should_move_a_document() {
+            let dbname = "should_move_a_document";
+            let (client, db, doc) = setup(dbname).await;
+            let id = doc.get_id().into_owned();
+
+            let details = db.move_doc(&id, "moved").await.unwrap();
+            assert_eq!(details.id, "moved");
+
+            let moved: Value = db.get("moved").await.unwrap();
+            assert_eq!(moved["thing"], doc["thing"]);
+            assert!(db.get::<Value>(&id).await.unwrap_err().is_not_found());
+
+            teardown(client, dbname).await;
+        }
+
+        #[tokio::test]
+        async fn should_move_a_document_with_an_attachment() {
+            let dbname = "should_move_a_document_with_an_attachment";
+            let (client, db, doc) = setup(dbname).await;
+            let id = doc.get_id().into_owned();
+            db.put_attachment(&id, &doc.get_rev(), "notes.txt", "text/plain", b"hello".to_vec())
+                .await
+                .expect("can not put attachment");
+
+            let details = db.move_doc(&id, "moved").await.expect("can not move document");
+            assert_eq!(details.id, "moved");
+
+            let moved: Value = db.get("moved").await.unwrap();
+            assert_eq!(moved["thing"], doc["thing"]);
+            let info = db
+                .attachment_info("moved", "notes.txt")
+                .await
+                .expect("can not get attachment info")
+                .expect("attachment was not moved");
+            assert_eq!(info.content_type, "text/plain");
+            assert_eq!(info.length, Some(5));
+            assert!(db.get::<Value>(&id).await.unwrap_err().is_not_found());
+
+            teardown(client, dbname).await;
+        }
+
+        #[tokio::test]
+        async fn should_not_move_a_document_onto_an_existing_id() {
+            let dbname = "should_not_move_a_document_onto_an_existing_id";
+            let (client, db, doc) = setup(dbname).await;
+            let id = doc.get_id().into_owned();
+            let mut other = json!({ "thing": "other" });
+            db.create_with_id("taken", &mut other).await.unwrap();
+
+            let err = db.move_doc(&id, "taken").await.unwrap_err();
+            assert_eq!(err.status(), Some(StatusCode::CONFLICT));
+            assert!(db.get::<Value>(&id).await.is_ok());
+
+            teardown(client, dbname).await;
+        }
+
+        #[tokio::test]
+        async fn should_get_a_deleted_document() {
+            let dbname = "should_get_a_deleted_document";
+            let (client, db, doc) = setup(dbname).await;
+            let id = doc.get_id().into_owned();
+
+            let (current, deleted) = db.get_including_deleted::<Value>(&id).await.unwrap().unwrap();
+            assert_eq!(current.get_rev(), doc.get_rev());
+            assert!(!deleted);
+
+            assert!(db.remove(&doc).await);
+            let (tombstone, deleted) = db.get_including_deleted::<Value>(&id).await.unwrap().unwrap();
+            assert!(deleted);
+            assert_ne!(tombstone.get_rev(), doc.get_rev());
+
+            assert_eq!(db.get_including_deleted::<Value>("never_existed").await.unwrap(), None);
+
+            teardown(client, dbname).await;
+        }
+
+        #[tokio::test]
+        async fn should_increment_a_counter() {
+            let dbname = "should_increment_a_counter";
+            let (client, db, _doc) = setup(dbname).await;
+
+            let update = CouchUpdate::new(
+                "increment",
+                "function (doc, req) { if (!doc) { doc = { _id: req.id, value: 0 }; } doc.value += JSON.parse(req.body).by; return [doc, String(doc.value)]; }",
+            );
+            db.create_update_functions("counters", update)
+                .await
+                .expect("can not create update function");
+
+            assert_eq!(db.increment("counters", "increment", "counter", 1).await.unwrap(), 1);
+            assert_eq!(db.increment("counters", "increment", "counter", 5).await.unwrap(), 6);
+            assert_eq!(db.get_raw("counter").await.unwrap()["value"], json!(6));
+
+            teardown(client, dbname).await;
+        }
+
+        #[tokio::test]
+        async fn should_find_batched_with_a_total_limit() {
+            let dbname = "should_find_batched_with_a_total_limit";
+            let (client, db, _docs) = setup_multiple(dbname, 50).await;
+
+            let (tx, mut rx): (Sender<DocumentCollection<Value>>, Receiver<DocumentCollection<Value>>) =
+                mpsc::channel(100);
+            let results = db
+                .find_batched(FindQuery::find_all().limit(30), tx, 10, 0)
+                .await
+                .expect("can not find batched");
+            assert_eq!(results, 30);
+
+            let mut batches = 0;
+            let mut retrieved = 0;
+            while let Some(batch) = rx.recv().await {
+                batches += 1;
+                retrieved += batch.rows.len();
+            }
+            assert_eq!(batches, 3);
+            assert_eq!(retrieved, 30);
+
+            teardown(client, dbname).await;
+        }
+
+        #[tokio::test]
+        async fn should_list_design_documents() {
+            let dbname = "should_list_design_documents";
+            let (client, db, _doc) = setup(dbname).await;
+
+            for design in ["orders", "users"] {
+                let views = CouchViews::new("by_id", CouchFunc::new("function (doc) { emit(doc._id, null); }", None));
+                db.create_view(design, views).await.expect("can not create view");
+            }
+
+            assert_eq!(db.list_designs().await.unwrap(), vec![s!("orders"), s!("users")]);
+
+            teardown(client, dbname).await;
+        }
+
+        #[tokio::test]
+        async fn should_find_documents_by_prefix_using_an_index() {
+            let dbname = "should_find_documents_by_prefix_using_an_index";
+            let client = Client::new_local_test().unwrap();
+            let db = client.db(dbname).await.expect("can not create db");
+
+            let mut docs: Vec<Value> = ["car:audi", "car:bmw", "cat:felix", "bike:gazelle"]
+                .iter()
+                .map(|name| json!({ "name": name }))
+                .collect();
+            db.bulk_docs(&mut docs).await.expect("can not insert docs");
+            let spec = types::index::IndexFields::new(vec![types::find::SortSpec::Simple(s!("name"))]);
+            db.insert_index("name-index", spec, None, None)
+                .await
+                .expect("can not create index");
+
+            let query = FindQuery::new(Selector::field("name").begins_with("car:").into());
+            let explained = db.explain(&query).await.expect("can not explain query");
+            assert_eq!(explained.index.name, "name-index");
+
+            let found = db.find_raw(&query).await.expect("can not find docs");
+            assert_eq!(found.rows.len(), 2);
+
+            teardown(client, dbname).await;
+        }
+
+        #[tokio::test]
+        async fn should_reject_invalid_documents_with_a_design_document() {
+            let dbname = "should_reject_invalid_documents_with_a_design_document";
+            let (client, db, doc) = setup(dbname).await;
+
+            let design = DesignDocument::default()
+                .view("by_name", CouchFunc::new("function (doc) { emit(doc.name, null); }", None))
+                .validate_doc_update(
+                    "function (newDoc, oldDoc, userCtx) { if (!newDoc._deleted && !newDoc.name) { throw({forbidden: 'name is required'}); } }",
+                );
+            db.create_design("validation", design)
+                .await
+                .expect("can not create design document");
+            // installing it again replaces the existing design document
+            db.create_design("validation", DesignDocument::default().validate_doc_update("function (newDoc) { if (!newDoc._deleted && !newDoc.name) { throw({forbidden: 'name is required'}); } }"))
+                .await
+                .expect("can not replace design document");
+
+            let err = db.create(&mut json!({ "thing": true })).await.unwrap_err();
+            assert_eq!(err.status(), Some(StatusCode::FORBIDDEN));
+            assert!(err.is_validation_error());
+            assert!(db.create(&mut json!({ "name": "valid" })).await.is_ok());
+
+            let mut docs = vec![json!({ "name": "valid" }), json!({ "thing": true })];
+            let results = db.bulk_docs(&mut docs).await.expect("can not bulk insert docs");
+            assert!(results[0].is_ok());
+            assert!(results[1].as_ref().unwrap_err().is_validation_error());
+
+            let conflict = db
+                .create_with_id(&doc.get_id(), &mut json!({ "name": "valid" }))
+                .await
+                .unwrap_err();
+            assert!(!conflict.is_validation_error());
+
+            teardown(client, dbname).await;
+        }
+
+        #[tokio::test]
+        async fn should_query_many_with_integer_keys() {
+            let dbname = "should_query_many_with_integer_keys";
+            let client = Client::new_local_test().unwrap();
+            let db = client.db(dbname).await.expect("can not create db");
+
+            let mut docs = vec![
+                json!({"year": 2022, "amount": 1}),
+                json!({"year": 2023, "amount": 2}),
+                json!({"year": 2023, "amount": 3}),
+                json!({"year": 2024, "amount": 4}),
+            ];
+            db.bulk_docs(&mut docs).await.expect("can not insert docs");
+
+            let couch_func = CouchFunc::new("function (doc) { emit(doc.year, doc.amount); }", None);
+            db.create_view("reports", CouchViews::new("by_year", couch_func))
+                .await
+                .expect("can not create view");
+
+            let queries = QueriesParams::new(vec![
+                QueryParams::from_keys(vec![2022, 2024]),
+                QueryParams::default().start_key(2023).end_key(2023),
+            ]);
+            let collections = db
+                .query_many("reports", "by_year", queries)
+                .await
+                .expect("can not query many");
+            assert_eq!(collections.len(), 2);
+            let keys: Vec<_> = collections[0].rows.iter().map(|r| r.key.clone()).collect();
+            assert_eq!(keys, vec![json!(2022), json!(2024)]);
+            let amounts: Vec<_> = collections[1].rows.iter().map(|r| r.value.clone()).collect();
+            assert_eq!(amounts, vec![json!(2), json!(3)]);
+
+            client.destroy_db(dbname).await.expect("can not destroy db");
+        }
+
+        #[tokio::test]
+        async fn should_populate_referenced_documents() {
+            let dbname = "should_populate_referenced_documents";
+            let client = Client::new_local_test().unwrap();
+            let db = client.db(dbname).await.expect("can not create db");
+
+            let mut docs = vec![
+                json!({"_id": "john", "age": 8}),
+                json!({"_id": "jill", "age": 6}),
+                json!({"_id": "jane", "children": ["john", "jill"]}),
+            ];
+            db.bulk_docs(&mut docs).await.expect("can not insert docs");
+
+            let mut parent: Value = db.get("jane").await.expect("can not get parent");
+            db.populate::<Value>(&mut parent, "children")
+                .await
+                .expect("can not populate children");
+            let children = parent["children"].as_array().expect("children are not an array");
+            assert_eq!(children.len(), 2);
+            assert_eq!(children[0]["_id"], "john");
+            assert_eq!(children[0]["age"], 8);
+            assert_eq!(children[1]["_id"], "jill");
+            assert_eq!(children[1]["age"], 6);
+
+            client.destroy_db(dbname).await.expect("can not destroy db");
+        }
+
+        #[tokio::test]
+        async fn should_insert_indexes_into_the_same_ddoc() {
+            let dbname = "should_insert_indexes_into_the_same_ddoc";
+            let (client, db, _) = setup(dbname).await;
+
+            let spec = types::index::IndexFields::new(vec![types::find::SortSpec::Simple(s!("thing"))]);
+            let created = db
+                .insert_index("thing-index", spec, None, Some(s!("shared")))
+                .await
+                .expect("can not create index");
+            assert_eq!(created.index_status().unwrap(), IndexStatus::CreatedDesign);
+
+            let spec = types::index::IndexFields::new(vec![types::find::SortSpec::Simple(s!("other"))]);
+            let added = db
+                .insert_index("other-index", spec.clone(), None, Some(s!("_design/shared")))
+                .await
+                .expect("can not create index");
+            assert_eq!(added.index_status().unwrap(), IndexStatus::AddedToDesign);
+            assert_eq!(added.id, created.id);
+
+            let exists = db
+                .insert_index("other-index", spec, None, Some(s!("shared")))
+                .await
+                .expect("can not create index");
+            assert_eq!(exists.index_status().unwrap(), IndexStatus::Exists);
+
+            let mut names: Vec<_> = db
+                .read_indexes_for_ddoc("shared")
+                .await
+                .expect("can not read indexes")
+                .into_iter()
+                .map(|index| index.name)
+                .collect();
+            names.sort();
+            assert_eq!(names, vec![s!("other-index"), s!("thing-index")]);
+
+            teardown(client, dbname).await;
+        }
+
+        #[tokio::test]
+        async fn should_bulk_insert_with_request_compression() {
+            let dbname = "should_bulk_insert_with_request_compression";
+            let client = Client::new_local_test().unwrap().with_request_compression(1024);
+            let db = client.db(dbname).await.expect("can not create db");
+
+            let mut docs: Vec<Value> = (0..100)
+                .map(|i| json!({"_id": format!("doc-{i:03}"), "thing": true}))
+                .collect();
+            let results = db.bulk_docs(&mut docs).await.expect("can not bulk insert docs");
+            assert!(results.iter().all(Result::is_ok));
+            assert_eq!(db.doc_count().await.unwrap(), 100);
+
+            let doc: Value = db.get("doc-042").await.expect("can not get doc");
+            assert_eq!(doc["thing"], json!(true));
+
+            client.destroy_db(dbname).await.expect("can not destroy db");
+        }
+
+        #[tokio::test]
+        async fn should_find_projected_fields() {
+            #[derive(Deserialize, Debug, PartialEq)]
+            struct LastName {
+                last_name: String,
+            }
+
+            let dbname = "should_find_projected_fields";
+            let client = Client::new_local_test().unwrap();
+            let db = client.db(dbname).await.expect("can not create db");
+
+            let mut docs = vec![
+                json!({"first_name": "John", "last_name": "Doe"}),
+                json!({"first_name": "Jane", "last_name": "Roe"}),
+            ];
+            db.bulk_docs(&mut docs).await.expect("can not insert docs");
+
+            let query = FindQuery::new(json!({"first_name": "John"})).fields(vec![s!("last_name")]);
+            let names: Vec<LastName> = db.find_projected(&query).await.expect("can not find");
+            assert_eq!(names, vec![LastName { last_name: s!("Doe") }]);
+
+            client.destroy_db(dbname).await.expect("can not destroy db");
+        }
+
+        #[tokio::test]
+        async fn should_query_a_compound_key_range() {
+            let dbname = "should_query_a_compound_key_range";
+            let client = Client::new_local_test().unwrap();
+            let db = client.db(dbname).await.expect("can not create db");
+
+            let mut docs = vec![
+                json!({"date": [2023, 12, 31], "amount": 1}),
+                json!({"date": [2024, 1, 1], "amount": 2}),
+                json!({"date": [2024, 6, 15], "amount": 3}),
+                json!({"date": [2025, 1, 1], "amount": 4}),
+            ];
+            db.bulk_docs(&mut docs).await.expect("can not insert docs");
+
+            let couch_func = CouchFunc::new("function (doc) { emit(doc.date, doc.amount); }", None);
+            db.create_view("reports", CouchViews::new("by_day", couch_func))
+                .await
+                .expect("can not create view");
+
+            let options = QueryParams::default()
+                .start_key_array(&[json!(2024), min_key()])
+                .end_key_array(&[json!(2024), max_key()]);
+            let result: RawViewCollection<Value, u64> = db
+                .query("reports", "by_day", Some(options))
+                .await
+                .expect("can not query range");
+            let amounts: Vec<u64> = result.rows.iter().map(|r| r.value).collect();
+            assert_eq!(amounts, vec![2, 3]);
+
+            let options = QueryParams::default().key_prefix(&[json!(2024), json!(6)]);
+            let result: RawViewCollection<Value, u64> = db
+                .query("reports", "by_day", Some(options))
+                .await
+                .expect("can not query prefix");
+            assert_eq!(result.rows.len(), 1);
+            assert_eq!(result.rows[0].key, json!([2024, 6, 15]));
+
+            client.destroy_db(dbname).await.expect("can not destroy db");
+        }
+
+        #[tokio::test]
+        async fn should_get_all_documents_by_shard() {
+            let dbname = "should_get_all_documents_by_shard";
+            let (client, db, _) = setup_multiple(dbname, 20).await;
+            if db.get_info().await.unwrap().cluster.is_none() {
+                // a single node CouchDB without shards
+                teardown(client, dbname).await;
+                return;
+            }
+
+            let shards = db.shards().await.expect("can not get shards");
+            assert!(!shards.is_empty());
+            assert_eq!(shards[0].begin, 0);
+            assert_eq!(shards.last().unwrap().end, u32::MAX);
+
+            let mut ids = vec![];
+            for shard in &shards {
+                let (tx, mut rx): (Sender<DocumentCollection<Value>>, Receiver<DocumentCollection<Value>>) =
+                    mpsc::channel(100);
+                let count = db
+                    .get_all_batched_in_shard(shard, tx, 3, 0)
+                    .await
+                    .expect("can not read shard");
+                let mut shard_ids = vec![];
+                while let Some(docs) = rx.recv().await {
+                    shard_ids.extend(docs.rows.iter().map(|doc| doc.get_id().into_owned()));
+                }
+                assert_eq!(count, shard_ids.len() as u64);
+                ids.extend(shard_ids);
+            }
+
+            // every document is read exactly once
+            let total = ids.len();
+            ids.sort();
+            ids.dedup();
+            assert_eq!(ids.len(), total);
+            assert_eq!(total, 20);
+
+            teardown(client, dbname).await;
+        }
+
+        #[tokio::test]
+        async fn should_bootstrap_idempotently() {
+            let dbname = "should_bootstrap_idempotently";
+            let client = Client::new_local_test().unwrap();
+            let indexes = [types::index::Index {
+                ddoc: Some(s!("bootstrap")),
+                name: s!("thing-index"),
+                index_type: None,
+                def: types::index::IndexFields::new(vec![types::find::SortSpec::Simple(s!("thing"))]).into(),
+            }];
+            let designs = [(
+                s!("reports"),
+                CouchViews::new(
+                    "by_thing",
+                    CouchFunc::new("function (doc) { emit(doc.thing, null); }", None),
+                ),
+            )];
+
+            let db = client
+                .bootstrap(dbname, &indexes, &designs)
+                .await
+                .expect("can not bootstrap");
+            let design_revs = db
+                .get_bulk_revs(vec![s!("_design/reports")])
+                .await
+                .expect("can not get design rev");
+            let indexes_before = db.read_indexes().await.expect("can not read indexes").indexes.len();
+
+            let db = client
+                .bootstrap(dbname, &indexes, &designs)
+                .await
+                .expect("can not bootstrap again");
+            let unchanged = db
+                .get_bulk_revs(vec![s!("_design/reports")])
+                .await
+                .expect("can not get design rev");
+            assert_eq!(unchanged, design_revs);
+            assert_eq!(
+                db.read_indexes().await.expect("can not read indexes").indexes.len(),
+                indexes_before
+            );
+            assert_eq!(
+                db.list_designs().await.expect("can not list designs"),
+                vec![s!("bootstrap"), s!("reports")]
+            );
+
+            teardown(client, dbname).await;
+        }
+
+        #[tokio::test]
+        async fn should_get_documents_as_a_map() {
+            let dbname = "should_get_documents_as_a_map";
+            let client = Client::new_local_test().unwrap();
+            let db = client.db(dbname).await.expect("can not create db");
+
+            let mut docs = vec![json!({"_id": "john", "age": 8}), json!({"_id": "jill", "age": 6})];
+            db.bulk_docs(&mut docs).await.expect("can not insert docs");
+
+            let docs: HashMap<DocumentId, Value> = db
+                .get_map(vec![s!("john"), s!("jack"), s!("jill")])
+                .await
+                .expect("can not get docs");
+            assert_eq!(docs.len(), 2);
+            assert!(!docs.contains_key("jack"));
+            assert_eq!(docs["john"]["age"], json!(8));
+            assert_eq!(docs["jill"]["age"], json!(6));
+
+            client.destroy_db(dbname).await.expect("can not destroy db");
+        }
+
+        #[tokio::test]
+        async fn should_query_a_reduce_view_without_reducing() {
+            let dbname = "should_query_a_reduce_view_without_reducing";
+            let client = Client::new_local_test().unwrap();
+            let db = client.db(dbname).await.expect("can not create db");
+
+            let mut docs = vec![
+                json!({"_id": "a", "year": 2023, "amount": 1}),
+                json!({"_id": "b", "year": 2024, "amount": 2}),
+                json!({"_id": "c", "year": 2024, "amount": 3}),
+            ];
+            db.bulk_docs(&mut docs).await.expect("can not insert docs");
+
+            let couch_func =
+                CouchFunc::with_builtin_reduce("function (doc) { emit(doc.year, doc.amount); }", Reduce::Sum);
+            db.create_view("reports", CouchViews::new("total_by_year", couch_func))
+                .await
+                .expect("can not create view");
+
+            // by default, the view is reduced, which does not allow include_docs
+            let err = db
+                .query::<u32, u64, Value>(
+                    "reports",
+                    "total_by_year",
+                    Some(QueryParams::default().include_docs(true)),
+                )
+                .await
+                .unwrap_err();
+            assert_eq!(err.status(), Some(StatusCode::BAD_REQUEST));
+
+            let result: ViewCollection<u32, u64, Value> = db
+                .query_no_reduce_with_docs("reports", "total_by_year", Some(QueryParams::default().key(2024)))
+                .await
+                .expect("can not query without reduce");
+            let ids: Vec<_> = result
+                .rows
+                .iter()
+                .map(|r| r.doc.as_ref().unwrap().get_id().into_owned())
+                .collect();
+            assert_eq!(ids, vec![s!("b"), s!("c")]);
+            let amounts: Vec<u64> = result.rows.iter().map(|r| r.value).collect();
+            assert_eq!(amounts, vec![2, 3]);
+
+            client.destroy_db(dbname).await.expect("can not destroy db");
+        }
+
+        #[tokio::test]
+        async fn should_query_reduce_value() {
+            let dbname = "should_query_reduce_value";
+            let (client, db, docs) = setup_multiple(dbname, 5).await;
+
+            let count_things = CouchFunc::with_builtin_reduce("function (doc) { emit(doc._id, null); }", Reduce::Count);
+            db.create_view("stats", CouchViews::new("count_things", count_things))
+                .await
+                .expect("can not create view");
+
+            let count: Option<u64> = db
+                .query_reduce_value("stats", "count_things")
+                .await
+                .expect("can not query reduce value");
+            assert_eq!(count, Some(docs.len() as u64));
+
+            teardown(client, dbname).await;
+        }
+
+        #[tokio::test]
+        async fn should_query_stale() {
+            let dbname = "should_query_stale";
+            let (client, db, docs) = setup_multiple(dbname, 3).await;
+
+            let func = CouchFunc::new("function (doc) { emit(doc._id, null); }", None);
+            db.create_view("stale", CouchViews::new("ids", func))
+                .await
+                .expect("can not create view");
+
+            // bring the index up to date, so the stale read sees all documents
+            let _: RawViewCollection<String, Value> = db.query("stale", "ids", None).await.expect("can not query");
+            let result: RawViewCollection<String, Value> =
+                db.query_stale("stale", "ids", None).await.expect("can not query stale");
+            assert_eq!(result.rows.len(), docs.len());
+
+            teardown(client, dbname).await;
+        }
+
+        #[tokio::test]
+        async fn should_dump_all_leaf_revisions() {
+            let dbname = "should_dump_all_leaf_revisions";
+            let (client, db, doc) = setup(dbname).await;
+            let id = doc.get_id().into_owned();
+
+            // a competing revision, as a replication from another node could introduce
+            let competing = json!({ "_id": id, "_rev": "1-00000000000000000000000000000001", "thing": "competing" });
+            db.bulk_docs_replicated(&[competing]).await.unwrap();
+
+            let mut deleted = json!({ "thing": "deleted" });
+            db.create(&mut deleted).await.unwrap();
+            assert!(db.remove(&deleted).await);
+
+            let dump: Vec<Value> = db.dump().map(|doc| doc.unwrap()).collect().await;
+            let mut revs: Vec<String> = dump
+                .iter()
+                .filter(|leaf| leaf["_id"] == id)
+                .map(|leaf| leaf.get_rev().into_owned())
+                .collect();
+            revs.sort();
+            let mut expected = vec![doc.get_rev().into_owned(), s!("1-00000000000000000000000000000001")];
+            expected.sort();
+            assert_eq!(revs, expected);
+            assert!(dump.iter().all(|leaf| leaf.get("_revisions").is_some()));
+
+            let tombstone = dump.iter().find(|leaf| leaf["_id"] == deleted["_id"]).unwrap();
+            assert_eq!(tombstone["_deleted"], true);
+
+            teardown(client, dbname).await;
+        }
+
+        #[tokio::test]
+        async fn should_patch_under_concurrent_modification() {
+            let dbname = "should_patch_under_concurrent_modification";
+            let (client, db, doc) = setup(dbname).await;
+            let id = doc.get_id().into_owned();
+
+            // every patch conflicts with the others, and has to be retried on the new revision
+            let patches = (0..5).map(|_| {
+                db.patch(&id, |doc| {
+                    let count = doc["count"].as_u64().unwrap_or(0);
+                    doc["count"] = json!(count + 1);
+                })
+            });
+            for result in futures_util::future::join_all(patches).await {
+                result.expect("can not patch");
+            }
+
+            let patched: Value = db.get(&id).await.unwrap();
+            assert_eq!(patched["count"], 5);
+            assert_eq!(patched["thing"], doc["thing"]);
+            assert!(patched.get_rev().starts_with("6-"));
+
+            teardown(client, dbname).await;
+        }
+
+        #[tokio::test]
+        async fn should_get_exists_meta() {
+            let dbname = "should_get_exists_meta";
+            let (client, db, doc) = setup(dbname).await;
+
+            let meta = db
+                .exists_meta(&doc.get_id())
+                .await
+                .expect("can not get meta")
+                .expect("document does not exist");
+            assert_eq!(meta.rev, doc.get_rev());
+            assert!(meta.size.unwrap() > 0);
+
+            assert!(db.remove(&doc).await);
+            assert_eq!(db.exists_meta(&doc.get_id()).await.unwrap(), None);
+
+            teardown(client, dbname).await;
+        }
+    }
+
+    mod typed_tests {
+        use crate::client::Client;
+        use crate::couch_rs_tests::{LastNameOnly, TestDoc};
+        use crate::document::TypedCouchDocument;
+        use crate::typed::Database;
+        use crate::types::find::FindQuery;
+        use crate::types::query::QueryParams;
+        use crate::types::view::{CouchFunc, CouchViews, ViewCollection};
+        use serde_json::{json, Value};
+
+        #[tokio::test]
+        async fn should_query_a_view_returning_a_different_document_type() {
+            let dbname = "should_query_a_view_returning_a_different_document_type";
+            let client = Client::new_local_test().unwrap();
+            let db: Database<TestDoc> = client.db(dbname).await.expect("can not create db").into();
+
+            let mut doc = TestDoc {
+                _id: "jdoe".to_string(),
+                _rev: String::new(),
+                first_name: "John".to_string(),
+                last_name: "Doe".to_string(),
+            };
+            db.create(&mut doc).await.expect("can not create doc");
+
+            let view_name = "by_last_name";
+            db.create_view(
+                view_name,
+                CouchViews::new(
+                    view_name,
+                    CouchFunc::new("function (doc) { emit(doc.last_name, null); }", None),
+                ),
+            )
+            .await
+            .expect("can not create view");
+
+            let result: ViewCollection<String, Value, LastNameOnly> = db
+                .query_as(view_name, view_name, Some(QueryParams::default().include_docs(true)))
+                .await
+                .expect("can not query view");
+            assert_eq!(result.rows.len(), 1);
+            let included = result.rows[0].doc.as_ref().expect("doc should be included");
+            assert_eq!(included.get_id(), "jdoe");
+            assert_eq!(included.last_name, "Doe");
+
+            client.destroy_db(dbname).await.expect("can not destroy db");
+        }
+
+        #[tokio::test]
+        async fn should_scope_a_collection_to_its_type() {
+            let dbname = "should_scope_a_collection_to_its_type";
+            let client = Client::new_local_test().unwrap();
+            let db: Database<Value> = client.db(dbname).await.expect("can not create db").into();
+            let cars = db.collection("type", "car");
+            let boats = db.collection("type", "boat");
+
+            cars.create(&mut json!({"brand": "Volvo"}))
+                .await
+                .expect("can not create car");
+            cars.create(&mut json!({"brand": "Saab"}))
+                .await
+                .expect("can not create car");
+            let mut boat = json!({"brand": "Volvo"});
+            boats.create(&mut boat).await.expect("can not create boat");
+            assert_eq!(
+                db.get(&boat.get_id()).await.expect("can not get boat")["type"],
+                json!("boat")
+            );
+
+            let all_cars = cars.get_all().await.expect("can not get cars");
+            assert_eq!(all_cars.len(), 2);
+            assert!(all_cars.iter().all(|car| car["type"] == json!("car")));
+
+            let volvos = cars
+                .find(&FindQuery::new(json!({"brand": "Volvo"})))
+                .await
+                .expect("can not find cars");
+            assert_eq!(volvos.rows.len(), 1);
+            assert_eq!(volvos.rows[0]["type"], json!("car"));
+
+            client.destroy_db(dbname).await.expect("can not destroy db");
+        }
     }
 }
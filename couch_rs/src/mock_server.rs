@@ -0,0 +1,94 @@
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    task::JoinHandle,
+};
+
+type Headers = &'static [(&'static str, &'static str)];
+
+/// Starts a server that answers a single request with the given status and json body. Returns the uri
+/// to connect to, and a handle resolving to the raw request the server received (headers and body).
+pub(crate) async fn mock_server(status: u16, response_body: &'static str) -> (String, JoinHandle<String>) {
+    mock_server_with_headers(status, &[], response_body).await
+}
+
+/// Like `mock_server`, with additional headers on the response.
+pub(crate) async fn mock_server_with_headers(
+    status: u16,
+    response_headers: Headers,
+    response_body: &'static str,
+) -> (String, JoinHandle<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let uri = format!("http://{}", listener.local_addr().unwrap());
+
+    let handle = tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let request = read_request(&mut socket).await;
+        write_response(&mut socket, status, &format_headers(response_headers), response_body).await;
+        request
+    });
+
+    (uri, handle)
+}
+
+/// Starts a server that answers one request per given status, headers and json body, in order. Returns the
+/// uri to connect to, and a handle resolving to the raw requests the server received.
+pub(crate) async fn mock_server_sequence(
+    responses: &'static [(u16, Headers, &'static str)],
+) -> (String, JoinHandle<Vec<String>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let uri = format!("http://{}", listener.local_addr().unwrap());
+
+    let handle = tokio::spawn(async move {
+        let mut requests = vec![];
+        for (status, response_headers, response_body) in responses {
+            // the connection is closed after every response, so the client connects again for the next one
+            let (mut socket, _) = listener.accept().await.unwrap();
+            requests.push(read_request(&mut socket).await);
+            let mut extra_headers = format_headers(response_headers);
+            extra_headers.push_str("Connection: close\r\n");
+            write_response(&mut socket, *status, &extra_headers, response_body).await;
+        }
+        requests
+    });
+
+    (uri, handle)
+}
+
+async fn read_request(socket: &mut TcpStream) -> String {
+    let mut request = vec![];
+    let mut buf = [0; 1024];
+    let header_end = loop {
+        if let Some(pos) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+        let n = socket.read(&mut buf).await.unwrap();
+        request.extend_from_slice(&buf[..n]);
+    };
+
+    let headers = String::from_utf8_lossy(&request[..header_end]).to_lowercase();
+    let content_length = headers
+        .lines()
+        .find_map(|line| line.strip_prefix("content-length: "))
+        .map_or(0, |len| len.trim().parse::<usize>().unwrap());
+    while request.len() < header_end + content_length {
+        let n = socket.read(&mut buf).await.unwrap();
+        request.extend_from_slice(&buf[..n]);
+    }
+    String::from_utf8_lossy(&request).to_string()
+}
+
+fn format_headers(headers: &[(&str, &str)]) -> String {
+    headers
+        .iter()
+        .map(|(name, value)| format!("{name}: {value}\r\n"))
+        .collect()
+}
+
+async fn write_response(socket: &mut TcpStream, status: u16, extra_headers: &str, response_body: &str) {
+    let response = format!(
+        "HTTP/1.1 {status} MOCK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n{extra_headers}\r\n{response_body}",
+        response_body.len(),
+    );
+    socket.write_all(response.as_bytes()).await.unwrap();
+}
@@ -0,0 +1,76 @@
+use crate::{database::Database, error::CouchResult, Client};
+use std::ops::Deref;
+
+/// A database that is destroyed when it goes out of scope; primarily intended for tests.
+///
+/// Dereferences to the underlying [`Database`], so all database operations can be called on it directly.
+///
+/// Rust has no async `Drop`, so dropping a `TempDatabase` only *schedules* its destruction: a cleanup task
+/// is spawned on the current tokio runtime, on a best-effort basis. That task may not complete when the
+/// runtime shuts down right after the drop (e.g. at the end of a `#[tokio::test]`), and nothing is spawned
+/// when the drop happens outside a runtime. Call [`TempDatabase::close`] to destroy the database
+/// deterministically, and to observe the result.
+///
+/// Usage:
+/// ```
+/// use couch_rs::error::CouchResult;
+/// use serde_json::json;
+///
+/// #[tokio::main]
+/// async fn main() -> CouchResult<()> {
+///     let client = couch_rs::Client::new_local_test()?;
+///     let db = client.temp_db("temp_db").await?;
+///     let mut doc = json!({"first_name": "John"});
+///     db.create(&mut doc).await?;
+///     db.close().await?;
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct TempDatabase {
+    client: Client,
+    dbname: String,
+    db: Database,
+    closed: bool,
+}
+
+impl TempDatabase {
+    pub(crate) fn new(client: Client, dbname: &str, db: Database) -> TempDatabase {
+        TempDatabase {
+            client,
+            dbname: s!(dbname),
+            db,
+            closed: false,
+        }
+    }
+
+    /// Destroys the database and waits for the result.
+    pub async fn close(mut self) -> CouchResult<bool> {
+        self.closed = true;
+        self.client.destroy_db(&self.dbname).await
+    }
+}
+
+impl Deref for TempDatabase {
+    type Target = Database;
+
+    fn deref(&self) -> &Database {
+        &self.db
+    }
+}
+
+impl Drop for TempDatabase {
+    fn drop(&mut self) {
+        if self.closed {
+            return;
+        }
+
+        if let Ok(handle) = tokio::runtime::Handle::try_current() {
+            let client = self.client.clone();
+            let dbname = std::mem::take(&mut self.dbname);
+            handle.spawn(async move {
+                let _ = client.destroy_db(&dbname).await;
+            });
+        }
+    }
+}
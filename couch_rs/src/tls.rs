@@ -0,0 +1,85 @@
+pub use reqwest::{Certificate, Identity};
+
+/// TLS settings for a client, e.g. to connect to a `CouchDB` server behind a private CA, or one that
+/// requires mutual TLS. Pass it to `Client::new_with_tls`.
+///
+/// Usage:
+/// ```
+/// use couch_rs::error::CouchResult;
+/// use couch_rs::tls::{Certificate, TlsConfig};
+///
+/// fn main() -> CouchResult<()> {
+///     let ca = std::fs::read("ca.pem").expect("can not read ca.pem");
+///     let tls = TlsConfig::default().add_root_certificate(Certificate::from_pem(&ca)?);
+///     let client = couch_rs::Client::new_with_tls("https://couchdb.internal:6984", Some("admin"), Some("password"), tls)?;
+///     Ok(())
+/// }
+/// ```
+#[derive(Default, Clone)]
+pub struct TlsConfig {
+    pub(crate) root_certificates: Vec<Certificate>,
+    pub(crate) identity: Option<Identity>,
+}
+
+impl TlsConfig {
+    /// Trusts an additional root certificate, next to the ones of the system (or the bundled roots,
+    /// depending on the tls feature).
+    #[must_use]
+    pub fn add_root_certificate(mut self, certificate: Certificate) -> Self {
+        self.root_certificates.push(certificate);
+        self
+    }
+
+    /// Sets the client certificate and key to present to the server, for mutual TLS.
+    #[must_use]
+    pub fn identity(mut self, identity: Identity) -> Self {
+        self.identity = Some(identity);
+        self
+    }
+
+    pub(crate) fn apply(self, mut builder: reqwest::ClientBuilder) -> reqwest::ClientBuilder {
+        for certificate in self.root_certificates {
+            builder = builder.add_root_certificate(certificate);
+        }
+        if let Some(identity) = self.identity {
+            builder = builder.identity(identity);
+        }
+        builder
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Client;
+
+    const TEST_CA: &str = "-----BEGIN CERTIFICATE-----
+MIIBjjCCATOgAwIBAgIUJsS0h5HgPWl+5sKVoN3vbh2N8rIwCgYIKoZIzj0EAwIw
+GzEZMBcGA1UEAwwQY291Y2gtcnMgdGVzdCBDQTAgFw0yNjEwMTYxOTI0MTlaGA8y
+MTI2MDkyMjE5MjQxOVowGzEZMBcGA1UEAwwQY291Y2gtcnMgdGVzdCBDQTBZMBMG
+ByqGSM49AgEGCCqGSM49AwEHA0IABMZ6ndEeUzdy6i1ZjukIF+MrV9CVPU3DuAIK
+9lz0jmmZnfXEtenj5DSSxozPJLltOEi+SZDmxBq+9UdVa8ugeSKjUzBRMB0GA1Ud
+DgQWBBS+E3cGuZ+CEZ8v2M5hvTEK6nES2TAfBgNVHSMEGDAWgBS+E3cGuZ+CEZ8v
+2M5hvTEK6nES2TAPBgNVHRMBAf8EBTADAQH/MAoGCCqGSM49BAMCA0kAMEYCIQC3
+/YLOWIlxxa4aqQ/qR3ICEGTht8FK5Hum0LEmBaraPgIhAPHtKrR/8o6IETLoavXv
+smOT2iU5bug+Uv3UhvOkVviz
+-----END CERTIFICATE-----
+";
+
+    #[test]
+    fn test_tls_config_with_root_certificate() {
+        let certificate = Certificate::from_pem(TEST_CA.as_bytes()).expect("can not load certificate");
+        let tls = TlsConfig::default().add_root_certificate(certificate);
+        assert_eq!(tls.root_certificates.len(), 1);
+        assert!(tls.identity.is_none());
+
+        let client = Client::new_with_tls("https://localhost:6984", Some("admin"), Some("password"), tls)
+            .expect("can not create client");
+        assert!(format!("{client:?}").contains("https://localhost:6984/"));
+    }
+
+    #[test]
+    fn test_tls_config_rejects_invalid_pem() {
+        assert!(Certificate::from_pem(b"not a certificate").is_err());
+    }
+}
@@ -0,0 +1,961 @@
+use crate::{
+    changes::ChangesStream,
+    database::Database as RawDatabase,
+    document::{DocumentCollection, TypedCouchDocument},
+    error::{CouchError, CouchResult},
+    types::{
+        attachment::{AttachmentInfo, Attachments},
+        design::{DesignCreated, DesignDocument},
+        document::{BulkGetItem, DocMeta, DocumentCreatedResult, DocumentId, LeafRevision},
+        find::{ExplainResult, FindQuery, FindQueryRef},
+        index::{DatabaseIndexList, Index, IndexDef, IndexType},
+        query::{QueriesParams, QueryParams},
+        selector::Selector,
+        system::{DbInfo, PurgedInfos, ShardRange},
+        view::{CouchUpdate, CouchViews, RawViewCollection, ViewCollection},
+    },
+};
+use futures_core::Stream;
+use reqwest::StatusCode;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::{collections::HashMap, fmt, io::Write, marker::PhantomData};
+use tokio::sync::mpsc::Sender;
+
+/// Typed wrapper around a [`crate::database::Database`], binding all document operations to a
+/// single document type `T`. This avoids having to annotate the document type on every call.
+///
+/// Every public method of the raw `Database` has a delegate here, with the following exceptions:
+/// - the `*_raw` variants (`get_raw`, `get_bulk_raw`, `bulk_get_raw`, `get_all_raw`, `get_all_params_raw` and
+///   `find_raw`) are omitted, since they only exist to pin the document type to `Value`;
+/// - `ensure_index` is omitted, because it is deprecated in favour of `insert_index`.
+///
+/// Use [`Database::raw`] to reach any of the raw `Database` methods directly.
+///
+/// Usage:
+/// ```
+/// use couch_rs::document::TypedCouchDocument;
+/// use couch_rs::error::CouchResult;
+/// use couch_rs::types::document::DocumentId;
+/// use couch_rs::CouchDocument;
+/// use serde::{Deserialize, Serialize};
+///
+/// const TEST_DB: &str = "test_db";
+///
+/// #[derive(Serialize, Deserialize, CouchDocument)]
+/// pub struct UserDetails {
+///     #[serde(skip_serializing_if = "String::is_empty")]
+///     pub _id: DocumentId,
+///     #[serde(skip_serializing_if = "String::is_empty")]
+///     pub _rev: String,
+///     pub last_name: String,
+/// }
+///
+/// #[tokio::main]
+/// async fn main() -> CouchResult<()> {
+///     let client = couch_rs::Client::new_local_test()?;
+///     let db: couch_rs::typed::Database<UserDetails> = client.db(TEST_DB).await?.into();
+///
+///     let mut doc = UserDetails {
+///         _id: "jdoe".to_string(),
+///         _rev: "".to_string(),
+///         last_name: "Doe".to_string(),
+///     };
+///     db.upsert(&mut doc).await?;
+///
+///     // no need to annotate the document type
+///     let user = db.get("jdoe").await?;
+///     assert_eq!(user.last_name, "Doe");
+///     Ok(())
+/// }
+/// ```
+pub struct Database<T: TypedCouchDocument> {
+    db: RawDatabase,
+    _phantom: PhantomData<T>,
+}
+
+impl<T: TypedCouchDocument> Clone for Database<T> {
+    fn clone(&self) -> Self {
+        Database {
+            db: self.db.clone(),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<T: TypedCouchDocument> fmt::Debug for Database<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Database").field("db", &self.db).finish()
+    }
+}
+
+impl<T: TypedCouchDocument> From<RawDatabase> for Database<T> {
+    fn from(db: RawDatabase) -> Self {
+        Database::new(db)
+    }
+}
+
+impl<T: TypedCouchDocument> Database<T> {
+    #[must_use]
+    pub fn new(db: RawDatabase) -> Self {
+        Database {
+            db,
+            _phantom: PhantomData,
+        }
+    }
+
+    // convenience function to retrieve the name of the database
+    #[must_use]
+    pub fn name(&self) -> &str {
+        self.db.name()
+    }
+
+    /// Returns the underlying raw `Database`. Use this as an escape hatch to reach methods that
+    /// have no typed delegate.
+    ///
+    /// Usage:
+    /// ```
+    /// use couch_rs::error::CouchResult;
+    /// use serde_json::Value;
+    ///
+    /// const TEST_DB: &str = "test_db";
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> CouchResult<()> {
+    ///     let client = couch_rs::Client::new_local_test()?;
+    ///     let typed_db: couch_rs::typed::Database<Value> = client.db(TEST_DB).await?.into();
+    ///
+    ///     // the raw variants are not delegated, but can be reached through `raw()`
+    ///     let all_docs = typed_db.raw().get_all_raw().await?;
+    ///     println!("{} documents", all_docs.total_rows);
+    ///     Ok(())
+    /// }
+    /// ```
+    #[must_use]
+    pub fn raw(&self) -> &RawDatabase {
+        &self.db
+    }
+
+    /// Returns a collection scoped to the documents whose discriminator `field` equals `value`, for
+    /// databases that store multiple document types.
+    /// See [`TypedCollection`] for details.
+    #[must_use]
+    pub fn collection(&self, field: &str, value: impl Into<Value>) -> TypedCollection<T> {
+        TypedCollection::new(self.clone(), field, value)
+    }
+
+    /// Launches the compact process
+    pub async fn compact(&self) -> bool {
+        self.db.compact().await
+    }
+
+    /// Starts the compaction of all views
+    pub async fn compact_views(&self) -> bool {
+        self.db.compact_views().await
+    }
+
+    /// Starts the compaction of a given index
+    pub async fn compact_index(&self, index: &str) -> bool {
+        self.db.compact_index(index).await
+    }
+
+    /// Gets information about this database.
+    pub async fn get_info(&self) -> CouchResult<DbInfo> {
+        self.db.get_info().await
+    }
+
+    /// Returns the number of documents in this database, excluding deleted documents.
+    pub async fn doc_count(&self) -> CouchResult<u64> {
+        self.db.doc_count().await
+    }
+
+    /// Returns the number of deleted documents in this database.
+    pub async fn deleted_count(&self) -> CouchResult<u64> {
+        self.db.deleted_count().await
+    }
+
+    /// Returns the purge sequence of this database.
+    pub async fn purge_seq(&self) -> CouchResult<String> {
+        self.db.purge_seq().await
+    }
+
+    /// Returns the documents and revisions that were purged from this database.
+    /// See [`crate::database::Database::purged_infos`] for details.
+    pub async fn purged_infos(&self) -> CouchResult<PurgedInfos> {
+        self.db.purged_infos().await
+    }
+
+    /// Returns the maximum number of purges that are tracked for this database.
+    pub async fn purged_infos_limit(&self) -> CouchResult<u64> {
+        self.db.purged_infos_limit().await
+    }
+
+    /// Sets the maximum number of purges that are tracked for this database.
+    pub async fn set_purged_infos_limit(&self, limit: u64) -> CouchResult<bool> {
+        self.db.set_purged_infos_limit(limit).await
+    }
+
+    /// Flushes pending writes of this database to disk, and returns the `instance_start_time`.
+    /// See [`crate::database::Database::ensure_full_commit`] for details.
+    pub async fn ensure_full_commit(&self) -> CouchResult<String> {
+        self.db.ensure_full_commit().await
+    }
+
+    /// Checks if a document ID exists
+    pub async fn exists(&self, id: &str) -> bool {
+        self.db.exists(id).await
+    }
+
+    /// Returns the current revision and size of a document, or `None` when it does not exist.
+    /// See [`crate::database::Database::exists_meta`] for details.
+    pub async fn exists_meta(&self, id: &str) -> CouchResult<Option<DocMeta>> {
+        self.db.exists_meta(id).await
+    }
+
+    /// Gets one document
+    pub async fn get(&self, id: &str) -> CouchResult<T> {
+        self.db.get(id).await
+    }
+
+    /// Gets the metadata of an attachment, without downloading it.
+    /// See [`crate::database::Database::attachment_info`] for details.
+    pub async fn attachment_info(&self, doc_id: &str, name: &str) -> CouchResult<Option<AttachmentInfo>> {
+        self.db.attachment_info(doc_id, name).await
+    }
+
+    /// Adds an attachment to a document, or replaces it, and returns the new revision of the document.
+    /// See [`crate::database::Database::put_attachment`] for details.
+    pub async fn put_attachment(
+        &self,
+        doc_id: &str,
+        rev: &str,
+        name: &str,
+        content_type: &str,
+        data: Vec<u8>,
+    ) -> DocumentCreatedResult {
+        self.db.put_attachment(doc_id, rev, name, content_type, data).await
+    }
+
+    /// Gets one document, unless its current revision is `known_rev`.
+    /// See [`crate::database::Database::get_if_changed`] for details.
+    pub async fn get_if_changed(&self, id: &str, known_rev: &str) -> CouchResult<Option<T>> {
+        self.db.get_if_changed(id, known_rev).await
+    }
+
+    /// Gets one document, together with its `_local_seq`.
+    /// See [`crate::database::Database::get_with_local_seq`] for details.
+    pub async fn get_with_local_seq(&self, id: &str) -> CouchResult<(T, u64)> {
+        self.db.get_with_local_seq(id).await
+    }
+
+    /// Gets every leaf revision of a document, including conflicts and deleted tombstones.
+    /// See [`crate::database::Database::get_all_leaf_revs`] for details.
+    pub async fn get_all_leaf_revs(&self, id: &str) -> CouchResult<Vec<LeafRevision<T>>> {
+        self.db.get_all_leaf_revs(id).await
+    }
+
+    /// Gets the winning revision of a document, even when it has been deleted, and whether it is deleted.
+    /// See [`crate::database::Database::get_including_deleted`] for details.
+    pub async fn get_including_deleted(&self, id: &str) -> CouchResult<Option<(T, bool)>> {
+        self.db.get_including_deleted(id).await
+    }
+
+    /// Gets one document, including the data of its attachments.
+    /// See [`crate::database::Database::get_with_attachments`] for details.
+    pub async fn get_with_attachments(&self, id: &str) -> CouchResult<T> {
+        self.db.get_with_attachments(id).await
+    }
+
+    /// Gets the metadata of a document's attachments.
+    pub async fn get_attachment_stubs(&self, id: &str) -> CouchResult<Attachments> {
+        self.db.get_attachment_stubs(id).await
+    }
+
+    /// Gets one document as the untouched response body.
+    /// See [`crate::database::Database::get_bytes`] for details.
+    pub async fn get_bytes(&self, id: &str) -> CouchResult<Vec<u8>> {
+        self.db.get_bytes(id).await
+    }
+
+    /// Gets documents in bulk with provided IDs list
+    pub async fn get_bulk(&self, ids: Vec<DocumentId>) -> CouchResult<DocumentCollection<T>> {
+        self.db.get_bulk(ids).await
+    }
+
+    /// Gets documents in bulk with provided IDs list, as a map keyed by id.
+    /// See [`crate::database::Database::get_map`] for details.
+    pub async fn get_map(&self, ids: Vec<DocumentId>) -> CouchResult<HashMap<DocumentId, T>> {
+        self.db.get_map(ids).await
+    }
+
+    /// Replaces the ids in the array `field` of `doc` with the documents they refer to.
+    /// See [`crate::database::Database::populate`] for details.
+    pub async fn populate(&self, doc: &mut Value, field: &str) -> CouchResult<()> {
+        self.db.populate::<T>(doc, field).await
+    }
+
+    /// Gets documents in bulk through `_bulk_get`, optionally at specific revisions.
+    /// See [`crate::database::Database::bulk_get`] for details.
+    pub async fn bulk_get(&self, docs: Vec<BulkGetItem>) -> CouchResult<DocumentCollection<T>> {
+        self.db.bulk_get(docs).await
+    }
+
+    /// Gets the conflicting revisions of the documents with the provided IDs.
+    /// See [`crate::database::Database::conflicts`] for details.
+    pub async fn conflicts(&self, ids: Vec<DocumentId>) -> CouchResult<HashMap<DocumentId, Vec<String>>> {
+        self.db.conflicts(ids).await
+    }
+
+    /// Gets documents in bulk, splitting the IDs over multiple requests.
+    /// See [`crate::database::Database::bulk_get_chunked`] for details.
+    pub async fn bulk_get_chunked(
+        &self,
+        ids: Vec<DocumentId>,
+        chunk_size: usize,
+    ) -> CouchResult<DocumentCollection<T>> {
+        self.db.bulk_get_chunked(ids, chunk_size).await
+    }
+
+    /// Gets documents in bulk with provided IDs list, with added params.
+    /// See [`crate::database::Database::get_bulk_params`] for details.
+    pub async fn get_bulk_params(
+        &self,
+        ids: Vec<DocumentId>,
+        params: Option<QueryParams<DocumentId>>,
+    ) -> CouchResult<DocumentCollection<T>> {
+        self.db.get_bulk_params(ids, params).await
+    }
+
+    /// Creates or updates a list of documents in one request.
+    /// See [`crate::database::Database::bulk_docs`] for details.
+    pub async fn bulk_docs(&self, raw_docs: &mut [T]) -> CouchResult<Vec<DocumentCreatedResult>> {
+        self.db.bulk_docs(raw_docs).await
+    }
+
+    /// Creates or updates documents in bulk, splitting the documents over multiple requests.
+    /// See [`crate::database::Database::bulk_docs_chunked`] for details.
+    pub async fn bulk_docs_chunked(
+        &self,
+        raw_docs: &mut [T],
+        chunk_size: usize,
+    ) -> CouchResult<Vec<DocumentCreatedResult>> {
+        self.db.bulk_docs_chunked(raw_docs, chunk_size).await
+    }
+
+    /// Gets the current revision of each of the documents with the provided IDs.
+    /// See [`crate::database::Database::get_bulk_revs`] for details.
+    pub async fn get_bulk_revs(&self, ids: Vec<DocumentId>) -> CouchResult<HashMap<DocumentId, String>> {
+        self.db.get_bulk_revs(ids).await
+    }
+
+    /// Gets all the documents in database
+    pub async fn get_all(&self) -> CouchResult<DocumentCollection<T>> {
+        self.db.get_all().await
+    }
+
+    /// Gets all documents in the database, using bookmarks to iterate through all the documents.
+    /// See [`crate::database::Database::get_all_batched`] for details.
+    pub async fn get_all_batched(
+        &self,
+        tx: Sender<DocumentCollection<T>>,
+        batch_size: u64,
+        max_results: u64,
+    ) -> CouchResult<u64> {
+        self.db.get_all_batched(tx, batch_size, max_results).await
+    }
+
+    /// Gets the shard ranges of this database.
+    /// See [`crate::database::Database::shards`] for details.
+    pub async fn shards(&self) -> CouchResult<Vec<ShardRange>> {
+        self.db.shards().await
+    }
+
+    /// Gets the documents in a single shard of this database, in batches.
+    /// See [`crate::database::Database::get_all_batched_in_shard`] for details.
+    pub async fn get_all_batched_in_shard(
+        &self,
+        shard: &ShardRange,
+        tx: Sender<DocumentCollection<T>>,
+        batch_size: u64,
+        max_results: u64,
+    ) -> CouchResult<u64> {
+        self.db
+            .get_all_batched_in_shard(shard, tx, batch_size, max_results)
+            .await
+    }
+
+    /// Finds documents in the database, using bookmarks to iterate through all the documents.
+    /// See [`crate::database::Database::find_batched`] for details.
+    pub async fn find_batched(
+        &self,
+        query: FindQuery,
+        tx: Sender<DocumentCollection<T>>,
+        batch_size: u64,
+        max_results: u64,
+    ) -> CouchResult<u64> {
+        self.db.find_batched(query, tx, batch_size, max_results).await
+    }
+
+    /// Finds all documents matching the query, following the bookmarks until the results are exhausted.
+    /// See [`crate::database::Database::find_all`] for details.
+    pub async fn find_all(&self, query: FindQuery, page_size: u64) -> CouchResult<Vec<T>> {
+        self.db.find_all(query, page_size).await
+    }
+
+    /// Exports all documents of the database as NDJSON.
+    /// See [`crate::database::Database::export_to`] for details.
+    pub async fn export_to<W: Write>(&self, writer: W) -> CouchResult<u64> {
+        self.db.export_to(writer).await
+    }
+
+    /// Updates a raw document in place, retrying on conflicts.
+    /// See [`crate::database::Database::patch`] for details.
+    pub async fn patch<F: Fn(&mut Value)>(&self, id: &str, patch: F) -> DocumentCreatedResult {
+        self.db.patch(id, patch).await
+    }
+
+    /// Streams every leaf revision of every document, as raw Values, for a lossless backup.
+    /// See [`crate::database::Database::dump`] for details.
+    pub fn dump(&self) -> impl Stream<Item = CouchResult<Value>> + '_ {
+        self.db.dump()
+    }
+
+    /// Finds documents as a stream, skipping documents that were already returned.
+    /// See [`crate::database::Database::find_stream_dedup`] for details.
+    pub fn find_stream_dedup(
+        &self,
+        query: FindQuery,
+        batch_size: u64,
+        max_tracked: usize,
+    ) -> impl Stream<Item = CouchResult<T>> + '_ {
+        self.db.find_stream_dedup(query, batch_size, max_tracked)
+    }
+
+    /// Executes multiple specified built-in view queries of all documents in this database.
+    pub async fn query_many_all_docs(
+        &self,
+        queries: QueriesParams,
+    ) -> CouchResult<Vec<ViewCollection<Value, Value, Value>>> {
+        self.db.query_many_all_docs(queries).await
+    }
+
+    /// Executes multiple queries against a view.
+    pub async fn query_many<K: Serialize + PartialEq + std::fmt::Debug + Clone>(
+        &self,
+        design_name: &str,
+        view_name: &str,
+        queries: QueriesParams<K>,
+    ) -> CouchResult<Vec<ViewCollection<Value, Value, Value>>> {
+        self.db.query_many(design_name, view_name, queries).await
+    }
+
+    /// Gets all the documents in database, with applied parameters.
+    pub async fn get_all_params(&self, params: Option<QueryParams<DocumentId>>) -> CouchResult<DocumentCollection<T>> {
+        self.db.get_all_params(params).await
+    }
+
+    /// Finds a document in the database through a Mango query.
+    pub async fn find<'q>(&self, query: impl Into<FindQueryRef<'q>>) -> CouchResult<DocumentCollection<T>> {
+        self.db.find(query).await
+    }
+
+    /// Finds the single document matching the query, or `None`; more than one match is an error.
+    /// See [`crate::database::Database::find_one`] for details.
+    pub async fn find_one<'q>(&self, query: impl Into<FindQueryRef<'q>>) -> CouchResult<Option<T>> {
+        self.db.find_one(query).await
+    }
+
+    /// Finds documents through a Mango query, and deserializes only the selected `fields` into `P`.
+    /// See [`crate::database::Database::find_projected`] for details.
+    pub async fn find_projected<'q, P: DeserializeOwned>(
+        &self,
+        query: impl Into<FindQueryRef<'q>>,
+    ) -> CouchResult<Vec<P>> {
+        self.db.find_projected(query).await
+    }
+
+    /// Shows which index would be used by `CouchDB` to execute the query.
+    pub async fn explain(&self, query: &FindQuery) -> CouchResult<ExplainResult> {
+        self.db.explain(query).await
+    }
+
+    /// Fails when the query can not use an index.
+    /// See [`crate::database::Database::assert_indexed`] for details.
+    pub async fn assert_indexed(&self, query: &FindQuery) -> CouchResult<()> {
+        self.db.assert_indexed(query).await
+    }
+
+    /// Saves a document to `CouchDB`.
+    /// See [`crate::database::Database::save`] for details.
+    pub async fn save(&self, doc: &mut T) -> DocumentCreatedResult {
+        self.db.save(doc).await
+    }
+
+    /// Saves a document, returning `(old_rev, new_rev)`.
+    /// See [`crate::database::Database::save_returning_revs`] for details.
+    pub async fn save_returning_revs(&self, doc: &mut T) -> CouchResult<(String, String)> {
+        self.db.save_returning_revs(doc).await
+    }
+
+    /// Creates a document.
+    pub async fn create(&self, doc: &mut T) -> DocumentCreatedResult {
+        self.db.create(doc).await
+    }
+
+    /// Creates a document under the given id.
+    /// See [`crate::database::Database::create_with_id`] for details.
+    pub async fn create_with_id(&self, id: &str, doc: &mut T) -> DocumentCreatedResult {
+        self.db.create_with_id(id, doc).await
+    }
+
+    /// Moves a document to a new id, deleting the original.
+    /// See [`crate::database::Database::move_doc`] for details.
+    pub async fn move_doc(&self, old_id: &str, new_id: &str) -> DocumentCreatedResult {
+        self.db.move_doc(old_id, new_id).await
+    }
+
+    /// Creates the document, or updates it when it already exists.
+    /// See [`crate::database::Database::upsert`] for details.
+    pub async fn upsert(&self, doc: &mut T) -> DocumentCreatedResult {
+        self.db.upsert(doc).await
+    }
+
+    /// Bulk upsert a list of documents.
+    /// See [`crate::database::Database::bulk_upsert`] for details.
+    pub async fn bulk_upsert(&self, docs: &mut [T]) -> CouchResult<Vec<DocumentCreatedResult>>
+    where
+        T: Clone,
+    {
+        self.db.bulk_upsert(docs).await
+    }
+
+    /// Creates a design with one of more view documents.
+    pub async fn create_view<V: Into<Value>>(&self, design_name: &str, views: V) -> CouchResult<DesignCreated> {
+        self.db.create_view(design_name, views).await
+    }
+
+    /// Creates or updates the views of a design document, only writing it when the views differ.
+    /// See [`crate::database::Database::upsert_views`] for details.
+    pub async fn upsert_views(&self, design_name: &str, views: &CouchViews) -> CouchResult<bool> {
+        self.db.upsert_views(design_name, views).await
+    }
+
+    /// Installs update functions in a design document, preserving its views.
+    /// See [`crate::database::Database::create_update_functions`] for details.
+    pub async fn create_update_functions(&self, design_name: &str, updates: CouchUpdate) -> CouchResult<DesignCreated> {
+        self.db.create_update_functions(design_name, updates).await
+    }
+
+    /// Installs a complete design document, replacing an existing one with the same name.
+    /// See [`crate::database::Database::create_design`] for details.
+    pub async fn create_design(&self, design_name: &str, design: DesignDocument) -> CouchResult<DesignCreated> {
+        self.db.create_design(design_name, design).await
+    }
+
+    /// Lists the names of all design documents in the database.
+    /// See [`crate::database::Database::list_designs`] for details.
+    pub async fn list_designs(&self) -> CouchResult<Vec<DocumentId>> {
+        self.db.list_designs().await
+    }
+
+    /// Executes a query against a view, returning untyped Values
+    pub async fn query_raw(
+        &self,
+        design_name: &str,
+        view_name: &str,
+        options: Option<QueryParams<Value>>,
+    ) -> CouchResult<ViewCollection<Value, Value, Value>> {
+        self.db.query_raw(design_name, view_name, options).await
+    }
+
+    /// Executes a query against a view.
+    /// See [`crate::database::Database::query`] for details.
+    pub async fn query<K: Serialize + DeserializeOwned + PartialEq + fmt::Debug + Clone, V: DeserializeOwned>(
+        &self,
+        design_name: &str,
+        view_name: &str,
+        options: Option<QueryParams<K>>,
+    ) -> CouchResult<ViewCollection<K, V, T>> {
+        self.db.query(design_name, view_name, options).await
+    }
+
+    /// Executes a query against a view, where the documents included through `include_docs` are of
+    /// a different type `D` than the type this database is bound to. Views frequently emit documents
+    /// of a different shape than the main collection type.
+    /// See [`crate::database::Database::query`] for details.
+    pub async fn query_as<
+        K: Serialize + DeserializeOwned + PartialEq + fmt::Debug + Clone,
+        V: DeserializeOwned,
+        D: TypedCouchDocument,
+    >(
+        &self,
+        design_name: &str,
+        view_name: &str,
+        options: Option<QueryParams<K>>,
+    ) -> CouchResult<ViewCollection<K, V, D>> {
+        self.db.query(design_name, view_name, options).await
+    }
+
+    /// Executes a query against a view without waiting for the view index to be updated.
+    /// See [`crate::database::Database::query_stale`] for details.
+    pub async fn query_stale<K: Serialize + DeserializeOwned + PartialEq + fmt::Debug + Clone, V: DeserializeOwned>(
+        &self,
+        design_name: &str,
+        view_name: &str,
+        params: Option<QueryParams<K>>,
+    ) -> CouchResult<ViewCollection<K, V, T>> {
+        self.db.query_stale(design_name, view_name, params).await
+    }
+
+    /// Returns the single reduced value of a view with a reduce function.
+    /// See [`crate::database::Database::query_reduce_value`] for details.
+    pub async fn query_reduce_value<V: DeserializeOwned>(
+        &self,
+        design_name: &str,
+        view_name: &str,
+    ) -> CouchResult<Option<V>> {
+        self.db.query_reduce_value(design_name, view_name).await
+    }
+
+    /// Executes a query against a view with a reduce function, returning the map rows with their documents.
+    /// See [`crate::database::Database::query_no_reduce_with_docs`] for details.
+    pub async fn query_no_reduce_with_docs<
+        K: Serialize + DeserializeOwned + PartialEq + fmt::Debug + Clone,
+        V: DeserializeOwned,
+    >(
+        &self,
+        design_name: &str,
+        view_name: &str,
+        options: Option<QueryParams<K>>,
+    ) -> CouchResult<ViewCollection<K, V, T>> {
+        self.db.query_no_reduce_with_docs(design_name, view_name, options).await
+    }
+
+    /// Executes a query against a reduce view, grouped at `group_level`.
+    /// See [`crate::database::Database::query_grouped`] for details.
+    pub async fn query_grouped<
+        K: Serialize + DeserializeOwned + PartialEq + fmt::Debug + Clone,
+        V: DeserializeOwned,
+    >(
+        &self,
+        design_name: &str,
+        view_name: &str,
+        group_level: u32,
+        range: Option<(K, K)>,
+    ) -> CouchResult<RawViewCollection<K, V>> {
+        self.db.query_grouped(design_name, view_name, group_level, range).await
+    }
+
+    /// Executes an update function.
+    pub async fn execute_update(
+        &self,
+        design_id: &str,
+        name: &str,
+        document_id: &str,
+        body: Option<Value>,
+    ) -> CouchResult<String> {
+        self.db.execute_update(design_id, name, document_id, body).await
+    }
+
+    /// Increments a counter document through an update function, and returns its new value.
+    /// See [`crate::database::Database::increment`] for details.
+    pub async fn increment(&self, design_id: &str, update_fn: &str, counter_id: &str, by: i64) -> CouchResult<i64> {
+        self.db.increment(design_id, update_fn, counter_id, by).await
+    }
+
+    /// Removes a document from the database. Returns success in a `bool`
+    pub async fn remove(&self, doc: &T) -> bool {
+        self.db.remove(doc).await
+    }
+
+    /// Inserts an index on a database, using the `_index` endpoint.
+    /// See [`crate::database::Database::insert_index`] for details.
+    pub async fn insert_index(
+        &self,
+        name: &str,
+        def: impl Into<IndexDef>,
+        index_type: Option<IndexType>,
+        ddoc: Option<DocumentId>,
+    ) -> CouchResult<DesignCreated> {
+        self.db.insert_index(name, def, index_type, ddoc).await
+    }
+
+    /// Reads the database's indexes and returns them
+    pub async fn read_indexes(&self) -> CouchResult<DatabaseIndexList> {
+        self.db.read_indexes().await
+    }
+
+    /// Reads the indexes in the given design document.
+    /// See [`crate::database::Database::read_indexes_for_ddoc`] for details.
+    pub async fn read_indexes_for_ddoc(&self, ddoc: &str) -> CouchResult<Vec<Index>> {
+        self.db.read_indexes_for_ddoc(ddoc).await
+    }
+
+    /// Deletes a db index. Returns true if successful, false otherwise.
+    pub async fn delete_index(&self, ddoc: DocumentId, name: String) -> CouchResult<bool> {
+        self.db.delete_index(ddoc, name).await
+    }
+
+    /// Deletes an index, as returned by `read_indexes`.
+    /// See [`crate::database::Database::delete_index_spec`] for details.
+    pub async fn delete_index_spec(&self, index: &Index) -> CouchResult<bool> {
+        self.db.delete_index_spec(index).await
+    }
+
+    /// A streaming handler for the `CouchDB` `_changes` endpoint.
+    /// See [`crate::database::Database::changes`] for details.
+    #[must_use]
+    pub fn changes(&self, last_seq: Option<Value>) -> ChangesStream {
+        self.db.changes(last_seq)
+    }
+}
+
+/// A collection of documents of a single type, in a database that stores multiple document types that
+/// are distinguished by a discriminator field, e.g. `"type": "car"` and `"type": "boat"`. All operations
+/// are scoped to the documents with the discriminator value of this collection.
+///
+/// Usage:
+/// ```
+/// use couch_rs::error::CouchResult;
+/// use couch_rs::types::find::FindQuery;
+/// use serde_json::{json, Value};
+///
+/// #[tokio::main]
+/// async fn main() -> CouchResult<()> {
+///     let client = couch_rs::Client::new_local_test()?;
+///     let db: couch_rs::typed::Database<Value> = client.db("vehicles").await?.into();
+///     let cars = db.collection("type", "car");
+///
+///     // the discriminator is added when creating a document
+///     cars.create(&mut json!({"brand": "Volvo"})).await?;
+///     let volvos = cars.find(&FindQuery::new(json!({"brand": "Volvo"}))).await?;
+///     let all_cars = cars.get_all().await?;
+///     Ok(())
+/// }
+/// ```
+pub struct TypedCollection<T: TypedCouchDocument> {
+    db: Database<T>,
+    field: String,
+    value: Value,
+}
+
+impl<T: TypedCouchDocument> Clone for TypedCollection<T> {
+    fn clone(&self) -> Self {
+        TypedCollection {
+            db: self.db.clone(),
+            field: self.field.clone(),
+            value: self.value.clone(),
+        }
+    }
+}
+
+impl<T: TypedCouchDocument> fmt::Debug for TypedCollection<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TypedCollection")
+            .field("db", &self.db)
+            .field("field", &self.field)
+            .field("value", &self.value)
+            .finish()
+    }
+}
+
+impl<T: TypedCouchDocument> TypedCollection<T> {
+    #[must_use]
+    pub fn new(db: Database<T>, field: &str, value: impl Into<Value>) -> Self {
+        TypedCollection {
+            db,
+            field: field.to_string(),
+            value: value.into(),
+        }
+    }
+
+    /// The selector matching the documents of this collection
+    #[must_use]
+    pub fn selector(&self) -> Selector {
+        Selector::field(&self.field).eq(self.value.clone())
+    }
+
+    /// Finds the documents of this collection that match the query.
+    /// See [`crate::database::Database::find`] for details.
+    pub async fn find(&self, query: &FindQuery) -> CouchResult<DocumentCollection<T>> {
+        self.db.find(&self.scoped(query)).await
+    }
+
+    /// Gets all documents of this collection.
+    /// See [`crate::database::Database::find_all`] for details.
+    pub async fn get_all(&self) -> CouchResult<Vec<T>> {
+        self.db.find_all(FindQuery::new(self.selector().into()), 0).await
+    }
+
+    /// Creates a document, setting the discriminator field to the value of this collection.
+    /// See [`crate::database::Database::create`] for details.
+    pub async fn create(&self, doc: &mut T) -> DocumentCreatedResult {
+        let mut value = serde_json::to_value(&*doc)?;
+        let Some(map) = value.as_object_mut() else {
+            return Err(CouchError::new(
+                s!("invalid document type, expected something that deserializes as json object"),
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ));
+        };
+        map.insert(self.field.clone(), self.value.clone());
+
+        let details = self.db.raw().create(&mut value).await?;
+        doc.set_id(&details.id);
+        doc.set_rev(&details.rev);
+        Ok(details)
+    }
+
+    fn scoped(&self, query: &FindQuery) -> FindQuery {
+        let mut query = query.clone();
+        query.selector = self.selector().and(query.selector.into()).into();
+        query
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate as couch_rs;
+    use crate::client::Client;
+    use crate::types::index::IndexFields;
+    use crate::types::view::CouchFunc;
+    use couch_rs_derive::CouchDocument;
+    use serde::Deserialize;
+    use tokio::sync::mpsc;
+
+    #[derive(Serialize, Deserialize, CouchDocument, Debug, Default, Clone)]
+    struct TestDocument {
+        #[serde(skip_serializing_if = "String::is_empty")]
+        pub _id: String,
+        #[serde(skip_serializing_if = "String::is_empty")]
+        pub _rev: String,
+    }
+
+    // Never awaited; this only exists to make sure every delegate compiles against a concrete type.
+    async fn call_all_delegates(db: Database<TestDocument>) -> CouchResult<()> {
+        let mut doc = TestDocument::default();
+        let (tx, _rx) = mpsc::channel(1);
+        let (tx2, _rx2) = mpsc::channel(1);
+        let (tx3, _rx3) = mpsc::channel(1);
+
+        let _: &str = db.name();
+        let _: bool = db.compact().await;
+        let _: Option<AttachmentInfo> = db.attachment_info("id", "name").await?;
+        db.put_attachment("id", "rev", "name", "text/plain", vec![]).await?;
+        let _: Option<TestDocument> = db.get_if_changed("id", "1-a").await?;
+        let _: bool = db.compact_views().await;
+        let _: bool = db.compact_index("index").await;
+        let _: DbInfo = db.get_info().await?;
+        let _: u64 = db.doc_count().await?;
+        let _: u64 = db.deleted_count().await?;
+        let _: String = db.purge_seq().await?;
+        let _: HashMap<DocumentId, String> = db.get_bulk_revs(vec![]).await?;
+        let _: PurgedInfos = db.purged_infos().await?;
+        let _: u64 = db.purged_infos_limit().await?;
+        let _: bool = db.set_purged_infos_limit(1000).await?;
+        let _: String = db.ensure_full_commit().await?;
+        let _: bool = db.exists("id").await;
+        let _: TestDocument = db.get("id").await?;
+        let _: (TestDocument, u64) = db.get_with_local_seq("id").await?;
+        let _: Vec<u8> = db.get_bytes("id").await?;
+        let _: TestDocument = db.get_with_attachments("id").await?;
+        let _: Attachments = db.get_attachment_stubs("id").await?;
+        let _: Vec<LeafRevision<TestDocument>> = db.get_all_leaf_revs("id").await?;
+        let _: Option<(TestDocument, bool)> = db.get_including_deleted("id").await?;
+        let _: DocumentCollection<TestDocument> = db.get_bulk(vec![]).await?;
+        let _: HashMap<DocumentId, TestDocument> = db.get_map(vec![]).await?;
+        db.populate(&mut Value::Null, "field").await?;
+        let _: DocumentCollection<TestDocument> = db.bulk_get(vec![]).await?;
+        let _: HashMap<DocumentId, Vec<String>> = db.conflicts(vec![]).await?;
+        let _: DocumentCollection<TestDocument> = db.get_bulk_params(vec![], None).await?;
+        let _: Vec<DocumentCreatedResult> = db.bulk_docs(&mut [doc.clone()]).await?;
+        let _: Vec<DocumentCreatedResult> = db.bulk_docs_chunked(&mut [doc.clone()], 0).await?;
+        let _: DocumentCollection<TestDocument> = db.bulk_get_chunked(vec![], 0).await?;
+        let _: DocumentCollection<TestDocument> = db.get_all().await?;
+        let _: u64 = db.get_all_batched(tx, 0, 0).await?;
+        let shards: Vec<ShardRange> = db.shards().await?;
+        let _: u64 = db.get_all_batched_in_shard(&shards[0], tx3, 0, 0).await?;
+        let _: u64 = db.find_batched(FindQuery::find_all(), tx2, 0, 0).await?;
+        let _: Vec<TestDocument> = db.find_all(FindQuery::find_all(), 0).await?;
+        let _ = db.find_stream_dedup(FindQuery::find_all(), 0, 0);
+        let _ = db.dump();
+        let _: Option<DocMeta> = db.exists_meta("id").await?;
+        let _ = db.patch("id", |doc| doc["name"] = Value::from("name")).await?;
+        let _: u64 = db.export_to(Vec::new()).await?;
+        let _ = db.query_many_all_docs(QueriesParams::new(vec![])).await?;
+        let _ = db
+            .query_many("design", "view", QueriesParams::<DocumentId>::new(vec![]))
+            .await?;
+        let _ = db
+            .query_many(
+                "design",
+                "view",
+                QueriesParams::new(vec![QueryParams::from_keys(vec![1])]),
+            )
+            .await?;
+        let _: DocumentCollection<TestDocument> = db.get_all_params(None).await?;
+        let _: DocumentCollection<TestDocument> = db.find(&FindQuery::find_all()).await?;
+        let _: DocumentCollection<TestDocument> = db.find(FindQueryRef::new(&Value::Null)).await?;
+        let _: Option<TestDocument> = db.find_one(&FindQuery::find_all()).await?;
+        let _: Vec<Value> = db.find_projected(&FindQuery::find_all()).await?;
+        let _: ExplainResult = db.explain(&FindQuery::find_all()).await?;
+        db.assert_indexed(&FindQuery::find_all()).await?;
+        db.save(&mut doc).await?;
+        let _: (String, String) = db.save_returning_revs(&mut doc).await?;
+        db.create(&mut doc).await?;
+        db.create_with_id("id", &mut doc).await?;
+        db.move_doc("id", "new_id").await?;
+        db.upsert(&mut doc).await?;
+        db.bulk_upsert(&mut [doc.clone()]).await?;
+        db.create_view("design", Value::Null).await?;
+        let _: bool = db
+            .upsert_views("design", &CouchViews::new("view", CouchFunc::new("", None)))
+            .await?;
+        db.create_update_functions("design", CouchUpdate::new("update", "function"))
+            .await?;
+        db.create_design("design", DesignDocument::default()).await?;
+        let _: Vec<DocumentId> = db.list_designs().await?;
+        db.query_raw("design", "view", None).await?;
+        let _: ViewCollection<String, Value, TestDocument> = db.query("design", "view", None).await?;
+        let _: ViewCollection<String, Value, TestDocument> =
+            db.query_no_reduce_with_docs("design", "view", None).await?;
+        let _: Option<u64> = db.query_reduce_value("design", "view").await?;
+        let _: ViewCollection<String, Value, TestDocument> = db.query_stale("design", "view", None).await?;
+        let _: ViewCollection<String, Value, Value> = db.query_as("design", "view", None).await?;
+        let _: RawViewCollection<Value, u64> = db.query_grouped("design", "view", 1, None::<(Value, Value)>).await?;
+        db.execute_update("design", "name", "id", None).await?;
+        let _: i64 = db.increment("design", "increment", "id", 1).await?;
+        let _: bool = db.remove(&doc).await;
+        db.insert_index("name", IndexFields::new(vec![]), None, None).await?;
+        db.read_indexes().await?;
+        let _: Vec<Index> = db.read_indexes_for_ddoc("ddoc").await?;
+        db.delete_index("ddoc".to_string(), "name".to_string()).await?;
+        db.delete_index_spec(&db.read_indexes().await?.indexes[0]).await?;
+        let _: ChangesStream = db.changes(None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_collection_scopes_the_selector() {
+        let client = Client::new_local_test().unwrap();
+        let db: Database<Value> = RawDatabase::new("testdb".to_string(), client).into();
+        let cars = db.collection("type", "car");
+
+        let query = cars.scoped(&FindQuery::new(serde_json::json!({"brand": "Volvo"})).limit(10));
+        assert_eq!(
+            query.selector,
+            serde_json::json!({"$and": [{"type": {"$eq": "car"}}, {"brand": "Volvo"}]})
+        );
+        assert_eq!(query.limit, Some(10));
+    }
+
+    #[test]
+    fn test_typed_delegates_compile() {
+        let client = Client::new_local_test().unwrap();
+        let db: Database<TestDocument> = RawDatabase::new("testdb".to_string(), client).into();
+        assert_eq!(db.name(), "testdb");
+        assert_eq!(db.raw().name(), "testdb");
+        // the future is dropped without being polled, so no request is sent
+        drop(call_all_delegates(db.clone()));
+    }
+}
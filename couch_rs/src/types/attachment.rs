@@ -0,0 +1,100 @@
+use base64::{engine::general_purpose, Engine};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Attachments of a document, by name, as found in its `_attachments` field
+pub type Attachments = HashMap<String, Attachment>;
+
+/// Attachment abstraction. By default `CouchDB` only returns a stub with the attachment's metadata;
+/// the (base64 encoded) data is included when requested with `attachments=true`.
+/// See [attachments](https://docs.couchdb.org/en/stable/api/document/common.html#attachments) for details.
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone, Default)]
+pub struct Attachment {
+    pub content_type: String,
+    /// Whether this is a stub, without the attachment's data
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub stub: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub digest: Option<String>,
+    /// Length of the attachment in bytes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub length: Option<u64>,
+    /// Revision in which the attachment was last changed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revpos: Option<u64>,
+    /// Base64 encoded data of the attachment
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<String>,
+    /// Compression codec used to store the attachment, included with `att_encoding_info=true`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+    /// Length of the compressed attachment, included with `att_encoding_info=true`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encoded_length: Option<u64>,
+}
+
+impl Attachment {
+    /// Creates an inline attachment, to be stored as part of its document.
+    #[must_use]
+    pub fn new(content_type: &str, data: &[u8]) -> Attachment {
+        Attachment {
+            content_type: content_type.to_string(),
+            data: Some(general_purpose::STANDARD.encode(data)),
+            ..Default::default()
+        }
+    }
+
+    /// Returns the decoded data of the attachment, or `None` for a stub.
+    #[must_use]
+    pub fn decoded_data(&self) -> Option<Vec<u8>> {
+        self.data
+            .as_ref()
+            .and_then(|data| general_purpose::STANDARD.decode(data).ok())
+    }
+}
+
+/// Metadata of an attachment, as returned by the headers of a `HEAD` request for the attachment
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone, Default)]
+pub struct AttachmentInfo {
+    pub content_type: String,
+    /// Length of the attachment in bytes
+    pub length: Option<u64>,
+    /// Digest of the attachment, from the `ETag` header
+    pub digest: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_deserialize_attachment_stub() {
+        let attachments: Attachments = serde_json::from_value(json!({
+            "notes.txt": {
+                "content_type": "text/plain",
+                "revpos": 1,
+                "digest": "md5-0R9NyJ1ZtcLGPG6C6Rq+Ug==",
+                "length": 5,
+                "stub": true
+            }
+        }))
+        .unwrap();
+
+        let stub = &attachments["notes.txt"];
+        assert!(stub.stub);
+        assert_eq!(stub.length, Some(5));
+        assert_eq!(stub.content_type, "text/plain");
+        assert_eq!(stub.decoded_data(), None);
+    }
+
+    #[test]
+    fn test_inline_attachment() {
+        let attachment = Attachment::new("text/plain", b"hello");
+        assert_eq!(
+            serde_json::to_value(&attachment).unwrap(),
+            json!({"content_type": "text/plain", "data": "aGVsbG8="})
+        );
+        assert_eq!(attachment.decoded_data(), Some(b"hello".to_vec()));
+    }
+}
@@ -1,6 +1,10 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
-use crate::error::{CouchError, CouchResult, ErrorMessage};
+use crate::{
+    error::{CouchError, CouchResult, ErrorMessage},
+    types::view::CouchFunc,
+};
 
 /// Design document created abstraction
 #[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone)]
@@ -10,6 +14,10 @@ pub struct DesignCreated {
     pub name: Option<String>,
     pub error: Option<String>,
     pub reason: Option<String>,
+    /// Whether the design document of an index existed before `Database::insert_index` was called. Not part of
+    /// the response of `CouchDB`; exposed through [`DesignCreated::index_status`].
+    #[serde(skip)]
+    pub(crate) ddoc_existed: Option<bool>,
 }
 
 #[derive(PartialEq)]
@@ -18,7 +26,28 @@ pub enum Status {
     NotCreated,
 }
 
+/// What `Database::insert_index` did with the index and its design document.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum IndexStatus {
+    /// The index was created in a new design document.
+    CreatedDesign,
+    /// The index was added to an existing design document, next to the indexes already in there.
+    AddedToDesign,
+    /// An index with the same name and definition already existed; nothing changed.
+    Exists,
+}
+
 impl DesignCreated {
+    /// Tells whether `Database::insert_index` created a new design document for the index, added the index to
+    /// an existing one, or found that the index already existed.
+    pub fn index_status(&self) -> CouchResult<IndexStatus> {
+        match self.status()? {
+            Status::NotCreated => Ok(IndexStatus::Exists),
+            Status::Created if self.ddoc_existed == Some(true) => Ok(IndexStatus::AddedToDesign),
+            Status::Created => Ok(IndexStatus::CreatedDesign),
+        }
+    }
+
     pub fn status(&self) -> CouchResult<Status> {
         if let Some(result) = &self.result {
             if result == "created" {
@@ -39,3 +68,173 @@ impl DesignCreated {
         }
     }
 }
+
+/// A complete design document, with views, filters, update functions and a `validate_doc_update`
+/// function, to install with `Database::create_design`. See [design documents](https://docs.couchdb.org/en/stable/ddocs/ddocs.html)
+/// for details on the functions.
+///
+/// Usage:
+/// ```
+/// use couch_rs::error::CouchResult;
+/// use couch_rs::types::design::DesignDocument;
+/// use couch_rs::types::view::CouchFunc;
+///
+/// #[tokio::main]
+/// async fn main() -> CouchResult<()> {
+///     let client = couch_rs::Client::new_local_test()?;
+///     let db = client.db("user_db").await?;
+///
+///     let design = DesignDocument::default()
+///         .view("by_name", CouchFunc::new("function (doc) { emit(doc.name); }", None))
+///         .filter("important", "function (doc, req) { return doc.type === 'important'; }")
+///         .validate_doc_update(
+///             "function (newDoc, oldDoc, userCtx) { if (!newDoc._deleted && !newDoc.name) { throw({forbidden: 'name is required'}); } }",
+///         );
+///     db.create_design("users", design).await?;
+///     Ok(())
+/// }
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct DesignDocument {
+    language: String,
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    views: HashMap<String, CouchFunc>,
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    filters: HashMap<String, String>,
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    updates: HashMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    validate_doc_update: Option<String>,
+}
+
+impl DesignDocument {
+    /// Adds a view.
+    #[must_use]
+    pub fn view(mut self, name: &str, func: CouchFunc) -> Self {
+        self.views.insert(name.to_string(), func);
+        self
+    }
+
+    /// Adds a filter function, to filter the changes feed with.
+    #[must_use]
+    pub fn filter(mut self, name: &str, func: &str) -> Self {
+        self.filters.insert(name.to_string(), func.to_string());
+        self
+    }
+
+    /// Adds an update function, to call through `Database::execute_update`.
+    #[must_use]
+    pub fn update(mut self, name: &str, func: &str) -> Self {
+        self.updates.insert(name.to_string(), func.to_string());
+        self
+    }
+
+    /// Sets the function that validates every document update; it rejects an update by throwing
+    /// `{forbidden: reason}` or `{unauthorized: reason}`.
+    #[must_use]
+    pub fn validate_doc_update(mut self, func: &str) -> Self {
+        self.validate_doc_update = Some(func.to_string());
+        self
+    }
+
+    /// Sets the query server language of the functions; defaults to "javascript".
+    #[must_use]
+    pub fn language(mut self, language: &str) -> Self {
+        self.language = language.to_string();
+        self
+    }
+}
+
+impl Default for DesignDocument {
+    fn default() -> Self {
+        DesignDocument {
+            language: "javascript".to_string(),
+            views: HashMap::new(),
+            filters: HashMap::new(),
+            updates: HashMap::new(),
+            validate_doc_update: None,
+        }
+    }
+}
+
+impl From<DesignDocument> for serde_json::Value {
+    fn from(design: DesignDocument) -> Self {
+        serde_json::to_value(design)
+            .expect("a DesignDocument only has string map keys, so it always serializes to json")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    #[test]
+    fn test_design_document() {
+        let design: Value = DesignDocument::default()
+            .view("by_name", CouchFunc::new("function (doc) { emit(doc.name); }", None))
+            .filter("important", "function (doc, req) { return doc.type === 'important'; }")
+            .update("touch", "function (doc, req) { return [doc, 'ok']; }")
+            .validate_doc_update("function (newDoc, oldDoc, userCtx) {}")
+            .into();
+        assert_eq!(
+            design,
+            json!({
+                "language": "javascript",
+                "views": {"by_name": {"map": "function (doc) { emit(doc.name); }"}},
+                "filters": {"important": "function (doc, req) { return doc.type === 'important'; }"},
+                "updates": {"touch": "function (doc, req) { return [doc, 'ok']; }"},
+                "validate_doc_update": "function (newDoc, oldDoc, userCtx) {}"
+            })
+        );
+    }
+
+    #[test]
+    fn test_index_status() {
+        let created: DesignCreated =
+            serde_json::from_value(json!({"result": "created", "id": "_design/a", "name": "by_name"})).unwrap();
+        assert_eq!(created.index_status().unwrap(), IndexStatus::CreatedDesign);
+
+        let added = DesignCreated {
+            ddoc_existed: Some(true),
+            ..created.clone()
+        };
+        assert_eq!(added.index_status().unwrap(), IndexStatus::AddedToDesign);
+
+        let exists = DesignCreated {
+            result: Some("exists".to_string()),
+            ..added
+        };
+        assert_eq!(exists.index_status().unwrap(), IndexStatus::Exists);
+        assert!(!serde_json::to_string(&exists).unwrap().contains("ddoc_existed"));
+
+        // only set by `insert_index`, never read from a response
+        let response: DesignCreated =
+            serde_json::from_value(json!({"result": "created", "ddoc_existed": true})).unwrap();
+        assert_eq!(response.index_status().unwrap(), IndexStatus::CreatedDesign);
+    }
+
+    #[test]
+    fn test_read_design_document() {
+        let design: DesignDocument = serde_json::from_value(json!({
+            "_id": "_design/users",
+            "_rev": "1-a",
+            "views": {"by_name": {"map": "function (doc) { emit(doc.name); }"}},
+            "validate_doc_update": "function (newDoc, oldDoc, userCtx) {}"
+        }))
+        .unwrap();
+        assert_eq!(design.language, "javascript");
+        assert_eq!(design.views["by_name"].map, "function (doc) { emit(doc.name); }");
+        assert!(design.filters.is_empty());
+
+        let value: Value = design.clone().into();
+        assert_eq!(value["validate_doc_update"], "function (newDoc, oldDoc, userCtx) {}");
+    }
+
+    #[test]
+    fn test_empty_design_document() {
+        let design: Value = DesignDocument::default().language("erlang").into();
+        assert_eq!(design, json!({"language": "erlang"}));
+    }
+}
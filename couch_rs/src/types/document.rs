@@ -14,7 +14,7 @@ pub enum DocumentRef<T> {
 }
 
 /// Abstracted document creation response
-#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone)]
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone, Default)]
 pub(crate) struct DocumentCreatedResponse {
     /// Document ID
     pub id: Option<String>,
@@ -47,11 +47,10 @@ impl From<DocumentCreatedResponse> for DocumentCreatedResult {
                 _ => StatusCode::INTERNAL_SERVER_ERROR,
             };
 
-            Err(CouchError::new_with_id(
-                response.id,
-                response.reason.unwrap_or_default(),
-                status_code,
-            ))
+            Err(
+                CouchError::new_with_id(response.id, response.reason.unwrap_or_default(), status_code)
+                    .with_couch_error(Some(error)),
+            )
         } else {
             match (response.id, response.rev) {
                 (Some(id), Some(rev)) => Ok(DocumentCreatedDetails { id, rev }),
@@ -65,3 +64,176 @@ impl From<DocumentCreatedResponse> for DocumentCreatedResult {
 }
 
 pub type DocumentCreatedResult = CouchResult<DocumentCreatedDetails>;
+
+/// Revision history of a document, as returned with `revs=true`
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone)]
+pub struct Revisions {
+    /// Generation number of the newest revision
+    pub start: u64,
+    /// Hashes of the revisions, newest first
+    pub ids: Vec<String>,
+}
+
+/// Metadata of a document, from the headers of a `HEAD` request; see `Database::exists_meta`.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct DocMeta {
+    /// Current revision of the document
+    pub rev: String,
+    /// Size of the document's JSON in bytes, when `CouchDB` reports it
+    pub size: Option<u64>,
+}
+
+/// A leaf revision of a document; either the winning revision, a conflict or a deleted tombstone
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct LeafRevision<T> {
+    /// Revision token of this leaf
+    pub rev: String,
+    /// Whether this leaf is a deleted tombstone
+    pub deleted: bool,
+    /// Revision history leading up to this leaf
+    pub revisions: Option<Revisions>,
+    /// The document; `None` for a deleted tombstone
+    pub doc: Option<T>,
+}
+
+/// A document to fetch with `_bulk_get`; optionally at a specific revision
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone)]
+pub struct BulkGetItem {
+    pub id: DocumentId,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rev: Option<String>,
+}
+
+impl BulkGetItem {
+    /// Fetches the current revision of the document
+    #[must_use]
+    pub fn new(id: &str) -> Self {
+        BulkGetItem {
+            id: id.to_string(),
+            rev: None,
+        }
+    }
+
+    /// Fetches the given revision of the document, instead of the current one
+    #[must_use]
+    pub fn rev(mut self, rev: &str) -> Self {
+        self.rev = Some(rev.to_string());
+        self
+    }
+}
+
+/// Response of `_bulk_get`
+#[derive(Deserialize, Debug)]
+pub(crate) struct BulkGetResponse<T> {
+    pub results: Vec<BulkGetResult<T>>,
+}
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct BulkGetResult<T> {
+    pub docs: Vec<BulkGetDoc<T>>,
+}
+
+/// The requested document; `ok` is absent when it could not be fetched, which `CouchDB` reports in `error`
+#[derive(Deserialize, Debug)]
+pub(crate) struct BulkGetDoc<T> {
+    pub ok: Option<T>,
+}
+
+/// Summary of the per-document results of a bulk operation, like [`crate::database::Database::bulk_docs`]
+///
+/// Usage:
+/// ```
+/// use couch_rs::error::CouchResult;
+/// use couch_rs::types::document::BulkDocsReport;
+/// use serde_json::json;
+///
+/// #[tokio::main]
+/// async fn main() -> CouchResult<()> {
+///     let client = couch_rs::Client::new_local_test()?;
+///     let db = client.db("test_db").await?;
+///     let mut docs = vec![json!({"_id": "first"}), json!({"_id": "first"})];
+///     let report = BulkDocsReport::from(db.bulk_docs(&mut docs).await?);
+///     println!("{} saved, conflicts: {:?}", report.succeeded().len(), report.conflicts());
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BulkDocsReport {
+    results: Vec<DocumentCreatedResult>,
+}
+
+impl BulkDocsReport {
+    /// All results, in the order of the documents in the request
+    pub fn results(&self) -> &[DocumentCreatedResult] {
+        &self.results
+    }
+
+    /// Details of the documents that were saved
+    #[must_use]
+    pub fn succeeded(&self) -> Vec<&DocumentCreatedDetails> {
+        self.results.iter().filter_map(|r| r.as_ref().ok()).collect()
+    }
+
+    /// Ids of the documents that were rejected with a conflict
+    #[must_use]
+    pub fn conflicts(&self) -> Vec<&str> {
+        self.errors()
+            .into_iter()
+            .filter(|err| err.status() == Some(StatusCode::CONFLICT))
+            .filter_map(|err| match err {
+                CouchError::OperationFailed(details) => details.id.as_deref(),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// All errors, including conflicts
+    #[must_use]
+    pub fn errors(&self) -> Vec<&CouchError> {
+        self.results.iter().filter_map(|r| r.as_ref().err()).collect()
+    }
+
+    /// Whether every document was saved
+    #[must_use]
+    pub fn is_success(&self) -> bool {
+        self.results.iter().all(Result::is_ok)
+    }
+}
+
+impl From<Vec<DocumentCreatedResult>> for BulkDocsReport {
+    fn from(results: Vec<DocumentCreatedResult>) -> Self {
+        BulkDocsReport { results }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bulk_docs_report() {
+        let report = BulkDocsReport::from(vec![
+            Ok(DocumentCreatedDetails {
+                id: s!("1"),
+                rev: s!("1-a"),
+            }),
+            Err(CouchError::new_with_id(
+                Some(s!("2")),
+                s!("conflict"),
+                StatusCode::CONFLICT,
+            )),
+            Err(CouchError::new_with_id(
+                Some(s!("3")),
+                s!("forbidden"),
+                StatusCode::FORBIDDEN,
+            )),
+        ]);
+
+        assert_eq!(report.results().len(), 3);
+        assert_eq!(report.succeeded().len(), 1);
+        assert_eq!(report.succeeded()[0].id, "1");
+        assert_eq!(report.conflicts(), vec!["2"]);
+        assert_eq!(report.errors().len(), 2);
+        assert!(!report.is_success());
+    }
+}
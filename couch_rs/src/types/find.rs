@@ -1,4 +1,7 @@
-use crate::document::TypedCouchDocument;
+use crate::{
+    document::TypedCouchDocument,
+    types::{index::Index, query::UpdateView},
+};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::{
@@ -35,14 +38,35 @@ pub enum SortSpec {
     Complex(SortSpecContent),
 }
 
-/// Index spec abstraction
+/// Index spec abstraction, used to force the index `_find` uses.
+/// ```
+/// use couch_rs::types::find::{FindQuery, IndexSpec};
+/// use serde_json::json;
+/// let _query = FindQuery::new(json!({"thing": true})).use_index(IndexSpec::named("thing-ddoc", "thing-index"));
+/// ```
 #[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone)]
 #[serde(untagged)]
 pub enum IndexSpec {
+    /// Any index in the given design document; serialized as `"ddoc"`
     DesignDocument(String),
+    /// The named index in the given design document; serialized as `["ddoc", "name"]`
     IndexName((String, String)),
 }
 
+impl IndexSpec {
+    /// Use any index in the design document `ddoc`.
+    #[must_use]
+    pub fn ddoc(ddoc: &str) -> IndexSpec {
+        IndexSpec::DesignDocument(ddoc.to_string())
+    }
+
+    /// Use the index named `index` in the design document `ddoc`.
+    #[must_use]
+    pub fn named(ddoc: &str, index: &str) -> IndexSpec {
+        IndexSpec::IndexName((ddoc.to_string(), index.to_string()))
+    }
+}
+
 /// Find query abstraction
 /// Parameters here [/db/_find](https://docs.couchdb.org/en/latest/api/database/find.html)
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
@@ -70,12 +94,16 @@ pub struct FindQuery {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub bookmark: Option<String>,
 
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub update: Option<bool>,
+    /// Whether to update the index prior to returning the result. `_find` only supports
+    /// `UpdateView::True` and `UpdateView::False`, which are sent as a json boolean.
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "update_view_as_bool")]
+    pub update: Option<UpdateView>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stable: Option<bool>,
 
+    /// Deprecated by `CouchDB`; `stale=ok` equals `update=false, stable=true`.
+    #[deprecated(since = "0.10.2", note = "please use `update` and `stable` instead")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stale: Option<String>,
 
@@ -100,13 +128,15 @@ pub struct FindResult<T: TypedCouchDocument> {
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
 pub struct ExplainResult {
     pub dbname: String,
-    pub index: IndexSpec,
+    pub index: Index,
     pub selector: Value,
     pub opts: Value,
     pub limit: u32,
     pub skip: u64,
-    pub fields: Vec<String>,
-    pub range: Value,
+    /// Either the list of requested fields, or `"all_fields"`
+    pub fields: Value,
+    #[serde(default)]
+    pub range: Option<Value>,
 }
 
 /// $ne operation
@@ -174,6 +204,7 @@ impl FindQuery {
     // Create a new FindQuery from a valid selector. The selector syntax is documented here:
     // https://docs.couchdb.org/en/latest/api/database/find.html#find-selectors
     #[must_use]
+    #[allow(deprecated)]
     pub fn new(selector: Value) -> Self {
         FindQuery {
             selector,
@@ -237,6 +268,12 @@ impl FindQuery {
         self
     }
 
+    /// Returns the names of the fields this query sorts on.
+    #[must_use]
+    pub fn sort_fields(&self) -> Vec<String> {
+        sort_fields(&self.sort)
+    }
+
     #[must_use]
     pub fn bookmark(mut self, bookmark: &str) -> Self {
         self.bookmark = Some(bookmark.to_string());
@@ -244,7 +281,7 @@ impl FindQuery {
     }
 
     #[must_use]
-    pub fn update(mut self, update: bool) -> Self {
+    pub fn update(mut self, update: UpdateView) -> Self {
         self.update = Some(update);
         self
     }
@@ -256,6 +293,8 @@ impl FindQuery {
     }
 
     #[must_use]
+    #[deprecated(since = "0.10.2", note = "please use `update` and `stable` instead")]
+    #[allow(deprecated)]
     pub fn stale(mut self, stale: &str) -> Self {
         self.stale = Some(stale.to_string());
         self
@@ -268,6 +307,207 @@ impl FindQuery {
     }
 }
 
+fn sort_fields(sort: &[SortSpec]) -> Vec<String> {
+    sort.iter()
+        .flat_map(|spec| match spec {
+            SortSpec::Simple(field) => vec![field.clone()],
+            SortSpec::Complex(fields) => fields.keys().cloned().collect(),
+        })
+        .collect()
+}
+
+/// A find query that borrows its selector and other parameters, instead of owning them.
+///
+/// `Database::find` accepts both a `&FindQuery` and a `FindQueryRef`. Sending many similar queries that
+/// only differ in e.g. their `limit` or `bookmark` through owned `FindQuery`s means cloning the whole
+/// selector for every request; a `FindQueryRef` borrowing one selector, or one `FindQuery` as a template,
+/// avoids those clones. A selector can be shared between tasks as an `Arc<Value>`, and borrowed per request.
+///
+/// Usage:
+/// ```
+/// use couch_rs::error::CouchResult;
+/// use couch_rs::types::find::{FindQuery, FindQueryRef};
+/// use serde_json::{json, Value};
+/// use std::sync::Arc;
+///
+/// #[tokio::main]
+/// async fn main() -> CouchResult<()> {
+///     let client = couch_rs::Client::new_local_test()?;
+///     let db = client.db("test_db").await?;
+///     let selector = Arc::new(json!({"thing": true}));
+///
+///     let mut bookmark: Option<String> = None;
+///     loop {
+///         let query = FindQueryRef::new(&selector).limit(100).bookmark(bookmark.as_deref());
+///         let docs = db.find::<Value>(query).await?;
+///         if docs.rows.is_empty() {
+///             break;
+///         }
+///         bookmark = docs.bookmark;
+///     }
+///
+///     // or use an existing query as the template
+///     let template = FindQuery::new(json!({"thing": true})).limit(100);
+///     let _ = db.find::<Value>(FindQueryRef::from(&template).skip(100)).await?;
+///     Ok(())
+/// }
+/// ```
+#[derive(Serialize, PartialEq, Eq, Debug, Clone)]
+pub struct FindQueryRef<'a> {
+    selector: &'a Value,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    limit: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    skip: Option<u64>,
+
+    #[serde(skip_serializing_if = "<[SortSpec]>::is_empty")]
+    sort: &'a [SortSpec],
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fields: Option<&'a [String]>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    use_index: Option<&'a IndexSpec>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    r: Option<i32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bookmark: Option<&'a str>,
+
+    #[serde(skip_serializing_if = "Option::is_none", with = "update_view_as_bool")]
+    update: Option<UpdateView>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stable: Option<bool>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stale: Option<&'a str>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    execution_stats: Option<bool>,
+}
+
+impl<'a> FindQueryRef<'a> {
+    /// Creates a query for the borrowed selector, without any other parameters.
+    #[must_use]
+    pub fn new(selector: &'a Value) -> Self {
+        FindQueryRef {
+            selector,
+            limit: None,
+            skip: None,
+            sort: &[],
+            fields: None,
+            use_index: None,
+            r: None,
+            bookmark: None,
+            update: None,
+            stable: None,
+            stale: None,
+            execution_stats: None,
+        }
+    }
+
+    #[must_use]
+    pub fn selector(&self) -> &'a Value {
+        self.selector
+    }
+
+    #[must_use]
+    pub fn limit(mut self, limit: u64) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    #[must_use]
+    pub fn skip(mut self, skip: u64) -> Self {
+        self.skip = Some(skip);
+        self
+    }
+
+    /// Sets the bookmark to continue from; `None` starts from the beginning.
+    #[must_use]
+    pub fn bookmark(mut self, bookmark: Option<&'a str>) -> Self {
+        self.bookmark = bookmark;
+        self
+    }
+
+    /// Returns the names of the fields this query sorts on.
+    #[must_use]
+    pub fn sort_fields(&self) -> Vec<String> {
+        sort_fields(self.sort)
+    }
+}
+
+impl<'a> From<&'a FindQuery> for FindQueryRef<'a> {
+    #[allow(deprecated)]
+    fn from(query: &'a FindQuery) -> Self {
+        // destructured, so a field added to `FindQuery` can not be forgotten here
+        let FindQuery {
+            selector,
+            limit,
+            skip,
+            sort,
+            fields,
+            use_index,
+            r,
+            bookmark,
+            update,
+            stable,
+            stale,
+            execution_stats,
+        } = query;
+        FindQueryRef {
+            selector,
+            limit: *limit,
+            skip: *skip,
+            sort,
+            fields: fields.as_deref(),
+            use_index: use_index.as_ref(),
+            r: *r,
+            bookmark: bookmark.as_deref(),
+            update: update.clone(),
+            stable: *stable,
+            stale: stale.as_deref(),
+            execution_stats: *execution_stats,
+        }
+    }
+}
+
+/// `_find` expects `update` as a json boolean, whereas the view API expects a string.
+mod update_view_as_bool {
+    use crate::types::query::UpdateView;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(untagged)]
+    enum BoolOrView {
+        Bool(bool),
+        View(UpdateView),
+    }
+
+    pub fn serialize<S: Serializer>(update: &Option<UpdateView>, serializer: S) -> Result<S::Ok, S::Error> {
+        match update {
+            Some(UpdateView::True) => serializer.serialize_bool(true),
+            Some(UpdateView::False) => serializer.serialize_bool(false),
+            // not supported by _find, let CouchDB report the error
+            Some(UpdateView::Lazy) => UpdateView::Lazy.serialize(serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<UpdateView>, D::Error> {
+        Ok(match Option::<BoolOrView>::deserialize(deserializer)? {
+            Some(BoolOrView::Bool(true)) => Some(UpdateView::True),
+            Some(BoolOrView::Bool(false)) => Some(UpdateView::False),
+            Some(BoolOrView::View(view)) => Some(view),
+            None => None,
+        })
+    }
+}
+
 impl From<FindQuery> for serde_json::Value {
     fn from(q: FindQuery) -> Self {
         serde_json::to_value(q).expect("can not convert into json")
@@ -298,6 +538,53 @@ mod tests {
     use super::*;
     use serde_json::json;
 
+    #[test]
+    fn test_find_query_ref_serializes_like_find_query() {
+        let query = FindQuery::new(json!({"thing": true}))
+            .limit(10)
+            .skip(5)
+            .sort(vec![SortSpec::Simple(s!("thing"))])
+            .fields(vec![s!("_id")])
+            .use_index(IndexSpec::named("ddoc", "index"))
+            .r(1)
+            .bookmark("bookmark")
+            .update(UpdateView::False)
+            .stable(true)
+            .execution_stats(true);
+
+        let query_ref = FindQueryRef::from(&query);
+        assert_eq!(serde_json::to_value(&query_ref).unwrap(), query.as_value());
+        assert_eq!(query_ref.sort_fields(), vec![s!("thing")]);
+
+        let minimal = FindQuery::new(json!({"thing": true}));
+        assert_eq!(
+            serde_json::to_value(FindQueryRef::from(&minimal)).unwrap(),
+            minimal.as_value()
+        );
+    }
+
+    #[test]
+    fn test_find_query_ref_does_not_clone_the_selector() {
+        let selector = std::sync::Arc::new(json!({"thing": {"$in": (0..1000).collect::<Vec<_>>()}}));
+
+        let mut bookmark = None;
+        for page in 0..1000 {
+            let query = FindQueryRef::new(&selector).limit(10).bookmark(bookmark);
+            // the query borrows the shared selector: no copy was made, and no reference was added
+            assert!(std::ptr::eq(query.selector(), &*selector));
+            assert_eq!(std::sync::Arc::strong_count(&selector), 1);
+            if page == 0 {
+                bookmark = Some("next");
+            }
+        }
+
+        let query = FindQueryRef::new(&selector).limit(10).bookmark(Some("next"));
+        let value = serde_json::to_value(query).unwrap();
+        assert_eq!(value["limit"], json!(10));
+        assert_eq!(value["bookmark"], json!("next"));
+        assert_eq!(&value["selector"], &*selector);
+    }
+
     #[test]
     fn test_convert_to_value() {
         let mut sort = HashMap::new();
@@ -320,6 +607,24 @@ mod tests {
         assert_eq!(selector, r#"{"selector":{"_id":{"$ne":null}}}"#);
     }
 
+    #[test]
+    fn test_update_serialization() {
+        let query = FindQuery::find_all().update(UpdateView::False);
+        assert_eq!(query.to_string(), r#"{"selector":{"_id":{"$ne":null}},"update":false}"#);
+        let query = FindQuery::find_all().update(UpdateView::True);
+        assert_eq!(query.to_string(), r#"{"selector":{"_id":{"$ne":null}},"update":true}"#);
+
+        let query = FindQuery::new_from_value(json!({"selector": {}, "sort": [], "update": false}));
+        assert_eq!(query.update, Some(UpdateView::False));
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_stale_serialization() {
+        let query = FindQuery::find_all().stale("ok");
+        assert_eq!(query.to_string(), r#"{"selector":{"_id":{"$ne":null}},"stale":"ok"}"#);
+    }
+
     #[test]
     fn test_from_json() {
         let query = FindQuery::new_from_value(json!({
@@ -345,4 +650,53 @@ mod tests {
             panic!("unexpected sort spec");
         }
     }
+
+    #[test]
+    fn test_explain_result_deserialization() {
+        let explained: ExplainResult = serde_json::from_value(json!({
+            "dbname": "test_db",
+            "index": {
+                "ddoc": null,
+                "name": "_all_docs",
+                "type": "special",
+                "def": {"fields": [{"_id": "asc"}]}
+            },
+            "partitioned": false,
+            "selector": {"first_name": {"$eq": "John"}},
+            "opts": {"use_index": [], "bookmark": "nil", "limit": 25, "skip": 0},
+            "limit": 25,
+            "skip": 0,
+            "fields": "all_fields",
+            "covering": null
+        }))
+        .expect("can not parse explain result");
+
+        assert_eq!(explained.index.name, "_all_docs");
+        assert_eq!(explained.fields, json!("all_fields"));
+        assert_eq!(explained.range, None);
+    }
+
+    #[test]
+    fn test_sort_fields() {
+        let query = FindQuery::find_all().sort(vec![
+            SortSpec::Simple(s!("first_name")),
+            SortSpec::Complex(HashMap::from([(s!("last_name"), SortDirection::Desc)])),
+        ]);
+        assert_eq!(query.sort_fields(), vec![s!("first_name"), s!("last_name")]);
+    }
+
+    #[test]
+    fn test_use_index_serialization() {
+        let query = FindQuery::find_all().use_index(IndexSpec::ddoc("thing-ddoc"));
+        assert_eq!(
+            query.to_string(),
+            r#"{"selector":{"_id":{"$ne":null}},"use_index":"thing-ddoc"}"#
+        );
+
+        let query = FindQuery::find_all().use_index(IndexSpec::named("thing-ddoc", "thing-index"));
+        assert_eq!(
+            query.to_string(),
+            r#"{"selector":{"_id":{"$ne":null}},"use_index":["thing-ddoc","thing-index"]}"#
+        );
+    }
 }
@@ -2,7 +2,8 @@ use super::{document, find};
 use document::DocumentId;
 use find::SortSpec;
 use serde::{Deserialize, Serialize};
-use std::fmt;
+use serde_json::{json, Value};
+use std::{collections::HashMap, fmt};
 
 /// Index fields abstraction
 #[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone)]
@@ -17,14 +18,163 @@ impl IndexFields {
     }
 }
 
+/// Field of a text index, with the type of its value
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone)]
+#[serde(try_from = "RawTextIndexField")]
+pub struct TextIndexField {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub field_type: TextFieldType,
+}
+
+/// Text index fields are created as `{"name": "title", "type": "string"}`,
+/// but listed as `{"title": "string"}`.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawTextIndexField {
+    Named {
+        name: String,
+        #[serde(rename = "type")]
+        field_type: TextFieldType,
+    },
+    Listed(HashMap<String, TextFieldType>),
+}
+
+impl TryFrom<RawTextIndexField> for TextIndexField {
+    type Error = String;
+
+    fn try_from(raw: RawTextIndexField) -> Result<Self, Self::Error> {
+        match raw {
+            RawTextIndexField::Named { name, field_type } => Ok(TextIndexField { name, field_type }),
+            RawTextIndexField::Listed(field) => {
+                let mut fields = field.into_iter();
+                match (fields.next(), fields.next()) {
+                    (Some((name, field_type)), None) => Ok(TextIndexField { name, field_type }),
+                    _ => Err("expected a single text index field".to_string()),
+                }
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone)]
+pub enum TextFieldType {
+    #[serde(rename = "string")]
+    String,
+    #[serde(rename = "number")]
+    Number,
+    #[serde(rename = "boolean")]
+    Boolean,
+}
+
+impl TextIndexField {
+    #[must_use]
+    pub fn new(name: &str, field_type: TextFieldType) -> TextIndexField {
+        TextIndexField {
+            name: name.to_string(),
+            field_type,
+        }
+    }
+}
+
+/// Text index definition. See [creating a text index](https://docs.couchdb.org/en/stable/ddocs/search.html)
+/// for details on the analyzers and the default field.
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone, Default)]
+pub struct TextIndexDef {
+    /// Fields to index; all fields are indexed when empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<TextIndexField>,
+    /// Analyzer, e.g. "standard", or an object for a "perfield" analyzer
+    #[serde(alias = "default_analyzer", skip_serializing_if = "Option::is_none")]
+    pub analyzer: Option<Value>,
+    /// Configuration of the `$default` field, e.g. `{"enabled": true, "analyzer": "standard"}`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_field: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub selector: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index_array_lengths: Option<bool>,
+}
+
+impl TextIndexDef {
+    #[must_use]
+    pub fn new(fields: Vec<TextIndexField>) -> TextIndexDef {
+        TextIndexDef {
+            fields,
+            ..Default::default()
+        }
+    }
+
+    #[must_use]
+    pub fn analyzer(mut self, analyzer: &str) -> Self {
+        self.analyzer = Some(Value::String(analyzer.to_string()));
+        self
+    }
+
+    #[must_use]
+    pub fn default_field(mut self, enabled: bool, analyzer: &str) -> Self {
+        self.default_field = Some(json!({ "enabled": enabled, "analyzer": analyzer }));
+        self
+    }
+}
+
+/// Definition of either a json or a text index
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone)]
+#[serde(untagged)]
+pub enum IndexDef {
+    Json(IndexFields),
+    Text(TextIndexDef),
+}
+
+impl From<IndexFields> for IndexDef {
+    fn from(fields: IndexFields) -> Self {
+        IndexDef::Json(fields)
+    }
+}
+
+impl From<TextIndexDef> for IndexDef {
+    fn from(def: TextIndexDef) -> Self {
+        IndexDef::Text(def)
+    }
+}
+
 /// Index abstraction
 #[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone)]
+#[serde(try_from = "RawIndex")]
 pub struct Index {
     pub ddoc: Option<DocumentId>,
     pub name: String,
     #[serde(rename = "type")]
     pub index_type: Option<IndexType>,
-    pub def: IndexFields,
+    pub def: IndexDef,
+}
+
+/// Index as returned by `CouchDB`; the definition is parsed according to the index type.
+#[derive(Deserialize)]
+struct RawIndex {
+    ddoc: Option<DocumentId>,
+    name: String,
+    #[serde(rename = "type")]
+    index_type: Option<IndexType>,
+    def: Value,
+}
+
+impl TryFrom<RawIndex> for Index {
+    type Error = serde_json::Error;
+
+    fn try_from(raw: RawIndex) -> Result<Self, Self::Error> {
+        let def = match raw.index_type {
+            Some(IndexType::Text) => IndexDef::Text(serde_json::from_value(raw.def)?),
+            _ => IndexDef::Json(serde_json::from_value(raw.def)?),
+        };
+
+        Ok(Index {
+            ddoc: raw.ddoc,
+            name: raw.name,
+            index_type: raw.index_type,
+            def,
+        })
+    }
 }
 
 #[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone)]
@@ -58,3 +208,61 @@ pub struct DatabaseIndexList {
 pub struct DeleteIndexResponse {
     pub ok: bool,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_index_def_serialization() {
+        let def = TextIndexDef::new(vec![TextIndexField::new("title", TextFieldType::String)])
+            .analyzer("english")
+            .default_field(false, "standard");
+        assert_eq!(
+            serde_json::to_value(IndexDef::from(def)).unwrap(),
+            json!({
+                "fields": [{"name": "title", "type": "string"}],
+                "analyzer": "english",
+                "default_field": {"enabled": false, "analyzer": "standard"}
+            })
+        );
+    }
+
+    #[test]
+    fn test_deserialize_listed_indexes() {
+        let list: DatabaseIndexList = serde_json::from_value(json!({
+            "total_rows": 2,
+            "indexes": [
+                {
+                    "ddoc": "_design/json-ddoc",
+                    "name": "json-index",
+                    "type": "json",
+                    "def": {"fields": []}
+                },
+                {
+                    "ddoc": "_design/text-ddoc",
+                    "name": "text-index",
+                    "type": "text",
+                    "def": {
+                        "default_analyzer": "keyword",
+                        "default_field": {},
+                        "selector": {},
+                        "fields": [{"title": "string"}],
+                        "index_array_lengths": true
+                    }
+                }
+            ]
+        }))
+        .unwrap_or_else(|err| panic!("can not parse index list: {err}"));
+
+        assert_eq!(list.indexes[0].def, IndexDef::Json(IndexFields::new(vec![])));
+        assert_eq!(list.indexes[1].index_type, Some(IndexType::Text));
+        match &list.indexes[1].def {
+            IndexDef::Text(def) => {
+                assert_eq!(def.fields, vec![TextIndexField::new("title", TextFieldType::String)]);
+                assert_eq!(def.analyzer, Some(json!("keyword")));
+            }
+            IndexDef::Json(_) => panic!("expected a text index definition"),
+        }
+    }
+}
@@ -1,8 +1,10 @@
+pub mod attachment;
 pub mod changes;
 pub mod design;
 pub mod document;
 pub mod find;
 pub mod index;
 pub mod query;
+pub mod selector;
 pub mod system;
 pub mod view;
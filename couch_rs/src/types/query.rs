@@ -1,15 +1,24 @@
 use super::document::DocumentId;
-use crate::{document::TypedCouchDocument, types::view::ViewCollection};
+use crate::{
+    document::TypedCouchDocument,
+    error::{CouchError, CouchResult},
+    types::view::ViewCollection,
+};
+use reqwest::StatusCode;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
 
+/// Multiple queries, to send in a single request. The queries of a view can use any key type `K`, e.g.
+/// numbers or arrays; the queries of `_all_docs` use document ids.
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
-pub struct QueriesParams {
-    queries: Vec<QueryParams<DocumentId>>,
+pub struct QueriesParams<K: Serialize + PartialEq + std::fmt::Debug + Clone = DocumentId> {
+    queries: Vec<QueryParams<K>>,
 }
 
-impl QueriesParams {
+impl<K: Serialize + PartialEq + std::fmt::Debug + Clone> QueriesParams<K> {
     #[must_use]
-    pub fn new(params: Vec<QueryParams<DocumentId>>) -> Self {
+    pub fn new(params: Vec<QueryParams<K>>) -> Self {
         QueriesParams { queries: params }
     }
 }
@@ -90,6 +99,9 @@ pub struct QueryParams<K: Serialize + PartialEq + std::fmt::Debug + Clone> {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stable: Option<bool>,
 
+    /// Deprecated by `CouchDB` in favour of `update` combined with `stable`;
+    /// `stale=ok` equals `update=false, stable=true` and `stale=update_after` equals `update=lazy, stable=true`.
+    #[deprecated(since = "0.10.2", note = "please use `update` and `stable` instead")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stale: Option<String>,
 
@@ -104,9 +116,14 @@ pub struct QueryParams<K: Serialize + PartialEq + std::fmt::Debug + Clone> {
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub update_seq: Option<bool>,
+
+    /// Additional parameters that are not (yet) modelled by this struct, sent along with the typed fields.
+    #[serde(flatten, default)]
+    pub extra: HashMap<String, String>,
 }
 
 impl<K: Serialize + DeserializeOwned + PartialEq + std::fmt::Debug + Clone> Default for QueryParams<K> {
+    #[allow(deprecated)]
     fn default() -> Self {
         Self {
             conflicts: None,
@@ -131,6 +148,7 @@ impl<K: Serialize + DeserializeOwned + PartialEq + std::fmt::Debug + Clone> Defa
             start_key_doc_id: None,
             update: None,
             update_seq: None,
+            extra: HashMap::new(),
         }
     }
 }
@@ -144,6 +162,34 @@ impl<K: Serialize + DeserializeOwned + PartialEq + std::fmt::Debug + Clone> Quer
         }
     }
 
+    /// Checks for combinations of parameters that `CouchDB` rejects, to report them with a clear message
+    /// instead of an opaque `400 Bad Request`. `Database::query` validates the parameters before sending them.
+    pub fn validate(&self) -> CouchResult<()> {
+        let invalid = |message: &str| {
+            Err(CouchError::new(
+                format!("invalid query parameters: {message}"),
+                StatusCode::BAD_REQUEST,
+            ))
+        };
+
+        let grouped = self.group == Some(true) || self.group_level.is_some();
+        if self.reduce == Some(false) && grouped {
+            return invalid("`group` and `group_level` require a reduce, and can not be combined with `reduce=false`");
+        }
+        if self.group == Some(false) && self.group_level.is_some_and(|level| level > 0) {
+            return invalid("`group_level` can not be combined with `group=false`");
+        }
+        if self.reduce == Some(true) && self.include_docs == Some(true) {
+            return invalid(
+                "`include_docs` can not be combined with `reduce=true`; add `reduce=false` to include the documents",
+            );
+        }
+        if !self.keys.is_empty() && (self.key.is_some() || self.start_key.is_some() || self.end_key.is_some()) {
+            return invalid("`keys` can not be combined with `key`, `start_key` or `end_key`");
+        }
+        Ok(())
+    }
+
     #[must_use]
     pub fn conflicts(mut self, conflicts: bool) -> Self {
         self.conflicts = Some(conflicts);
@@ -174,6 +220,8 @@ impl<K: Serialize + DeserializeOwned + PartialEq + std::fmt::Debug + Clone> Quer
         self
     }
 
+    /// Includes the documents of the rows. A view with a reduce function also needs `reduce(false)`, or
+    /// `CouchDB` rejects the query; see `Database::query_no_reduce_with_docs`.
     #[must_use]
     pub fn include_docs(mut self, include_docs: bool) -> Self {
         self.include_docs = Some(include_docs);
@@ -263,11 +311,147 @@ impl<K: Serialize + DeserializeOwned + PartialEq + std::fmt::Debug + Clone> Quer
         self.update_seq = Some(update_seq);
         self
     }
+
+    /// Adds a parameter that has no dedicated field in `QueryParams`.
+    #[must_use]
+    pub fn extra_param(mut self, name: &str, value: &str) -> Self {
+        self.extra.insert(name.to_string(), value.to_string());
+        self
+    }
+}
+
+/// The lowest key in the [view collation](https://docs.couchdb.org/en/stable/ddocs/views/collation.html):
+/// `null` sorts before any other value. Use it as an element of an array key, to start a range before any
+/// value at that position, like `[2024, min_key()]`.
+#[must_use]
+pub fn min_key() -> Value {
+    Value::Null
+}
+
+/// A key that sorts after numbers, strings and arrays in the
+/// [view collation](https://docs.couchdb.org/en/stable/ddocs/views/collation.html): an empty object. Use it as
+/// an element of an array key, to end a range after any value at that position, like `[2024, max_key()]`.
+#[must_use]
+pub fn max_key() -> Value {
+    Value::Object(Map::new())
+}
+
+/// Helpers for views with array keys, like `[year, month, day]`.
+impl QueryParams<Value> {
+    #[must_use]
+    pub fn start_key_array(self, start_key: &[Value]) -> Self {
+        self.start_key(Value::Array(start_key.to_vec()))
+    }
+
+    #[must_use]
+    pub fn end_key_array(self, end_key: &[Value]) -> Self {
+        self.end_key(Value::Array(end_key.to_vec()))
+    }
+
+    /// Limits the query to the array keys that start with `prefix`; e.g. a prefix of `[2024]` matches
+    /// `[2024, 1, 31]`, but not `[2025, 1, 1]`.
+    #[must_use]
+    pub fn key_prefix(self, prefix: &[Value]) -> Self {
+        let mut end_key = prefix.to_vec();
+        end_key.push(max_key());
+        self.start_key_array(prefix).end_key_array(&end_key)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_array_keys() {
+        let qp = QueryParams::default()
+            .start_key_array(&[json!("2024"), min_key()])
+            .end_key_array(&[json!("2024"), max_key()]);
+        assert_eq!(
+            serde_json::to_string(&qp).unwrap(),
+            r#"{"end_key":["2024",{}],"start_key":["2024",null]}"#
+        );
+    }
+
+    #[test]
+    fn test_key_prefix() {
+        let qp = QueryParams::default().key_prefix(&[json!(2024), json!(1)]);
+        assert_eq!(qp.start_key, Some(json!([2024, 1])));
+        assert_eq!(qp.end_key, Some(json!([2024, 1, {}])));
+    }
+
+    #[test]
+    fn test_validate_valid_combinations() {
+        assert!(QueryParams::<String>::default().validate().is_ok());
+        assert!(QueryParams::<String>::default().group(true).validate().is_ok());
+        assert!(QueryParams::<String>::default()
+            .reduce(true)
+            .group_level(2)
+            .validate()
+            .is_ok());
+        assert!(QueryParams::<String>::default()
+            .group(false)
+            .group_level(0)
+            .validate()
+            .is_ok());
+        assert!(QueryParams::<String>::default()
+            .reduce(false)
+            .include_docs(true)
+            .validate()
+            .is_ok());
+        assert!(QueryParams::from_keys(vec![s!("a")]).group(true).validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_group_without_reduce() {
+        let err = QueryParams::<String>::default()
+            .reduce(false)
+            .group(true)
+            .validate()
+            .unwrap_err();
+        assert_eq!(err.status(), Some(StatusCode::BAD_REQUEST));
+        assert!(err.to_string().contains("reduce=false"));
+
+        let err = QueryParams::<String>::default()
+            .reduce(false)
+            .group_level(1)
+            .validate()
+            .unwrap_err();
+        assert!(err.to_string().contains("reduce=false"));
+    }
+
+    #[test]
+    fn test_validate_group_level_with_group_false() {
+        let err = QueryParams::<String>::default()
+            .group(false)
+            .group_level(1)
+            .validate()
+            .unwrap_err();
+        assert!(err.to_string().contains("group=false"));
+    }
+
+    #[test]
+    fn test_validate_include_docs_with_reduce() {
+        let err = QueryParams::<String>::default()
+            .reduce(true)
+            .include_docs(true)
+            .validate()
+            .unwrap_err();
+        assert!(err.to_string().contains("include_docs"));
+    }
+
+    #[test]
+    fn test_validate_keys_with_key_range() {
+        for params in [
+            QueryParams::from_keys(vec![s!("a")]).key(s!("a")),
+            QueryParams::from_keys(vec![s!("a")]).start_key(s!("a")),
+            QueryParams::from_keys(vec![s!("a")]).end_key(s!("a")),
+        ] {
+            let err = params.validate().unwrap_err();
+            assert!(err.to_string().contains("`keys` can not be combined"));
+        }
+    }
 
     #[test]
     fn test_query_params_builder_paradigm() {
@@ -281,4 +465,32 @@ mod tests {
         let str_val = serde_json::to_string(&qp).expect("can not convert to string");
         assert!(str_val.contains(r#""update":"lazy""#));
     }
+
+    #[test]
+    fn test_query_params_update_serialization() {
+        let qp: QueryParams<String> = QueryParams::default().update(UpdateView::False);
+        assert_eq!(serde_json::to_string(&qp).unwrap(), r#"{"update":"false"}"#);
+        let qp: QueryParams<String> = QueryParams::default().update(UpdateView::True);
+        assert_eq!(serde_json::to_string(&qp).unwrap(), r#"{"update":"true"}"#);
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_query_params_stale_serialization() {
+        let qp: QueryParams<String> = QueryParams {
+            stale: Some("ok".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(serde_json::to_string(&qp).unwrap(), r#"{"stale":"ok"}"#);
+    }
+
+    #[test]
+    fn test_query_params_extra_param_serialization() {
+        let qp: QueryParams<String> = QueryParams::default().limit(10).extra_param("custom", "value");
+        assert_eq!(serde_json::to_string(&qp).unwrap(), r#"{"limit":10,"custom":"value"}"#);
+
+        let qp: QueryParams<String> = serde_json::from_str(r#"{"keys":[],"limit":10,"custom":"value"}"#).unwrap();
+        assert_eq!(qp.limit, Some(10));
+        assert_eq!(qp.extra.get("custom"), Some(&"value".to_string()));
+    }
 }
@@ -0,0 +1,248 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+/// Builder for Mango selectors, as used by `_find` queries and partial indexes. The selector syntax is
+/// documented here: [find-selectors](https://docs.couchdb.org/en/latest/api/database/find.html#find-selectors)
+///
+/// Usage:
+/// ```
+/// use couch_rs::types::find::FindQuery;
+/// use couch_rs::types::selector::Selector;
+/// use serde_json::json;
+///
+/// // documents with exactly 3 items, of which at least one is a red shirt
+/// let selector = Selector::field("items")
+///     .size(3)
+///     .and(Selector::field("items").elem_match(Selector::field("kind").eq("shirt").and(Selector::field("color").eq("red"))));
+/// let _query = FindQuery::new(selector.into());
+/// ```
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone)]
+#[serde(transparent)]
+pub struct Selector(Value);
+
+impl Selector {
+    /// Starts a condition on a field. Use dots to refer to nested fields, e.g. `address.city`.
+    #[must_use]
+    pub fn field(name: &str) -> FieldSelector {
+        FieldSelector { name: s!(name) }
+    }
+
+    /// Full text search, for databases with a text index: `{"$text": query}`
+    #[must_use]
+    pub fn text(query: &str) -> Selector {
+        Selector(json!({ "$text": query }))
+    }
+
+    /// Combines this selector with another one; documents have to match both (`$and`).
+    #[must_use]
+    pub fn and(self, other: Selector) -> Selector {
+        let mut conditions = match self.0 {
+            Value::Object(mut map) if map.len() == 1 && map.contains_key("$and") => match map.remove("$and") {
+                Some(Value::Array(conditions)) => conditions,
+                Some(other) => vec![other],
+                None => vec![],
+            },
+            value => vec![value],
+        };
+        conditions.push(other.0);
+        Selector(json!({ "$and": conditions }))
+    }
+
+    #[must_use]
+    pub fn as_value(&self) -> &Value {
+        &self.0
+    }
+}
+
+impl From<Selector> for Value {
+    fn from(selector: Selector) -> Self {
+        selector.0
+    }
+}
+
+/// Any json value can be used as a selector, e.g. an operator like `{"$eq": "rust"}` for `$elemMatch`
+/// on an array of strings.
+impl From<Value> for Selector {
+    fn from(value: Value) -> Self {
+        Selector(value)
+    }
+}
+
+/// A field of a [`Selector`], waiting for its condition
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct FieldSelector {
+    name: String,
+}
+
+impl FieldSelector {
+    fn condition(self, operator: &str, argument: Value) -> Selector {
+        let mut condition = Map::new();
+        condition.insert(s!(operator), argument);
+        let mut selector = Map::new();
+        selector.insert(self.name, Value::Object(condition));
+        Selector(Value::Object(selector))
+    }
+
+    /// The field is equal to the value (`$eq`)
+    #[must_use]
+    pub fn eq(self, value: impl Into<Value>) -> Selector {
+        self.condition("$eq", value.into())
+    }
+
+    /// The field is not equal to the value (`$ne`)
+    #[must_use]
+    pub fn ne(self, value: impl Into<Value>) -> Selector {
+        self.condition("$ne", value.into())
+    }
+
+    /// The field is greater than the value (`$gt`)
+    #[must_use]
+    pub fn gt(self, value: impl Into<Value>) -> Selector {
+        self.condition("$gt", value.into())
+    }
+
+    /// The field is greater than or equal to the value (`$gte`)
+    #[must_use]
+    pub fn gte(self, value: impl Into<Value>) -> Selector {
+        self.condition("$gte", value.into())
+    }
+
+    /// The field is less than the value (`$lt`)
+    #[must_use]
+    pub fn lt(self, value: impl Into<Value>) -> Selector {
+        self.condition("$lt", value.into())
+    }
+
+    /// The field is less than or equal to the value (`$lte`)
+    #[must_use]
+    pub fn lte(self, value: impl Into<Value>) -> Selector {
+        self.condition("$lte", value.into())
+    }
+
+    /// The string field starts with the prefix. Expands to a range (`$gte` the prefix and `$lt` the prefix
+    /// followed by `\u{fff0}`), so unlike a `$regex` it can use a regular json index on the field.
+    #[must_use]
+    pub fn begins_with(self, prefix: &str) -> Selector {
+        let mut condition = Map::new();
+        condition.insert(s!("$gte"), Value::from(prefix));
+        condition.insert(s!("$lt"), Value::from(format!("{prefix}\u{fff0}")));
+        let mut selector = Map::new();
+        selector.insert(self.name, Value::Object(condition));
+        Selector(Value::Object(selector))
+    }
+
+    /// The array field contains at least one element matching the selector (`$elemMatch`)
+    #[must_use]
+    pub fn elem_match(self, selector: impl Into<Selector>) -> Selector {
+        self.condition("$elemMatch", selector.into().0)
+    }
+
+    /// All elements of the array field match the selector (`$allMatch`)
+    #[must_use]
+    pub fn all_match(self, selector: impl Into<Selector>) -> Selector {
+        self.condition("$allMatch", selector.into().0)
+    }
+
+    /// The map field contains at least one key matching the selector (`$keyMapMatch`)
+    #[must_use]
+    pub fn key_map_match(self, selector: impl Into<Selector>) -> Selector {
+        self.condition("$keyMapMatch", selector.into().0)
+    }
+
+    /// The array field contains all of the values (`$all`)
+    #[must_use]
+    pub fn all(self, values: Vec<Value>) -> Selector {
+        self.condition("$all", Value::Array(values))
+    }
+
+    /// The array field has exactly `size` elements (`$size`)
+    #[must_use]
+    pub fn size(self, size: u64) -> Selector {
+        self.condition("$size", Value::from(size))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::find::FindQuery;
+
+    #[test]
+    fn test_elem_match() {
+        let selector = Selector::field("tags").elem_match(json!({"$eq": "rust"}));
+        assert_eq!(Value::from(selector), json!({"tags": {"$elemMatch": {"$eq": "rust"}}}));
+
+        let selector = Selector::field("genre").elem_match(Selector::field("name").eq("Comedy"));
+        assert_eq!(
+            Value::from(selector),
+            json!({"genre": {"$elemMatch": {"name": {"$eq": "Comedy"}}}})
+        );
+    }
+
+    #[test]
+    fn test_all_match_and_key_map_match() {
+        let selector = Selector::field("genre").all_match(json!({"$eq": "Comedy"}));
+        assert_eq!(
+            Value::from(selector),
+            json!({"genre": {"$allMatch": {"$eq": "Comedy"}}})
+        );
+
+        let selector = Selector::field("cameras").key_map_match(json!({"$eq": "secondary"}));
+        assert_eq!(
+            Value::from(selector),
+            json!({"cameras": {"$keyMapMatch": {"$eq": "secondary"}}})
+        );
+    }
+
+    #[test]
+    fn test_all_and_size() {
+        let selector = Selector::field("genre").all(vec![json!("Comedy"), json!("Short")]);
+        assert_eq!(Value::from(selector), json!({"genre": {"$all": ["Comedy", "Short"]}}));
+
+        let selector = Selector::field("items").size(3);
+        assert_eq!(Value::from(selector), json!({"items": {"$size": 3}}));
+    }
+
+    #[test]
+    fn test_begins_with() {
+        let selector = Selector::field("_id").begins_with("car:");
+        assert_eq!(
+            Value::from(selector),
+            json!({"_id": {"$gte": "car:", "$lt": "car:\u{fff0}"}})
+        );
+    }
+
+    #[test]
+    fn test_text() {
+        assert_eq!(Value::from(Selector::text("Bond")), json!({"$text": "Bond"}));
+    }
+
+    #[test]
+    fn test_and_flattens() {
+        let selector = Selector::field("items")
+            .size(3)
+            .and(Selector::field("year").gte(2000))
+            .and(Selector::field("year").lt(2010));
+        assert_eq!(
+            Value::from(selector),
+            json!({"$and": [
+                {"items": {"$size": 3}},
+                {"year": {"$gte": 2000}},
+                {"year": {"$lt": 2010}}
+            ]})
+        );
+    }
+
+    #[test]
+    fn test_selector_in_find_query() {
+        let query = FindQuery::new(
+            Selector::field("items")
+                .elem_match(Selector::field("price").gt(10))
+                .into(),
+        );
+        assert_eq!(
+            query.as_value()["selector"],
+            json!({"items": {"$elemMatch": {"price": {"$gt": 10}}}})
+        );
+    }
+}
@@ -1,4 +1,8 @@
-use serde::{Deserialize, Serialize};
+use crate::error::{CouchError, CouchResult};
+use http::StatusCode;
+use serde::{de, Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
 
 /// Couch vendor abstraction
 #[derive(Serialize, Deserialize, Debug)]
@@ -43,23 +47,177 @@ pub struct SizeInfo {
 }
 
 /// Database information
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default)]
 pub struct DbProperties {
     partitioned: Option<bool>,
 }
 
-/// Database information
+/// Database information. The fields that not every version or setup of `CouchDB` returns are optional
+/// or default to empty: `cluster` is only returned by a clustered `CouchDB`, `sizes` and `props` by
+/// newer versions.
 #[derive(Serialize, Deserialize, Debug)]
 pub struct DbInfo {
-    pub cluster: ClusterInfo,
+    pub cluster: Option<ClusterInfo>,
     pub compact_running: bool,
     pub db_name: String,
     pub disk_format_version: u32,
     pub doc_count: u64,
     pub doc_del_count: u64,
+    #[serde(default)]
     pub instance_start_time: String,
+    /// A number on a single node `CouchDB`, and an opaque string on a cluster
+    #[serde(deserialize_with = "seq_to_string")]
     pub purge_seq: String,
-    pub sizes: SizeInfo,
+    pub sizes: Option<SizeInfo>,
+    /// A number on a single node `CouchDB`, and an opaque string on a cluster
+    #[serde(deserialize_with = "seq_to_string")]
     pub update_seq: String,
+    #[serde(default)]
     pub props: DbProperties,
 }
+
+fn seq_to_string<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    match Value::deserialize(deserializer)? {
+        Value::String(seq) => Ok(seq),
+        Value::Number(seq) => Ok(seq.to_string()),
+        other => Err(de::Error::invalid_type(
+            de::Unexpected::Other(&other.to_string()),
+            &"a string or a number",
+        )),
+    }
+}
+
+/// A shard of a database: the range of document id hashes it holds, and the nodes that hold a copy.
+/// Every document is in exactly one shard range, so the ranges partition a database.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ShardRange {
+    /// The range as named by `CouchDB`, like `00000000-7fffffff`
+    pub range: String,
+    pub begin: u32,
+    pub end: u32,
+    pub nodes: Vec<String>,
+}
+
+impl ShardRange {
+    pub(crate) fn parse(range: String, nodes: Vec<String>) -> CouchResult<ShardRange> {
+        let parsed = range
+            .split_once('-')
+            .and_then(|(begin, end)| Some((u32::from_str_radix(begin, 16).ok()?, u32::from_str_radix(end, 16).ok()?)));
+        let Some((begin, end)) = parsed else {
+            return Err(CouchError::new(
+                format!("invalid shard range: {range}"),
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ));
+        };
+        Ok(ShardRange {
+            range,
+            begin,
+            end,
+            nodes,
+        })
+    }
+}
+
+/// Response of `_shards`
+#[derive(Deserialize, Debug)]
+pub(crate) struct Shards {
+    pub shards: HashMap<String, Vec<String>>,
+}
+
+/// Response of `_purged_infos`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PurgedInfos {
+    /// Sequence of the last purge
+    pub purge_seq: Value,
+    pub purged_infos: Vec<PurgedInfo>,
+}
+
+/// The revisions that were purged from a document
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PurgedInfo {
+    pub id: String,
+    pub revs: Vec<String>,
+}
+
+/// Single entry of a `_dbs_info` response; `info` is absent when the database does not exist
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DbInfoResult {
+    pub key: String,
+    pub info: Option<DbInfo>,
+    pub error: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_clustered_db_info() {
+        let info: DbInfo = serde_json::from_value(json!({
+            "cluster": {"n": 3, "q": 8, "r": 2, "w": 2},
+            "compact_running": false,
+            "db_name": "receipts",
+            "disk_format_version": 8,
+            "doc_count": 6146,
+            "doc_del_count": 64637,
+            "instance_start_time": "0",
+            "props": {"partitioned": true},
+            "purge_seq": "0-g1AAAAFDeJzLYWBg4M",
+            "sizes": {"active": 65031503, "external": 66982448, "file": 137433211},
+            "update_seq": "292786-g1AAAAF7eJzLYWBg4M"
+        }))
+        .unwrap();
+        assert_eq!(info.cluster.unwrap().q, 8);
+        assert_eq!(info.purge_seq, "0-g1AAAAFDeJzLYWBg4M");
+        assert_eq!(info.update_seq, "292786-g1AAAAF7eJzLYWBg4M");
+        assert_eq!(info.sizes.unwrap().file, 137_433_211);
+        assert_eq!(info.props.partitioned, Some(true));
+    }
+
+    #[test]
+    fn test_single_node_db_info() {
+        let info: DbInfo = serde_json::from_value(json!({
+            "compact_running": false,
+            "db_name": "receipts",
+            "disk_format_version": 6,
+            "disk_size": 8282,
+            "data_size": 1196,
+            "doc_count": 2,
+            "doc_del_count": 0,
+            "instance_start_time": "1489592425917393",
+            "purge_seq": 0,
+            "update_seq": 5
+        }))
+        .unwrap();
+        assert!(info.cluster.is_none());
+        assert!(info.sizes.is_none());
+        assert_eq!(info.props.partitioned, None);
+        assert_eq!(info.purge_seq, "0");
+        assert_eq!(info.update_seq, "5");
+        assert_eq!(info.doc_count, 2);
+    }
+
+    #[test]
+    fn test_parse_shard_range() {
+        let shard = ShardRange::parse(s!("80000000-ffffffff"), vec![s!("couchdb@127.0.0.1")]).unwrap();
+        assert_eq!(shard.begin, 0x8000_0000);
+        assert_eq!(shard.end, u32::MAX);
+        assert!(ShardRange::parse(s!("80000000"), vec![]).is_err());
+        assert!(ShardRange::parse(s!("8000000g-ffffffff"), vec![]).is_err());
+    }
+
+    #[test]
+    fn test_db_info_with_invalid_seq() {
+        let info = serde_json::from_value::<DbInfo>(json!({
+            "compact_running": false,
+            "db_name": "receipts",
+            "disk_format_version": 8,
+            "doc_count": 0,
+            "doc_del_count": 0,
+            "purge_seq": null,
+            "update_seq": "1-a"
+        }));
+        assert!(info.is_err());
+    }
+}
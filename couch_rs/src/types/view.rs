@@ -1,7 +1,7 @@
 use crate::document::TypedCouchDocument;
-use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize, Serializer};
 use serde_json::Value;
-use std::collections::HashMap;
+use std::{collections::HashMap, fmt};
 
 #[derive(Default, Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
 #[serde(bound(deserialize = "T: TypedCouchDocument"))]
@@ -41,13 +41,54 @@ pub struct CouchViews {
 ///     reduce: None,
 /// };
 /// ```
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct CouchFunc {
     pub map: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub reduce: Option<String>,
 }
 
+/// Reduce function of a view; either one of the `CouchDB` [built-in reduce functions](https://docs.couchdb.org/en/stable/ddocs/ddocs.html#reducefun-builtin),
+/// or a custom function.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum Reduce {
+    /// `_count`
+    Count,
+    /// `_sum`
+    Sum,
+    /// `_stats`
+    Stats,
+    /// `_approx_count_distinct`
+    ApproxCountDistinct,
+    /// A custom reduce function
+    Custom(String),
+}
+
+impl Reduce {
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        match self {
+            Reduce::Count => "_count",
+            Reduce::Sum => "_sum",
+            Reduce::Stats => "_stats",
+            Reduce::ApproxCountDistinct => "_approx_count_distinct",
+            Reduce::Custom(func) => func,
+        }
+    }
+}
+
+impl fmt::Display for Reduce {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl Serialize for Reduce {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
 #[derive(serde::Serialize)]
 pub struct CouchUpdate {
     updates: HashMap<String, String>,
@@ -61,9 +102,50 @@ impl CouchViews {
         couch_views
     }
 
+    /// Creates views written in another query server language than javascript, e.g. "erlang" or "query".
+    #[must_use]
+    pub fn with_language(view_name: &str, func: CouchFunc, language: &str) -> Self {
+        let mut couch_views = CouchViews::new(view_name, func);
+        couch_views.set_language(language);
+        couch_views
+    }
+
+    /// Adds a view; allows creating multiple views in a single design document by chaining calls.
+    ///
+    /// ```
+    /// use couch_rs::error::CouchResult;
+    /// use couch_rs::types::view::{CouchFunc, CouchViews, RawViewCollection};
+    /// use serde_json::Value;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> CouchResult<()> {
+    ///     let client = couch_rs::Client::new_local_test()?;
+    ///     let db = client.db("view_db").await?;
+    ///
+    ///     let views = CouchViews::default()
+    ///         .view("by_first_name", CouchFunc::new("function (doc) { emit(doc.first_name); }", None))
+    ///         .view("by_last_name", CouchFunc::new("function (doc) { emit(doc.last_name); }", None));
+    ///     db.create_view("names", views).await?;
+    ///
+    ///     let _: RawViewCollection<String, Value> = db.query("names", "by_first_name", None).await?;
+    ///     let _: RawViewCollection<String, Value> = db.query("names", "by_last_name", None).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    #[must_use]
+    pub fn view(mut self, name: &str, func: CouchFunc) -> Self {
+        self.add(name, func);
+        self
+    }
+
     pub fn add(&mut self, name: &str, func: CouchFunc) {
         self.views.insert(name.to_string(), func);
     }
+
+    /// Sets the query server language of the views; defaults to "javascript".
+    pub fn set_language(&mut self, language: &str) {
+        self.language = language.to_string();
+    }
 }
 
 impl Default for CouchViews {
@@ -83,6 +165,15 @@ impl CouchFunc {
             reduce: reduce.map(std::string::ToString::to_string),
         }
     }
+
+    /// Creates a view function that uses the given (built-in) reduce function.
+    #[must_use]
+    pub fn with_builtin_reduce(map: &str, reduce: Reduce) -> Self {
+        CouchFunc {
+            map: map.to_string(),
+            reduce: Some(reduce.to_string()),
+        }
+    }
 }
 
 impl From<CouchViews> for serde_json::Value {
@@ -117,3 +208,64 @@ impl From<CouchUpdate> for serde_json::Value {
         serde_json::to_value(u).unwrap()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_views_default_to_javascript() {
+        let views = CouchViews::new("by_name", CouchFunc::new("function (doc) { emit(doc.name); }", None));
+        let value: Value = views.into();
+        assert_eq!(value["language"], json!("javascript"));
+    }
+
+    #[test]
+    fn test_views_with_language() {
+        let func = CouchFunc::new(r#"{"fields": {"name": "asc"}}"#, None);
+        let value: Value = CouchViews::with_language("by_name", func, "query").into();
+        assert_eq!(value["language"], json!("query"));
+
+        let mut views = CouchViews::default();
+        views.set_language("erlang");
+        let value: Value = views.into();
+        assert_eq!(value["language"], json!("erlang"));
+    }
+
+    #[test]
+    fn test_builtin_reduce() {
+        let map = "function (doc) { emit(doc.name, 1); }";
+        for (reduce, expected) in [
+            (Reduce::Count, "_count"),
+            (Reduce::Sum, "_sum"),
+            (Reduce::Stats, "_stats"),
+            (Reduce::ApproxCountDistinct, "_approx_count_distinct"),
+        ] {
+            assert_eq!(serde_json::to_value(&reduce).unwrap(), json!(expected));
+            let value: Value = CouchFunc::with_builtin_reduce(map, reduce).into();
+            assert_eq!(value, json!({"map": map, "reduce": expected}));
+        }
+    }
+
+    #[test]
+    fn test_custom_reduce() {
+        let reduce = Reduce::Custom(s!("function (keys, values) { return sum(values); }"));
+        assert_eq!(reduce.to_string(), "function (keys, values) { return sum(values); }");
+        let value: Value = CouchFunc::with_builtin_reduce("function (doc) { emit(doc.name, 1); }", reduce).into();
+        assert_eq!(
+            value["reduce"],
+            json!("function (keys, values) { return sum(values); }")
+        );
+    }
+
+    #[test]
+    fn test_views_builder() {
+        let value: Value = CouchViews::default()
+            .view("a", CouchFunc::new("function (doc) { emit(doc.a); }", None))
+            .view("b", CouchFunc::new("function (doc) { emit(doc.b); }", None))
+            .into();
+        assert_eq!(value["views"]["a"]["map"], json!("function (doc) { emit(doc.a); }"));
+        assert_eq!(value["views"]["b"]["map"], json!("function (doc) { emit(doc.b); }"));
+    }
+}